@@ -244,7 +244,7 @@ export class NodeManager {
 
     updateEdges(edges) {
         console.log(`Updating edges: ${edges.length}`);
-        const existingEdgeKeys = new Set(edges.map(edge => `${edge.source}-${edge.target_node}`));
+        const existingEdgeKeys = new Set(edges.map(edge => `${edge.source}-${edge.target_node}-${edge.kind || 'wikilink'}`));
 
         // Remove non-existent edges
         this.edgeMeshes.forEach((line, edgeKey) => {
@@ -261,7 +261,8 @@ export class NodeManager {
                 return;
             }
 
-            const edgeKey = `${edge.source}-${edge.target_node}`;
+            // Parallel edges of different kinds are kept apart
+            const edgeKey = `${edge.source}-${edge.target_node}-${edge.kind || 'wikilink'}`;
             let line = this.edgeMeshes.get(edgeKey);
             const sourceMesh = this.nodeMeshes.get(edge.source);
             const targetMesh = this.nodeMeshes.get(edge.target_node);
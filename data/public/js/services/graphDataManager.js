@@ -151,6 +151,9 @@ export class GraphDataManager {
                     source: e.source,
                     target: e.target_node,
                     weight: e.weight,
+                    kind: e.kind || 'wikilink',
+                    directed: e.directed !== false,
+                    attributes: e.attributes || {},
                     hyperlinks: e.hyperlinks
                 })),
                 metadata: newData.metadata || {}
@@ -235,4 +238,23 @@ export class GraphDataManager {
             console.error('Cannot recalculate layout: Invalid graph data');
         }
     }
+
+    /**
+     * Expands a page node into its outline blocks, or collapses it back.
+     * The server broadcasts the resulting graph update to every client.
+     * @param {string} pageId - The id of the page node.
+     * @param {boolean} expanded - Whether the page should show its blocks.
+     */
+    async setPageExpanded(pageId, expanded) {
+        const action = expanded ? 'expand' : 'collapse';
+        try {
+            const response = await fetch(`/api/graph/${action}/${encodeURIComponent(pageId)}`, { method: 'POST' });
+            const result = await response.json();
+            if (result.status !== 'success') {
+                console.error(`Failed to ${action} page ${pageId}:`, result.message);
+            }
+        } catch (error) {
+            console.error(`Failed to ${action} page ${pageId}:`, error);
+        }
+    }
 }
@@ -31,6 +31,15 @@ export default class WebsocketService {
         this.audioContext = new (window.AudioContext || window.webkitAudioContext)();
         this.audioQueue = [];
         this.isPlaying = false;
+
+        // Protocol handshake; ids let responses be matched to their requests
+        this.protocolVersion = 1;
+        this.protocol = null;
+        this.nextRequestId = 1;
+        // Position frames received since the hello reply, acknowledged for flow control
+        this.framesReceived = 0;
+        // Compressed frames are inflated asynchronously; this keeps messages in order
+        this.inbox = Promise.resolve();
         
         // Force-directed parameters
         this.forceDirectedParams = {
@@ -45,7 +54,10 @@ export default class WebsocketService {
     getWebSocketUrl() {
         const host = window.location.hostname;
         const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-        const url = `${protocol}//${host}/ws`;
+        // Join the room named in the page URL, e.g. /?room=team-vault
+        const room = new URLSearchParams(window.location.search).get('room');
+        const query = room ? `?room=${encodeURIComponent(room)}` : '';
+        const url = `${protocol}//${host}/ws${query}`;
         console.log('Generated WebSocket URL:', url);
         console.log('Current page protocol:', window.location.protocol);
         console.log('Current page hostname:', host);
@@ -72,7 +84,14 @@ export default class WebsocketService {
                     statusElement.className = 'connected';
                 }
                 
-                // Request initial graph data and settings
+                // Negotiate the protocol, then request initial graph data and settings
+                this.send({
+                    type: 'hello',
+                    version: this.protocolVersion,
+                    compression: typeof DecompressionStream !== 'undefined' ? ['deflate'] : [],
+                    binary_formats: ['position-velocity-f32'],
+                    flow_control: true
+                });
                 console.log('Requesting initial data');
                 this.send({ type: 'getInitialData' });
             };
@@ -104,11 +123,31 @@ export default class WebsocketService {
         }
     }
 
-    handleMessage = async (event) => {
+    handleMessage = (event) => {
+        this.inbox = this.inbox.then(() => this.processMessage(event));
+    }
+
+    /**
+     * Inflates a compressed frame: raw deflate after a 4-byte `COMP` (JSON) or `COMB` (binary) header.
+     */
+    async inflate(buffer) {
+        const stream = new Blob([buffer.slice(4)]).stream().pipeThrough(new DecompressionStream('deflate-raw'));
+        return new Response(stream).arrayBuffer();
+    }
+
+    async processMessage(event) {
         try {
             if (event.data instanceof ArrayBuffer) {
+                const header = new TextDecoder().decode(new Uint8Array(event.data, 0, Math.min(4, event.data.byteLength)));
+                if (header === 'COMP') {
+                    const text = new TextDecoder().decode(await this.inflate(event.data));
+                    this.handleServerMessage(JSON.parse(text));
+                    return;
+                }
+
                 // Handle binary position updates
-                const positions = new Float32Array(event.data);
+                const buffer = header === 'COMB' ? await this.inflate(event.data) : event.data;
+                const positions = new Float32Array(buffer);
                 const positionArray = [];
                 
                 // Each position update contains 6 float values (x,y,z, vx,vy,vz)
@@ -130,6 +169,7 @@ export default class WebsocketService {
                 window.dispatchEvent(new CustomEvent('nodePositionsUpdated', {
                     detail: positionArray
                 }));
+                this.acknowledgeFrame();
                 return;
             }
 
@@ -159,6 +199,10 @@ export default class WebsocketService {
         }
     }
 
+    /**
+     * Sends a message. JSON messages get a request id, returned so callers can match
+     * the responses, which carry it back as `id`.
+     */
     send(data) {
         if (this.socket && this.socket.readyState === WebSocket.OPEN) {
             if (data instanceof ArrayBuffer) {
@@ -166,8 +210,10 @@ export default class WebsocketService {
                 this.socket.send(data);
             } else {
                 // Send JSON data
-                console.log('Sending WebSocket message:', data);
-                this.socket.send(JSON.stringify(data));
+                const message = { id: this.nextRequestId++, ...data };
+                console.log('Sending WebSocket message:', message);
+                this.socket.send(JSON.stringify(message));
+                return message.id;
             }
         } else {
             console.warn('WebSocket is not open. Unable to send message:', data);
@@ -216,6 +262,21 @@ export default class WebsocketService {
         
         // Then handle specific message types
         switch (data.type) {
+            case 'hello':
+                this.protocol = {
+                    version: data.version,
+                    compression: data.compression,
+                    binaryFormat: data.binary_format,
+                    flowControl: data.flow_control
+                };
+                this.framesReceived = 0;
+                this.emit('hello', this.protocol);
+                break;
+
+            case 'completed':
+                this.emit('completed', { request: data.request, id: data.id });
+                break;
+
             case 'getInitialData':
                 console.log('Received initial data:', data);
                 if (data.graph_data) {
@@ -292,6 +353,107 @@ export default class WebsocketService {
                     detail: settings
                 }));
                 break;
+
+            case 'searchResults':
+                this.emit('searchResults', { query: data.query, results: data.results });
+                break;
+
+            case 'queryResult':
+                // A filter result replaces the rendered graph; positions then stream for these nodes only
+                if (data.filter && data.graph) {
+                    this.emit('graphUpdate', { graphData: data.graph });
+                }
+                this.emit('queryResult', { query: data.query, nodeIds: data.node_ids, graph: data.graph });
+                break;
+
+            case 'viewSet':
+                // The visible subgraph follows as a graphUpdate
+                this.emit('viewSet', { filters: data.filters, nodeCount: data.node_count });
+                break;
+
+            case 'clusterView':
+                // The aggregated graph follows as a graphUpdate
+                this.emit('clusterView', {
+                    enabled: data.enabled,
+                    expanded: data.expanded,
+                    nodeCount: data.node_count
+                });
+                break;
+
+            case 'historyStart':
+                this.emit('historyStart', {
+                    from: data.from,
+                    to: data.to,
+                    commitCount: data.commit_count,
+                    graph: data.graph,
+                    nodes: data.nodes,
+                    edges: data.edges
+                });
+                break;
+
+            case 'historyFrame':
+                this.emit('historyFrame', {
+                    commit: data.commit,
+                    timestamp: data.timestamp,
+                    message: data.message,
+                    changes: data.changes
+                });
+                break;
+
+            case 'historyEnd':
+                // The live graph follows as a graphUpdate
+                this.emit('historyEnd', { completed: data.completed });
+                break;
+
+            case 'presenceRoster':
+                this.sessionId = data.session;
+                this.emit('presenceRoster', { session: data.session, peers: data.peers, locks: data.locks });
+                break;
+
+            case 'peerJoined':
+                this.emit('peerJoined', data);
+                break;
+
+            case 'peerLeft':
+                this.emit('peerLeft', { session: data.session });
+                break;
+
+            case 'presenceUpdate':
+                this.emit('presenceUpdate', { peers: data.peers });
+                break;
+
+            case 'nodeLocked':
+                // Also the answer to our own grabNode
+                this.emit('nodeLocked', { node: data.node, session: data.session });
+                break;
+
+            case 'nodeUnlocked':
+                this.emit('nodeUnlocked', { node: data.node });
+                break;
+
+            case 'nodesMoved':
+                this.emit('nodesMoved', { moves: data.moves });
+                break;
+
+            case 'tourStep':
+                // The step's view follows as a viewSet and graphUpdate
+                this.emit('tourStep', {
+                    tour: data.tour,
+                    index: data.index,
+                    stepCount: data.step_count,
+                    camera: data.step.camera,
+                    focusNode: data.step.focus_node,
+                    narration: data.step.narration
+                });
+                break;
+
+            case 'tourEnd':
+                this.emit('tourEnd', { tour: data.tour, completed: data.completed });
+                break;
+
+            case 'frameRate':
+                this.emit('frameRate', { fps: data.fps, targetFps: data.target_fps, dropped: data.dropped });
+                break;
                 
             default:
                 console.warn('Unhandled message type:', data.type);
@@ -299,6 +461,20 @@ export default class WebsocketService {
         }
     }
 
+    /**
+     * Tells the server a position frame was handled, so it sends the next one.
+     * Sent straight to the socket: this runs for every frame.
+     */
+    acknowledgeFrame() {
+        if (!this.protocol || !this.protocol.flowControl) {
+            return;
+        }
+        this.framesReceived += 1;
+        if (this.socket && this.socket.readyState === WebSocket.OPEN) {
+            this.socket.send(JSON.stringify({ type: 'framesReceived', count: this.framesReceived }));
+        }
+    }
+
     handleRagflowResponse(data) {
         console.log('Handling RAGFlow response:', data);
         this.emit('ragflowAnswer', data.answer);
@@ -427,4 +603,120 @@ export default class WebsocketService {
             radius: settings.radius || 100.0
         });
     }
+
+    search(query, limit = 20) {
+        this.send({
+            type: 'search',
+            query,
+            limit
+        });
+    }
+
+    /**
+     * Runs a graph query, e.g. `tag:rust AND degree>3 NEAR[2] "Project X"`.
+     * With `filter`, the view narrows to the result; an empty filter query restores the full graph.
+     */
+    query(query, { subgraph = false, filter = false } = {}) {
+        this.send({
+            type: 'query',
+            query,
+            subgraph,
+            filter
+        });
+    }
+
+    /**
+     * Narrows this session to the nodes matching every filter, e.g.
+     * `[{ kind: 'prefix', prefix: 'projects/' }, { kind: 'neighbourhood', node: 'Rust', hops: 2 }]`.
+     * Other kinds: `nodes` (ids), `metadata` (key, value) and `query` (query). No filters shows everything.
+     */
+    setView(filters = []) {
+        this.send({
+            type: 'setView',
+            filters
+        });
+    }
+
+    setClusterView(enabled) {
+        this.send({
+            type: 'setClusterView',
+            enabled
+        });
+    }
+
+    expandCluster(cluster) {
+        this.send({
+            type: 'expandCluster',
+            cluster
+        });
+    }
+
+    collapseCluster(cluster) {
+        this.send({
+            type: 'collapseCluster',
+            cluster
+        });
+    }
+
+    playHistory({ from = null, to = null, intervalMs = null } = {}) {
+        this.send({
+            type: 'playHistory',
+            from,
+            to,
+            interval_ms: intervalMs
+        });
+    }
+
+    stopHistory() {
+        this.send({ type: 'stopHistory' });
+    }
+
+    /**
+     * Publishes this participant's presence. Poses are `{ position: [x, y, z], orientation: [x, y, z, w] }`;
+     * leave out a hand that isn't tracked. Safe to call every frame, the server throttles the relay.
+     */
+    updatePresence({ name = null, head = null, leftHand = null, rightHand = null, selectedNode = null, hoveredNode = null } = {}) {
+        this.send({
+            type: 'presence',
+            name,
+            head,
+            left_hand: leftHand,
+            right_hand: rightHand,
+            selected_node: selectedNode,
+            hovered_node: hoveredNode
+        });
+    }
+
+    /**
+     * Takes a node for dragging. Answered with `nodeLocked`, or an error with code
+     * NODE_LOCKED when another participant holds it.
+     */
+    grabNode(node) {
+        return this.send({ type: 'grabNode', node });
+    }
+
+    moveNode(node, position) {
+        this.send({ type: 'moveNode', node, position });
+    }
+
+    releaseNode(node) {
+        return this.send({ type: 'releaseNode', node });
+    }
+
+    /** Presents a stored tour to everyone connected; see /api/tours for authoring. */
+    startTour(name) {
+        return this.send({ type: 'startTour', name });
+    }
+
+    nextTourStep() {
+        return this.send({ type: 'nextTourStep' });
+    }
+
+    previousTourStep() {
+        return this.send({ type: 'previousTourStep' });
+    }
+
+    stopTour() {
+        return this.send({ type: 'stopTour' });
+    }
 }
@@ -1,20 +1,29 @@
 use std::sync::Arc;
+use serde_json::Value;
 use tokio::sync::RwLock;
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error::Error as StdError;
 
 use crate::models::graph::GraphData;
 use crate::config::Settings;
+use crate::services::content_store::ContentStore;
+use crate::services::embedding_service::{self, Embedder, NeighbourCache};
 use crate::services::file_service::GitHubService;
+use crate::services::history_service::{self, GraphHistory};
+use crate::services::import_service::GraphSource;
+use crate::services::room_service::{Room, RoomRegistry, DEFAULT_ROOM};
 use crate::services::perplexity_service::PerplexityService;
 use crate::services::ragflow_service::RAGFlowService;
+use crate::services::search_service::SearchIndex;
 use crate::services::speech_service::SpeechService;
+use crate::services::tour_service::TourStore;
 use crate::services::github_service::GitHubPRService;
 use crate::utils::websocket_manager::WebSocketManager;
 use crate::utils::gpu_compute::GPUCompute;
 
 pub struct AppState {
     pub graph_data: Arc<RwLock<GraphData>>,
-    pub file_cache: Arc<RwLock<HashMap<String, String>>>,
+    pub content_store: Arc<ContentStore>,
     pub settings: Arc<RwLock<Settings>>,
     pub github_service: Arc<dyn GitHubService + Send + Sync>,
     pub perplexity_service: Arc<dyn PerplexityService + Send + Sync>,
@@ -24,13 +33,25 @@ pub struct AppState {
     pub gpu_compute: Option<Arc<RwLock<GPUCompute>>>,
     pub ragflow_conversation_id: String,
     pub github_pr_service: Arc<dyn GitHubPRService + Send + Sync>,
+    /// Pages currently expanded into their blocks
+    pub expanded_pages: Arc<RwLock<HashSet<String>>>,
+    pub search_index: Arc<RwLock<SearchIndex>>,
+    pub embedder: Arc<dyn Embedder>,
+    pub neighbours: NeighbourCache,
+    /// Topology changes replayed from the vault's commit history
+    pub history: Arc<RwLock<Arc<GraphHistory>>>,
+    /// Imported graph served instead of the vault, when set
+    pub graph_source: Arc<RwLock<Option<Arc<dyn GraphSource>>>>,
+    pub tours: Arc<TourStore>,
+    /// Open rooms; the default one shares the graph and simulation above
+    pub rooms: RoomRegistry,
 }
 
 impl AppState {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         graph_data: Arc<RwLock<GraphData>>,
-        file_cache: Arc<RwLock<HashMap<String, String>>>,
+        content_store: Arc<ContentStore>,
         settings: Arc<RwLock<Settings>>,
         github_service: Arc<dyn GitHubService + Send + Sync>,
         perplexity_service: Arc<dyn PerplexityService + Send + Sync>,
@@ -40,10 +61,21 @@ impl AppState {
         gpu_compute: Option<Arc<RwLock<GPUCompute>>>,
         ragflow_conversation_id: String,
         github_pr_service: Arc<dyn GitHubPRService + Send + Sync>,
+        embedder: Arc<dyn Embedder>,
+        tours: Arc<TourStore>,
     ) -> Self {
+        let graph_source = Arc::new(RwLock::new(None));
+        let default_room = Room {
+            graph_source: graph_source.clone(),
+            graph_data: graph_data.clone(),
+            gpu_compute: gpu_compute.clone(),
+            conversation_id: websocket_manager.conversation_id.clone(),
+            ..Room::new(DEFAULT_ROOM.to_string(), "Default".to_string(), Value::Null)
+        };
+
         Self {
             graph_data,
-            file_cache,
+            content_store,
             settings,
             github_service,
             perplexity_service,
@@ -53,6 +85,44 @@ impl AppState {
             gpu_compute,
             ragflow_conversation_id,
             github_pr_service,
+            expanded_pages: Arc::new(RwLock::new(HashSet::new())),
+            search_index: Arc::new(RwLock::new(SearchIndex::new())),
+            embedder,
+            neighbours: NeighbourCache::new(),
+            history: Arc::new(RwLock::new(Arc::new(GraphHistory::default()))),
+            graph_source,
+            tours,
+            rooms: RoomRegistry::new(default_room),
+        }
+    }
+
+    /// Re-index pages whose stored version changed since the last refresh
+    pub async fn refresh_search_index(&self) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let files = self.content_store.blocking(|store| store.all()).await?;
+        self.search_index.write().await.sync(&files);
+        Ok(())
+    }
+
+    /// Embed pages changed since the last refresh, when semantic edges are enabled
+    pub async fn refresh_embeddings(&self) -> Result<usize, Box<dyn StdError + Send + Sync>> {
+        if !self.settings.read().await.semantic.enabled {
+            return Ok(0);
         }
+        embedding_service::refresh_embeddings(&self.content_store, self.embedder.as_ref()).await
+    }
+
+    /// Re-read the commit history and replay it, returning the number of commits that
+    /// changed the graph. Stored pages that no commit touched are taken to predate it.
+    pub async fn refresh_history(&self) -> Result<usize, Box<dyn StdError + Send + Sync>> {
+        let source = history_service::history_source_from_settings(&*self.settings.read().await)?;
+        let revisions = source.revisions().await?;
+        let pages: Vec<String> = self.content_store.blocking(|store| store.all()).await?
+            .into_iter()
+            .map(|file| file.file_name.trim_end_matches(".md").to_string())
+            .collect();
+        let history = GraphHistory::from_revisions(revisions, &pages);
+        let frames = history.frames.len();
+        *self.history.write().await = Arc::new(history);
+        Ok(frames)
     }
 }
@@ -1,5 +1,8 @@
 use config::{Config, ConfigError, Environment, File};
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use crate::models::reference::ReferenceKind;
+use std::collections::HashMap;
 use std::{env, fmt};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -13,6 +16,22 @@ pub struct Settings {
     pub visualization: VisualizationSettings,
     pub bloom: BloomSettings,
     pub fisheye: FisheyeSettings,
+    #[serde(default)]
+    pub publish_filter: PublishFilterSettings,
+    #[serde(default)]
+    pub references: ReferenceSettings,
+    #[serde(default)]
+    pub blocks: BlockGraphSettings,
+    #[serde(default)]
+    pub semantic: SemanticSettings,
+    #[serde(default)]
+    pub clusters: ClusterSettings,
+    #[serde(default)]
+    pub history: HistorySettings,
+    #[serde(default)]
+    pub import: ImportSettings,
+    #[serde(default)]
+    pub websocket: WebSocketSettings,
     pub prompt: String,
 }
 
@@ -87,7 +106,7 @@ pub struct BloomSettings {
     pub environment_bloom_threshold: f32,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct FisheyeSettings {
     pub fisheye_enabled: bool,
     pub fisheye_strength: f32,
@@ -97,6 +116,234 @@ pub struct FisheyeSettings {
     pub fisheye_focus_z: f32,
 }
 
+/// Rules deciding which vault pages are published into the graph.
+///
+/// Exclusions win over inclusions. A page is published when it matches any
+/// include rule; with no include rules configured every non-excluded page is published.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PublishFilterSettings {
+    /// Logseq page properties (`key:: value`) in the first block. An empty value list matches any value.
+    #[serde(default)]
+    pub page_properties: HashMap<String, Vec<String>>,
+    /// YAML front matter keys. An empty value list matches any value.
+    #[serde(default)]
+    pub front_matter: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub include_tags: Vec<String>,
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    /// Path globs (`*`, `**`, `?`) matched against file names in the vault directory;
+    /// only its top level is synced, since pages are keyed by file name
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+}
+
+impl Default for PublishFilterSettings {
+    /// Matches the historical behaviour: only pages marked `public:: true` are published.
+    fn default() -> Self {
+        Self {
+            page_properties: HashMap::from([("public".to_string(), vec!["true".to_string()])]),
+            front_matter: HashMap::new(),
+            include_tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+        }
+    }
+}
+
+/// Edge weight contributed by each occurrence of a reference type.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReferenceSettings {
+    pub link_weight: f32,
+    pub tag_weight: f32,
+    pub embed_weight: f32,
+    pub block_ref_weight: f32,
+    pub mention_weight: f32,
+}
+
+impl Default for ReferenceSettings {
+    fn default() -> Self {
+        Self {
+            link_weight: 1.0,
+            tag_weight: 0.5,
+            embed_weight: 1.5,
+            block_ref_weight: 1.0,
+            mention_weight: 0.25,
+        }
+    }
+}
+
+/// Block-level graph: pages can be expanded into their outline blocks.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockGraphSettings {
+    pub enabled: bool,
+    /// Blocks beyond this count are left folded into the page node
+    pub max_blocks_per_page: usize,
+    /// Weight of the edge from a page or block to each block it contains
+    pub containment_weight: f32,
+}
+
+impl Default for BlockGraphSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_blocks_per_page: 200,
+            containment_weight: 2.0,
+        }
+    }
+}
+
+/// Semantic similarity edges between pages with similar content.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SemanticSettings {
+    pub enabled: bool,
+    /// `local` for the built-in hashed n-gram model, `remote` for an embeddings API
+    pub provider: String,
+    /// Vector length of the local model
+    pub dimensions: usize,
+    /// OpenAI compatible embeddings endpoint, authenticated with the OpenAI key
+    pub api_url: String,
+    pub model: String,
+    /// Neighbours linked per page
+    pub top_k: usize,
+    /// Minimum cosine similarity for an edge
+    pub threshold: f32,
+    /// Edge weight is `weight * similarity`
+    pub weight: f32,
+}
+
+impl Default for SemanticSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: "local".to_string(),
+            dimensions: 256,
+            api_url: "https://api.openai.com/v1/embeddings".to_string(),
+            model: "text-embedding-3-small".to_string(),
+            top_k: 5,
+            threshold: 0.35,
+            weight: 0.5,
+        }
+    }
+}
+
+/// Level-of-detail clustering for large graphs.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClusterSettings {
+    /// `community` for Louvain communities, `folder` for the page path hierarchy
+    pub strategy: String,
+    /// Communities larger than this are split into sub-communities
+    pub max_cluster_size: usize,
+    /// Distance between neighbouring nodes in cluster layouts
+    pub spacing: f32,
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            strategy: "community".to_string(),
+            max_cluster_size: 50,
+            spacing: 8.0,
+        }
+    }
+}
+
+/// Commit history of the vault, replayed into a temporal graph.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    /// `github` for the commits API of the configured repository, `local` for a git checkout
+    pub source: String,
+    /// Local checkout, used with `source = "local"`
+    pub repo_path: String,
+    /// Vault directory inside the checkout
+    pub vault_path: String,
+    /// Most recent commits ingested
+    pub max_commits: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: "github".to_string(),
+            repo_path: ".".to_string(),
+            vault_path: "data/markdown".to_string(),
+            max_commits: 500,
+        }
+    }
+}
+
+/// Tuning of the WebSocket link to clients.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebSocketSettings {
+    /// Frames at least this many bytes are compressed when the client negotiated compression
+    pub compression_threshold: usize,
+    /// How often changes to avatar poses, selections and dragged nodes are relayed to the other sessions
+    pub presence_interval_ms: u64,
+    /// Record every frame each session sends and receives, for replay through `/ws/replay/{id}`
+    pub record_sessions: bool,
+    pub recording_dir: String,
+    /// Position frames a client that acknowledges them may have in flight; newer frames wait, keeping only the latest
+    pub max_outstanding_frames: u64,
+    /// Bounds of the position frame rate, adapted per session to what the client keeps up with
+    pub min_fps: f32,
+    pub max_fps: f32,
+}
+
+impl Default for WebSocketSettings {
+    fn default() -> Self {
+        Self {
+            compression_threshold: 1024,
+            presence_interval_ms: 100,
+            record_sessions: false,
+            recording_dir: "data/recordings".to_string(),
+            max_outstanding_frames: 4,
+            min_fps: 5.0,
+            max_fps: 60.0,
+        }
+    }
+}
+
+/// A graph file visualised instead of the markdown vault.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportSettings {
+    /// GraphML, GEXF, CSV edge list or JSON Graph file loaded at startup; empty uses the vault
+    pub path: String,
+    /// `graphml`, `gexf`, `csv` or `jgf`; empty picks by file extension or content
+    pub format: String,
+    /// Node attribute used as the node label
+    pub label_attribute: String,
+    /// Edge attribute used as the edge weight
+    pub weight_attribute: String,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            format: String::new(),
+            label_attribute: "label".to_string(),
+            weight_attribute: "weight".to_string(),
+        }
+    }
+}
+
+impl ReferenceSettings {
+    pub fn weight(&self, kind: ReferenceKind) -> f32 {
+        match kind {
+            ReferenceKind::Link => self.link_weight,
+            ReferenceKind::Tag => self.tag_weight,
+            ReferenceKind::Embed => self.embed_weight,
+            ReferenceKind::BlockRef => self.block_ref_weight,
+            ReferenceKind::Mention => self.mention_weight,
+        }
+    }
+}
+
 impl Settings {
     pub fn new() -> Result<Self, ConfigError> {
         let mut builder = Config::builder()
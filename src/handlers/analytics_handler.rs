@@ -0,0 +1,103 @@
+use actix_web::{web, HttpResponse};
+use log::error;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::AppState;
+use crate::handlers::room_handler::{self, RoomParams};
+use crate::services::analytics_service;
+
+const DEFAULT_HOPS: usize = 1;
+
+#[derive(Deserialize)]
+pub struct PathQuery {
+    pub from: String,
+    pub to: String,
+    pub room: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NeighbourhoodQuery {
+    pub node: String,
+    pub k: Option<usize>,
+    pub room: Option<String>,
+}
+
+/// Centrality, communities and components of the graph in `?room=`: `/api/graph/analytics`
+pub async fn get_analytics(state: web::Data<AppState>, params: web::Query<RoomParams>) -> HttpResponse {
+    let room = match room_handler::find_room(&state, params.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let analytics = room.analytics.analyze(&room.graph_data).await;
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "analytics": &*analytics
+    }))
+}
+
+/// Write the analytics into node attributes and push the updated graph to the room's clients.
+pub async fn apply_analytics(state: web::Data<AppState>, params: web::Query<RoomParams>) -> HttpResponse {
+    let room = match room_handler::find_room(&state, params.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    // Only take the write lock once the metrics are ready, not while computing them
+    let analytics = room.analytics.analyze(&room.graph_data).await;
+    analytics.apply_to_nodes(&mut *room.graph_data.write().await);
+
+    if let Err(e) = state.websocket_manager.broadcast_graph_update(&room.id).await {
+        error!("Failed to broadcast graph update: {}", e);
+    }
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "version": analytics.version,
+        "nodes": analytics.nodes.len()
+    }))
+}
+
+/// Shortest path by hop count: `/api/graph/path?from=Rust&to=WebXR`
+pub async fn shortest_path(state: web::Data<AppState>, query: web::Query<PathQuery>) -> HttpResponse {
+    let room = match room_handler::find_room(&state, query.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let graph = room.graph_data.read().await;
+    match analytics_service::shortest_path(&graph, &query.from, &query.to) {
+        Some(path) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "hops": path.len() - 1,
+            "path": path
+        })),
+        None => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("No path from {} to {}", query.from, query.to)
+        })),
+    }
+}
+
+/// Nodes within `k` hops of a node: `/api/graph/neighbourhood?node=Rust&k=2`
+pub async fn neighbourhood(state: web::Data<AppState>, query: web::Query<NeighbourhoodQuery>) -> HttpResponse {
+    let room = match room_handler::find_room(&state, query.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let k = query.k.unwrap_or(DEFAULT_HOPS);
+    let graph = room.graph_data.read().await;
+    match analytics_service::k_hop_neighbourhood(&graph, &query.node, k) {
+        Some(nodes) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "node": query.node,
+            "k": k,
+            "nodes": nodes.into_iter()
+                .map(|(id, distance)| json!({ "id": id, "distance": distance }))
+                .collect::<Vec<_>>()
+        })),
+        None => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("Node not found: {}", query.node)
+        })),
+    }
+}
@@ -0,0 +1,41 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+
+use crate::AppState;
+use crate::handlers::room_handler;
+
+#[derive(Deserialize)]
+pub struct ClusterParams {
+    /// Comma separated ids of clusters to show expanded
+    #[serde(default)]
+    pub expanded: String,
+    pub room: Option<String>,
+}
+
+/// The graph aggregated into clusters, expanding only the listed ones:
+/// `/api/graph/clusters?expanded=cluster:root.0,cluster:root.0.2`
+pub async fn get_clusters(state: web::Data<AppState>, params: web::Query<ClusterParams>) -> HttpResponse {
+    let room = match room_handler::find_room(&state, params.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let expanded: HashSet<String> = params.expanded.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let cluster_settings = room.effective_settings(&*state.settings.read().await).clusters;
+    // A copy, so graph writers don't wait while the hierarchy is built
+    let graph = room.graph_data.read().await.clone();
+    let hierarchy = room.clusters.build(&graph, &cluster_settings).await;
+    let cut = hierarchy.cut(&graph, &expanded);
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "expanded": expanded,
+        "graph": cut
+    }))
+}
@@ -5,15 +5,16 @@ use std::collections::HashMap;
 use crate::AppState;
 use crate::services::file_service::FileService;
 use crate::services::graph_service::{GraphService, FileMetadata};
+use crate::services::room_service::DEFAULT_ROOM;
 
 pub async fn fetch_and_process_files(state: web::Data<AppState>) -> HttpResponse {
     info!("Initiating optimized file fetch and processing");
 
-    // Load or create metadata, which now ensures directories exist
-    let mut metadata_map = match FileService::load_or_create_metadata() {
+    // Start from what is already stored locally so only changed files are fetched
+    let mut metadata_map = match state.content_store.blocking(|store| store.metadata()).await {
         Ok(map) => map,
         Err(e) => {
-            error!("Failed to load or create metadata: {}", e);
+            error!("Failed to load metadata from content store: {}", e);
             return HttpResponse::InternalServerError().json(json!({
                 "status": "error",
                 "message": format!("Failed to initialize metadata: {}", e)
@@ -30,38 +31,34 @@ pub async fn fetch_and_process_files(state: web::Data<AppState>) -> HttpResponse
 
             info!("Successfully processed {} public markdown files", processed_files.len());
 
-            // Update file cache with processed files
-            {
-                let mut file_cache = state.file_cache.write().await;
-                for processed_file in &processed_files {
-                    // Only public files reach this point due to optimization
-                    metadata_map.insert(processed_file.file_name.clone(), processed_file.metadata.clone());
-                    file_cache.insert(processed_file.file_name.clone(), processed_file.content.clone());
-                    debug!("Updated file cache with: {}", processed_file.file_name);
-                }
-            }
-
-            // Save the updated metadata
-            if let Err(e) = FileService::save_metadata(&metadata_map) {
-                error!("Failed to save metadata: {}", e);
+            // Update the content store with processed files
+            let stored = state.content_store.blocking(move |store| {
+                FileService::store_processed_files(store, &processed_files, &metadata_map)
+            }).await;
+            if let Err(e) = stored {
+                error!("Failed to update content store: {}", e);
                 return HttpResponse::InternalServerError().json(json!({
                     "status": "error",
-                    "message": format!("Failed to save metadata: {}", e)
+                    "message": format!("Failed to update content store: {}", e)
                 }));
             }
 
+            if let Err(e) = state.refresh_search_index().await {
+                error!("Failed to update search index: {}", e);
+            }
+            if let Err(e) = state.refresh_embeddings().await {
+                error!("Failed to update page embeddings: {}", e);
+            }
+
             // Update graph with processed files
             match GraphService::build_graph(&state).await {
                 Ok(graph_data) => {
                     let mut graph = state.graph_data.write().await;
-                    *graph = graph_data.clone();
+                    *graph = graph_data;
                     info!("Graph data structure updated successfully");
 
                     // Broadcast graph update to connected clients
-                    let broadcast_result = state.websocket_manager.broadcast_message(&json!({
-                        "type": "graphUpdate",
-                        "data": graph_data,
-                    }).to_string()).await;
+                    let broadcast_result = state.websocket_manager.broadcast_graph_update(DEFAULT_ROOM).await;
 
                     if let Err(e) = broadcast_result {
                         error!("Failed to broadcast graph update: {}", e);
@@ -93,18 +90,48 @@ pub async fn fetch_and_process_files(state: web::Data<AppState>) -> HttpResponse
     }
 }
 
+/// Dry run of the publish filter: lists every vault file with the decision and reason.
+pub async fn preview_publish_filter(state: web::Data<AppState>) -> HttpResponse {
+    info!("Evaluating publish filter (dry run)");
+
+    match FileService::preview_publish_filter(&*state.github_service, state.settings.clone()).await {
+        Ok(decisions) => {
+            let published = decisions.iter().filter(|d| d.publish).count();
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "total": decisions.len(),
+                "published": published,
+                "files": decisions
+            }))
+        },
+        Err(e) => {
+            error!("Failed to evaluate publish filter: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("Failed to evaluate publish filter: {}", e)
+            }))
+        }
+    }
+}
+
 pub async fn get_file_content(state: web::Data<AppState>, file_name: web::Path<String>) -> HttpResponse {
-    let file_cache = state.file_cache.read().await;
-    
-    match file_cache.get(file_name.as_str()) {
-        Some(content) => HttpResponse::Ok().body(content.clone()),
-        None => {
-            error!("File not found in cache: {}", file_name);
+    let name = file_name.clone();
+    match state.content_store.blocking(move |store| store.get(&name)).await {
+        Ok(Some(file)) => HttpResponse::Ok().body(file.content),
+        Ok(None) => {
+            error!("File not found in content store: {}", file_name);
             HttpResponse::NotFound().json(json!({
                 "status": "error",
                 "message": format!("File not found: {}", file_name)
             }))
         }
+        Err(e) => {
+            error!("Failed to read {} from content store: {}", file_name, e);
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("Failed to read file: {}", e)
+            }))
+        }
     }
 }
 
@@ -114,13 +141,10 @@ pub async fn refresh_graph(state: web::Data<AppState>) -> HttpResponse {
     match GraphService::build_graph(&state).await {
         Ok(graph_data) => {
             let mut graph = state.graph_data.write().await;
-            *graph = graph_data.clone();
+            *graph = graph_data;
             info!("Graph data structure refreshed successfully");
 
-            let broadcast_result = state.websocket_manager.broadcast_message(&json!({
-                "type": "graphUpdate",
-                "data": graph_data,
-            }).to_string()).await;
+            let broadcast_result = state.websocket_manager.broadcast_graph_update(DEFAULT_ROOM).await;
 
             if let Err(e) = broadcast_result {
                 error!("Failed to broadcast graph update: {}", e);
@@ -144,9 +168,18 @@ pub async fn refresh_graph(state: web::Data<AppState>) -> HttpResponse {
 }
 
 pub async fn update_graph(state: web::Data<AppState>) -> Result<HttpResponse, ActixError> {
-    let metadata = state.file_cache.read().await;
-    
-    // Convert the file cache into the expected metadata format
+    let metadata = match state.content_store.blocking(|store| store.metadata()).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            error!("Failed to read content store: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("Failed to read content store: {}", e)
+            })));
+        }
+    };
+
+    // Convert the stored files into the expected metadata format
     let metadata_map: HashMap<String, FileMetadata> = metadata
         .iter()
         .map(|(key, _)| {
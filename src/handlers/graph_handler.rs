@@ -2,8 +2,14 @@
 
 use actix_web::{web, HttpResponse, Responder};
 use crate::AppState;
-use serde::Serialize;
-use log::info;
+use crate::handlers::room_handler;
+use crate::services::export_service::{self, ExportFormat};
+use crate::services::graph_service::GraphService;
+use crate::services::room_service::DEFAULT_ROOM;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use serde_json::json;
+use log::{error, info};
 
 /// Struct to serialize GraphData for HTTP responses.
 #[derive(Serialize)]
@@ -43,3 +49,125 @@ pub async fn get_graph_data(state: web::Data<AppState>) -> impl Responder {
     // Step 3: Respond with the serialized graph data.
     HttpResponse::Ok().json(response)
 }
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    pub format: String,
+    /// Include centrality, community and component metrics as node attributes
+    pub metrics: Option<bool>,
+    pub room: Option<String>,
+}
+
+/// Handler to download the graph with its layout for other tools,
+/// e.g. `/api/graph/export?format=gexf` for Gephi.
+pub async fn export_graph(state: web::Data<AppState>, params: web::Query<ExportParams>) -> impl Responder {
+    let Some(format) = ExportFormat::parse(&params.format) else {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("Unknown export format {}, expected one of: {}", params.format, ExportFormat::NAMES.join(", "))
+        }));
+    };
+
+    let room = match room_handler::find_room(&state, params.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let mut graph = room.graph_data.read().await.clone();
+    // The simulation keeps the live layout on the GPU; the graph only has the initial positions
+    if let Some(gpu_compute) = &room.gpu_compute {
+        match gpu_compute.read().await.get_node_positions().await {
+            Ok(positions) => {
+                for (node, position) in graph.nodes.iter_mut().zip(&positions) {
+                    node.update_from_gpu_node(position);
+                }
+            },
+            Err(e) => error!("Failed to read layout for export, using stored positions: {}", e),
+        }
+    }
+    if params.metrics.unwrap_or(true) {
+        room.analytics.analyze(&room.graph_data).await.apply_to_nodes(&mut graph);
+    }
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", format.file_name())))
+        .body(export_service::export(&graph, format))
+}
+
+/// Handler to list nodes whose attributes match every query parameter,
+/// e.g. `/api/graph/nodes?tags=rust&status=done`.
+pub async fn query_nodes(state: web::Data<AppState>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+    let graph = state.graph_data.read().await;
+    let nodes: Vec<_> = graph.nodes.iter()
+        .filter(|node| node.matches_attributes(query.iter()))
+        .cloned()
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "count": nodes.len(),
+        "nodes": nodes
+    }))
+}
+
+/// Handler to expand a page node into its outline blocks.
+pub async fn expand_page(state: web::Data<AppState>, page: web::Path<String>) -> impl Responder {
+    set_page_expanded(state, page.into_inner(), true).await
+}
+
+/// Handler to fold a page's blocks back into the page node.
+pub async fn collapse_page(state: web::Data<AppState>, page: web::Path<String>) -> impl Responder {
+    set_page_expanded(state, page.into_inner(), false).await
+}
+
+async fn set_page_expanded(state: web::Data<AppState>, page: String, expanded: bool) -> HttpResponse {
+    if !state.settings.read().await.blocks.enabled {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Block-level graph is disabled"
+        }));
+    }
+    if !state.graph_data.read().await.nodes.iter().any(|n| n.id == page) {
+        return HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("Page not found: {}", page)
+        }));
+    }
+
+    info!("{} page {}", if expanded { "Expanding" } else { "Collapsing" }, page);
+    {
+        let mut expanded_pages = state.expanded_pages.write().await;
+        if expanded {
+            expanded_pages.insert(page.clone());
+        } else {
+            expanded_pages.remove(&page);
+        }
+    }
+
+    match GraphService::build_graph(&state).await {
+        Ok(mut graph) => {
+            let mut graph_data = state.graph_data.write().await;
+            GraphService::carry_over_positions(&mut graph, &graph_data);
+            *graph_data = graph;
+
+            if let Err(e) = state.websocket_manager.broadcast_graph_update(DEFAULT_ROOM).await {
+                error!("Failed to broadcast graph update: {}", e);
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "page": page,
+                "expanded": expanded,
+                "nodes": graph_data.nodes.len(),
+                "edges": graph_data.edges.len()
+            }))
+        },
+        Err(e) => {
+            error!("Failed to rebuild graph: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("Failed to rebuild graph: {}", e)
+            }))
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::AppState;
+use crate::handlers::room_handler;
+use crate::services::history_service::GraphHistory;
+use crate::utils::page_properties::parse_date;
+
+#[derive(Deserialize)]
+pub struct TimeRangeParams {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub room: Option<String>,
+}
+
+/// Resolve an optional `from`/`to` pair against the history, defaulting to all of it.
+pub fn resolve_time_range(
+    history: &GraphHistory,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let parse = |value: Option<&str>, default: Option<DateTime<Utc>>| match value {
+        Some(value) => parse_date(value).ok_or_else(|| format!("Invalid date: {}", value)),
+        None => Ok(default.unwrap_or_else(Utc::now)),
+    };
+    let from = parse(from, history.first_timestamp())?;
+    let to = parse(to, history.last_timestamp())?;
+    if from > to {
+        return Err(format!("Time range starts after it ends: {} > {}", from, to));
+    }
+    Ok((from, to))
+}
+
+/// The graph as it existed over a time range, with the commits that changed it:
+/// `/api/graph/history?from=2024-01-01&to=2024-06-30`
+pub async fn get_history(state: web::Data<AppState>, params: web::Query<TimeRangeParams>) -> HttpResponse {
+    if let Err(response) = room_handler::find_vault_room(&state, params.room.as_deref()) {
+        return response;
+    }
+    let history = state.history.read().await.clone();
+    let (from, to) = match resolve_time_range(&history, params.from.as_deref(), params.to.as_deref()) {
+        Ok(range) => range,
+        Err(message) => return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": message
+        })),
+    };
+
+    let graph = state.graph_data.read().await;
+    let range_graph = history.time_range(from, to).to_graph(&graph);
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "from": from,
+        "to": to,
+        "commits": history.frames_between(from, to),
+        "graph": range_graph
+    }))
+}
+
+/// Re-read the commit history of the vault.
+pub async fn refresh_history(state: web::Data<AppState>) -> HttpResponse {
+    match state.refresh_history().await {
+        Ok(frames) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "commits": frames
+        })),
+        Err(e) => {
+            error!("Failed to load graph history: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("Failed to load graph history: {}", e)
+            }))
+        }
+    }
+}
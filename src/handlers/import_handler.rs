@@ -0,0 +1,98 @@
+use actix_web::{web, HttpResponse};
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::AppState;
+use crate::services::graph_service::GraphService;
+use crate::services::import_service::{self, AttributeMapping, GraphSource, ImportFormat, UploadedGraphSource};
+use crate::services::room_service::DEFAULT_ROOM;
+
+/// Largest graph file accepted by the upload endpoint
+pub const MAX_IMPORT_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    /// graphml, gexf, csv or jgf; detected from the content when omitted
+    pub format: Option<String>,
+    pub name: Option<String>,
+    /// Override the `[import]` attribute mapping for this upload
+    pub label_attribute: Option<String>,
+    pub weight_attribute: Option<String>,
+}
+
+/// Replace the vault graph with an uploaded graph file:
+/// `curl --data-binary @graph.gexf '/api/graph/import?format=gexf'`
+pub async fn import_graph(state: web::Data<AppState>, params: web::Query<ImportParams>, body: web::Bytes) -> HttpResponse {
+    let source = match parse_upload(&state, &params, &body).await {
+        Ok(source) => source,
+        Err(response) => return response,
+    };
+    *state.graph_source.write().await = Some(source);
+    rebuild(state).await
+}
+
+/// Parse an uploaded graph file into a source, or the response rejecting it
+pub async fn parse_upload(state: &AppState, params: &ImportParams, body: &[u8]) -> Result<Arc<dyn GraphSource>, HttpResponse> {
+    let text = std::str::from_utf8(body).map_err(|e| bad_request(format!("Graph file is not UTF-8: {}", e)))?;
+    let format = match params.format.as_deref() {
+        Some(name) => ImportFormat::parse(name).ok_or_else(|| bad_request(format!("Unknown import format: {}", name)))?,
+        None => ImportFormat::detect(text),
+    };
+
+    let mut mapping = AttributeMapping::from(&state.settings.read().await.import);
+    if let Some(label) = &params.label_attribute {
+        mapping.label = label.clone();
+    }
+    if let Some(weight) = &params.weight_attribute {
+        mapping.weight = weight.clone();
+    }
+
+    let graph = import_service::import(text, format, &mapping)
+        .map_err(|e| bad_request(format!("Failed to parse graph: {}", e)))?;
+    let name = params.name.clone().unwrap_or_else(|| "upload".to_string());
+    info!("Importing uploaded graph {} with {} nodes and {} edges", name, graph.nodes.len(), graph.edges.len());
+    Ok(Arc::new(UploadedGraphSource::new(name, graph)))
+}
+
+/// Stop serving the imported graph and go back to the vault.
+pub async fn clear_import(state: web::Data<AppState>) -> HttpResponse {
+    *state.graph_source.write().await = None;
+    rebuild(state).await
+}
+
+/// Rebuild the default room's graph from its source and send it to the room's sessions
+pub async fn rebuild(state: web::Data<AppState>) -> HttpResponse {
+    match GraphService::build_graph(&state).await {
+        Ok(graph) => {
+            let mut graph_data = state.graph_data.write().await;
+            *graph_data = graph;
+
+            if let Err(e) = state.websocket_manager.broadcast_graph_update(DEFAULT_ROOM).await {
+                error!("Failed to broadcast graph update: {}", e);
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "source": state.graph_source.read().await.as_ref().map(|source| source.name()),
+                "nodes": graph_data.nodes.len(),
+                "edges": graph_data.edges.len()
+            }))
+        },
+        Err(e) => {
+            error!("Failed to rebuild graph: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("Failed to rebuild graph: {}", e)
+            }))
+        }
+    }
+}
+
+pub fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "status": "error",
+        "message": message
+    }))
+}
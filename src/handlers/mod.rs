@@ -1,7 +1,17 @@
+pub mod analytics_handler;
+pub mod cluster_handler;
 pub mod file_handler;
 pub mod graph_handler;
+pub mod history_handler;
+pub mod import_handler;
 pub mod perplexity_handler;
+pub mod query_handler;
+pub mod recording_handler;
+pub mod room_handler;
 pub mod ragflow_handler;
+pub mod search_handler;
+pub mod session_handler;
+pub mod tour_handler;
 pub mod visualization_handler;
 pub mod websocket_handlers;
 
@@ -1,47 +1,55 @@
 use actix_web::{web, HttpResponse};
 use log::{info, error};
-use chrono::Utc;
 use std::collections::HashMap;
 use crate::app_state::AppState;
 use crate::services::perplexity_service::ApiClientImpl;
-use crate::services::file_service::ProcessedFile;
 use crate::models::metadata::Metadata;
+use crate::services::file_service::{FileService, ProcessedFile};
 
 pub async fn process_files(app_state: web::Data<AppState>) -> HttpResponse {
     info!("Starting Perplexity processing for all files");
     
     let settings = app_state.settings.read().await;
     let api_client = ApiClientImpl::new();
-    let file_cache = app_state.file_cache.read().await;
-    let graph_data = app_state.graph_data.read().await;
+    let stored_files = match app_state.content_store.blocking(|store| store.all()).await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Failed to read content store: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "message": format!("Failed to read content store: {}", e)
+            }));
+        }
+    };
     
     let mut processed_count = 0;
     let mut error_count = 0;
     let mut pr_urls = Vec::new();
 
-    for (file_name, content) in file_cache.iter() {
-        let metadata = graph_data.metadata.get(file_name).cloned().unwrap_or_else(|| {
-            error!("No metadata found for file: {}", file_name);
-            Metadata {
-                file_name: file_name.clone(),
-                last_modified: Utc::now(),
-                topic_counts: HashMap::new(),
-                ..Default::default()
-            }
-        });
+    for stored in stored_files {
+        let file_name = &stored.file_name;
+        let metadata = stored.metadata;
 
         let processed_file = ProcessedFile {
             file_name: file_name.clone(),
-            content: content.clone(),
+            content: stored.content,
             is_public: true,
             metadata: metadata.clone(),
         };
 
         match app_state.perplexity_service.process_file(processed_file, &settings, &api_client).await {
             Ok(processed) => {
-                // Update file cache with processed content
-                let mut file_cache = app_state.file_cache.write().await;
-                file_cache.insert(file_name.clone(), processed.content.clone());
+                // Keep the processed content as a new version of the page, described by its own hash
+                let stored_metadata = Metadata {
+                    sha1: FileService::calculate_sha1(&processed.content),
+                    file_size: processed.content.len(),
+                    node_size: FileService::calculate_node_size(processed.content.len()),
+                    ..processed.metadata.clone()
+                };
+                let (name, content) = (file_name.clone(), processed.content.clone());
+                if let Err(e) = app_state.content_store.blocking(move |store| store.put(&name, &content, &stored_metadata)).await {
+                    error!("Failed to store processed content for {}: {}", file_name, e);
+                }
                 
                 // Create GitHub PR for the processed file
                 match app_state.github_pr_service.create_pull_request(
@@ -68,6 +76,13 @@ pub async fn process_files(app_state: web::Data<AppState>) -> HttpResponse {
         }
     }
 
+    if let Err(e) = app_state.refresh_search_index().await {
+        error!("Failed to update search index: {}", e);
+    }
+    if let Err(e) = app_state.refresh_embeddings().await {
+        error!("Failed to update page embeddings: {}", e);
+    }
+
     HttpResponse::Ok().json(serde_json::json!({
         "status": "completed",
         "processed_files": processed_count,
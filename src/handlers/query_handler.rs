@@ -0,0 +1,49 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::AppState;
+use crate::handlers::room_handler;
+use crate::services::query_service::{self, GraphQuery};
+
+#[derive(Deserialize)]
+pub struct GraphQueryParams {
+    pub q: String,
+    /// Also return the induced subgraph
+    #[serde(default)]
+    pub subgraph: bool,
+    pub room: Option<String>,
+}
+
+/// Select nodes with the graph query language:
+/// `/api/graph/query?q=tag:rust AND degree>3 NEAR[2] "Project X"&subgraph=true`
+pub async fn query_graph(state: web::Data<AppState>, params: web::Query<GraphQueryParams>) -> HttpResponse {
+    let query = match GraphQuery::parse(&params.q) {
+        Ok(query) => query,
+        Err(e) => return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("Invalid query: {}", e),
+            "position": e.position
+        })),
+    };
+
+    let room = match room_handler::find_room(&state, params.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let analytics = match query.needs_analytics() {
+        true => Some(room.analytics.analyze(&room.graph_data).await),
+        false => None,
+    };
+    let graph = room.graph_data.read().await;
+    let node_ids = query.evaluate(&graph, analytics.as_deref());
+    let subgraph = params.subgraph.then(|| query_service::induced_subgraph(&graph, &node_ids));
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "query": params.q,
+        "count": node_ids.len(),
+        "node_ids": node_ids,
+        "graph": subgraph
+    }))
+}
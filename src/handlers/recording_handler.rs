@@ -0,0 +1,219 @@
+use actix::prelude::*;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws::{self, WebsocketContext};
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, BufReader};
+use tokio::time::Duration;
+
+use crate::AppState;
+use crate::handlers::import_handler::bad_request;
+use crate::services::recording_service::{self, Payload, RecordedFrame, RecordingReader};
+
+pub const MIN_REPLAY_SPEED: f64 = 0.1;
+pub const MAX_REPLAY_SPEED: f64 = 100.0;
+
+#[derive(Deserialize)]
+pub struct ReplayParams {
+    /// Playback rate relative to the recording; 2 plays twice as fast
+    pub speed: Option<f64>,
+}
+
+/// Sent by a replay client to change the speed mid-replay: `{"speed": 4}`
+#[derive(Deserialize)]
+struct ReplayControl {
+    speed: f64,
+}
+
+fn clamp_speed(speed: f64) -> Option<f64> {
+    (speed.is_finite() && speed > 0.0).then(|| speed.clamp(MIN_REPLAY_SPEED, MAX_REPLAY_SPEED))
+}
+
+/// Plays the frames a recorded session was sent to a new client, spaced as they were
+/// recorded. Frames the recorded client sent are not replayed.
+pub struct ReplaySession {
+    id: String,
+    /// Taken while the next frame is read on the blocking pool
+    recording: Option<RecordingReader<BufReader<File>>>,
+    speed: f64,
+    /// Recording time of the last frame sent
+    position_ms: u32,
+}
+
+impl ReplaySession {
+    fn send_next(&mut self, ctx: &mut WebsocketContext<Self>) {
+        let Some(mut recording) = self.recording.take() else {
+            return;
+        };
+        let read = web::block(move || {
+            let frame = recording.next_sent();
+            (recording, frame)
+        });
+        ctx.spawn(read.into_actor(self).map(|result, act, ctx| {
+            let result = result.map_err(io::Error::other).and_then(|(recording, frame)| Ok((recording, frame?)));
+            match result {
+                Ok((recording, Some(frame))) => {
+                    act.recording = Some(recording);
+                    act.schedule(frame, ctx);
+                },
+                Ok((_, None)) => {
+                    info!("Finished replaying recording {}", act.id);
+                    act.finish(ctx, ws::CloseCode::Normal, "End of recording");
+                },
+                Err(e) => {
+                    error!("Failed to read recording {}: {}", act.id, e);
+                    act.finish(ctx, ws::CloseCode::Error, "Unreadable recording");
+                },
+            }
+        }));
+    }
+
+    /// Send `frame` once as much time has passed as did before it in the recording
+    fn schedule(&mut self, frame: RecordedFrame, ctx: &mut WebsocketContext<Self>) {
+        let wait_ms = frame.at_ms.saturating_sub(self.position_ms);
+        let delay = Duration::from_secs_f64(f64::from(wait_ms) / 1000.0 / self.speed);
+        ctx.run_later(delay, move |act, ctx| {
+            act.position_ms = frame.at_ms;
+            match frame.payload {
+                Payload::Text(text) => ctx.text(text),
+                Payload::Binary(data) => ctx.binary(data),
+            }
+            act.send_next(ctx);
+        });
+    }
+
+    fn finish(&mut self, ctx: &mut WebsocketContext<Self>, code: ws::CloseCode, description: &str) {
+        ctx.close(Some(ws::CloseReason { code, description: Some(description.to_string()) }));
+        ctx.stop();
+    }
+}
+
+impl Actor for ReplaySession {
+    type Context = WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.send_next(ctx);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ReplaySession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut WebsocketContext<Self>) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            // A new speed applies from the next frame on
+            Ok(ws::Message::Text(text)) => match serde_json::from_str::<ReplayControl>(&text) {
+                Ok(control) => match clamp_speed(control.speed) {
+                    Some(speed) => self.speed = speed,
+                    None => error!("Ignoring invalid replay speed {}", control.speed),
+                },
+                Err(e) => error!("Ignoring replay client message: {}", e),
+            },
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            },
+            Err(e) => {
+                error!("WebSocket error: {}", e);
+                ctx.stop();
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Replay a recorded session: `/ws/replay/20240501T120000Z-7?speed=2`
+pub async fn replay(
+    req: HttpRequest,
+    stream: web::Payload,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    params: web::Query<ReplayParams>,
+) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    let speed = match params.speed.map(clamp_speed) {
+        Some(Some(speed)) => speed,
+        Some(None) => return Ok(bad_request("Replay speed must be a positive number".to_string())),
+        None => 1.0,
+    };
+    let recording = match open_recording(&state, &id).await {
+        Ok(recording) => recording,
+        Err(response) => return Ok(response),
+    };
+    info!("Replaying session {} of room {} from recording {} at {}x", recording.info.session, recording.info.room, id, speed);
+    let session = ReplaySession {
+        id,
+        recording: Some(recording),
+        speed,
+        position_ms: 0,
+    };
+    ws::start(session, &req, stream)
+}
+
+async fn open_recording(state: &AppState, id: &str) -> Result<RecordingReader<BufReader<File>>, HttpResponse> {
+    recording_service::validate_recording_id(id).map_err(bad_request)?;
+    let path = recording_service::recording_path(&state.settings.read().await.websocket.recording_dir, id);
+    match web::block(move || RecordingReader::open(path)).await {
+        Ok(Ok(recording)) => Ok(recording),
+        Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => Err(not_found(id)),
+        Ok(Err(e)) => Err(recording_error(e)),
+        Err(e) => Err(recording_error(io::Error::other(e))),
+    }
+}
+
+/// Stored recordings, newest first
+pub async fn list_recordings(state: web::Data<AppState>) -> HttpResponse {
+    let dir = state.settings.read().await.websocket.recording_dir.clone();
+    match web::block(move || recording_service::list_recordings(dir)).await {
+        Ok(Ok(recordings)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "recordings": recordings
+        })),
+        Ok(Err(e)) => recording_error(e),
+        Err(e) => recording_error(io::Error::other(e)),
+    }
+}
+
+/// Download a recording, e.g. to keep as a protocol test fixture
+pub async fn download_recording(state: web::Data<AppState>, id: web::Path<String>) -> HttpResponse {
+    if let Err(e) = recording_service::validate_recording_id(&id) {
+        return bad_request(e);
+    }
+    let path = recording_service::recording_path(&state.settings.read().await.websocket.recording_dir, &id);
+    match tokio::fs::read(path).await {
+        Ok(data) => HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.{}\"", id, recording_service::RECORDING_EXTENSION)))
+            .body(data),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => not_found(&id),
+        Err(e) => recording_error(e),
+    }
+}
+
+pub async fn delete_recording(state: web::Data<AppState>, id: web::Path<String>) -> HttpResponse {
+    if let Err(e) = recording_service::validate_recording_id(&id) {
+        return bad_request(e);
+    }
+    let path = recording_service::recording_path(&state.settings.read().await.websocket.recording_dir, &id);
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => HttpResponse::Ok().json(json!({ "status": "success" })),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => not_found(&id),
+        Err(e) => recording_error(e),
+    }
+}
+
+fn not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "status": "error",
+        "message": format!("Unknown recording: {}", id)
+    }))
+}
+
+fn recording_error(e: io::Error) -> HttpResponse {
+    error!("Recording error: {}", e);
+    HttpResponse::InternalServerError().json(json!({
+        "status": "error",
+        "message": format!("Recording error: {}", e)
+    }))
+}
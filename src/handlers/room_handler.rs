@@ -0,0 +1,231 @@
+use actix_web::{web, HttpResponse};
+use log::{error, info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::AppState;
+use crate::config::Settings;
+use crate::handlers::import_handler::{self, bad_request, ImportParams};
+use crate::handlers::websocket_handlers::Disconnect;
+use crate::models::graph::GraphData;
+use crate::models::simulation_params::{SimulationParams, SimulationPhase};
+use crate::services::graph_service::GraphService;
+use crate::services::room_service::{self, Room};
+use crate::utils::gpu_compute::GPUCompute;
+
+/// `?room=` on endpoints acting within a room; the default room when omitted
+#[derive(Deserialize)]
+pub struct RoomParams {
+    pub room: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateRoom {
+    /// Derived from `name` when omitted
+    pub id: Option<String>,
+    pub name: String,
+    /// Settings overriding the server's, shaped like `settings.toml`
+    #[serde(default)]
+    pub settings: Value,
+}
+
+/// The room `id` names, the default room for `None`, or a 404 response
+pub fn find_room(state: &AppState, id: Option<&str>) -> Result<Arc<Room>, HttpResponse> {
+    match id {
+        Some(id) => state.rooms.get(id).ok_or_else(|| not_found(id)),
+        None => Ok(state.rooms.default_room()),
+    }
+}
+
+/// Like `find_room`, but a 400 response for rooms that don't serve the vault
+pub fn find_vault_room(state: &AppState, id: Option<&str>) -> Result<Arc<Room>, HttpResponse> {
+    let room = find_room(state, id)?;
+    if !room.serves_vault() {
+        return Err(bad_request(format!("Room {} doesn't serve the vault", room.id)));
+    }
+    Ok(room)
+}
+
+async fn describe(state: &AppState, room: &Room) -> Value {
+    let (nodes, edges) = {
+        let graph = room.graph_data.read().await;
+        (graph.nodes.len(), graph.edges.len())
+    };
+    json!({
+        "id": room.id,
+        "name": room.name,
+        "source": room.graph_source.read().await.as_ref().map(|source| source.name()),
+        "nodes": nodes,
+        "edges": edges,
+        "sessions": state.websocket_manager.room_sessions(&room.id).len(),
+        "gpu": room.gpu_compute.is_some(),
+        "tour": room.tour_player.tour_name(),
+        "settings": room.settings,
+        "created_at": room.created_at
+    })
+}
+
+pub async fn list_rooms(state: web::Data<AppState>) -> HttpResponse {
+    let mut rooms = Vec::new();
+    for room in state.rooms.all() {
+        rooms.push(describe(&state, &room).await);
+    }
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "rooms": rooms
+    }))
+}
+
+pub async fn get_room(state: web::Data<AppState>, id: web::Path<String>) -> HttpResponse {
+    match find_room(&state, Some(&id)) {
+        Ok(room) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "room": describe(&state, &room).await
+        })),
+        Err(response) => response,
+    }
+}
+
+/// Open an empty room with its own simulation; its graph is uploaded through
+/// `/api/rooms/{id}/import`: `curl -d '{"name":"Team Vault"}' /api/rooms`
+pub async fn create_room(state: web::Data<AppState>, request: web::Json<CreateRoom>) -> HttpResponse {
+    let request = request.into_inner();
+    let id = request.id.clone().unwrap_or_else(|| room_service::room_id_from_name(&request.name));
+    if let Err(e) = room_service::validate_room_id(&id) {
+        return bad_request(e);
+    }
+    if state.rooms.get(&id).is_some() {
+        return conflict(format!("Room {} already exists", id));
+    }
+    let settings = match room_service::apply_overlay(&*state.settings.read().await, &request.settings) {
+        Ok(settings) => settings,
+        Err(e) => return bad_request(e),
+    };
+
+    let graph = GraphData::default();
+
+    // Rooms simulate on the GPU when the server does
+    let gpu_compute = match state.gpu_compute {
+        Some(_) => match GPUCompute::new(&graph).await {
+            Ok(mut gpu) => {
+                if let Err(e) = gpu.update_simulation_params(&simulation_params(&settings)) {
+                    warn!("Failed to apply simulation settings of room {}: {}", id, e);
+                }
+                Some(Arc::new(RwLock::new(gpu)))
+            },
+            Err(e) => {
+                warn!("Failed to initialize GPU for room {}: {}", id, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let room = Room {
+        graph_data: Arc::new(RwLock::new(graph)),
+        gpu_compute,
+        ..Room::new(id, request.name, request.settings)
+    };
+    match state.rooms.insert(room) {
+        Ok(room) => {
+            info!("Opened room {}", room.id);
+            HttpResponse::Created().json(json!({
+                "status": "success",
+                "room": describe(&state, &room).await
+            }))
+        },
+        Err(e) => conflict(e),
+    }
+}
+
+/// Close a room, disconnecting its sessions.
+pub async fn delete_room(state: web::Data<AppState>, id: web::Path<String>) -> HttpResponse {
+    if state.rooms.get(&id).is_none() {
+        return not_found(&id);
+    }
+    match state.rooms.remove(&id) {
+        Ok(room) => {
+            room.tour_player.stop();
+            for (_, session) in state.websocket_manager.room_sessions(&room.id) {
+                session.do_send(Disconnect);
+            }
+            info!("Closed room {}", room.id);
+            HttpResponse::Ok().json(json!({ "status": "success" }))
+        },
+        Err(e) => bad_request(e),
+    }
+}
+
+/// Replace a room's graph with an uploaded graph file:
+/// `curl --data-binary @graph.gexf '/api/rooms/team-vault/import?format=gexf'`
+pub async fn import_into_room(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    params: web::Query<ImportParams>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let room = match find_room(&state, Some(&id)) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    let source = match import_handler::parse_upload(&state, &params, &body).await {
+        Ok(source) => source,
+        Err(response) => return response,
+    };
+    *room.graph_source.write().await = Some(source.clone());
+    if room.is_default() {
+        // Shares its source with `/api/graph/import`
+        return import_handler::rebuild(state).await;
+    }
+
+    let graph = match GraphService::build_imported_graph(source.as_ref()).await {
+        Ok(graph) => graph,
+        Err(e) => {
+            error!("Failed to load graph for room {}: {}", room.id, e);
+            return bad_request(format!("Failed to load graph: {}", e));
+        }
+    };
+    if let Some(gpu_compute) = &room.gpu_compute {
+        let params = simulation_params(&room.effective_settings(&*state.settings.read().await));
+        let mut gpu = gpu_compute.write().await;
+        if let Err(e) = gpu.update_graph_data(&graph) {
+            error!("Failed to update GPU graph of room {}: {}", room.id, e);
+        }
+        if let Err(e) = gpu.update_simulation_params(&params) {
+            error!("Failed to apply simulation settings of room {}: {}", room.id, e);
+        }
+    }
+    let (nodes, edges) = (graph.nodes.len(), graph.edges.len());
+    *room.graph_data.write().await = graph;
+
+    if let Err(e) = state.websocket_manager.broadcast_graph_update(&room.id).await {
+        error!("Failed to broadcast graph update: {}", e);
+    }
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "source": source.name(),
+        "nodes": nodes,
+        "edges": edges
+    }))
+}
+
+/// How the room's simulation runs, from its settings
+fn simulation_params(settings: &Settings) -> SimulationParams {
+    SimulationParams::from_config(&settings.visualization, SimulationPhase::Interactive)
+}
+
+fn not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "status": "error",
+        "message": format!("Unknown room: {}", id)
+    }))
+}
+
+fn conflict(message: String) -> HttpResponse {
+    HttpResponse::Conflict().json(json!({
+        "status": "error",
+        "message": message
+    }))
+}
@@ -0,0 +1,29 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::AppState;
+use crate::handlers::room_handler;
+use crate::services::search_service::DEFAULT_RESULT_LIMIT;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+    pub room: Option<String>,
+}
+
+/// Full-text search over page content and metadata: `/api/search?q=rust&limit=10`
+pub async fn search(state: web::Data<AppState>, query: web::Query<SearchQuery>) -> HttpResponse {
+    if let Err(response) = room_handler::find_vault_room(&state, query.room.as_deref()) {
+        return response;
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_RESULT_LIMIT);
+    let results = state.search_index.read().await.search(&query.q, limit);
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "query": query.q,
+        "results": results
+    }))
+}
@@ -0,0 +1,58 @@
+use actix_web::{web, HttpResponse};
+use futures::future::join_all;
+use serde_json::json;
+
+use crate::AppState;
+use crate::handlers::websocket_handlers::{DescribeSession, Disconnect};
+use crate::utils::websocket_manager::SessionId;
+use crate::utils::websocket_messages::ServerMessage;
+
+/// Every connected WebSocket session with its user, view and simulation mode.
+pub async fn list_sessions(state: web::Data<AppState>) -> HttpResponse {
+    let sessions = state.websocket_manager.sessions();
+    // A session that stops while being asked simply drops out of the listing
+    let sessions: Vec<_> = join_all(sessions.iter().map(|(_, session)| session.send(DescribeSession)))
+        .await
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "count": sessions.len(),
+        "sessions": sessions
+    }))
+}
+
+/// Send one server message to a single session:
+/// `curl -d '{"type":"error","message":"Server restarting"}' /api/sessions/3/message`
+pub async fn send_to_session(
+    state: web::Data<AppState>,
+    id: web::Path<SessionId>,
+    message: web::Json<ServerMessage>,
+) -> HttpResponse {
+    let id = id.into_inner();
+    match state.websocket_manager.send_to(id, message.into_inner()) {
+        true => HttpResponse::Ok().json(json!({ "status": "success" })),
+        false => not_found(id),
+    }
+}
+
+/// Close a session from the server side.
+pub async fn disconnect_session(state: web::Data<AppState>, id: web::Path<SessionId>) -> HttpResponse {
+    let id = id.into_inner();
+    match state.websocket_manager.session(id) {
+        Some(session) => {
+            session.do_send(Disconnect);
+            HttpResponse::Ok().json(json!({ "status": "success" }))
+        },
+        None => not_found(id),
+    }
+}
+
+fn not_found(id: SessionId) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "status": "error",
+        "message": format!("No session with id {}", id)
+    }))
+}
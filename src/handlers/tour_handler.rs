@@ -0,0 +1,222 @@
+use actix_web::{web, HttpResponse};
+use log::{error, info};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+
+use crate::AppState;
+use crate::handlers::room_handler::{self, RoomParams};
+use crate::handlers::websocket_handlers::{EndTour, ShowTourStep};
+use crate::services::room_service::Room;
+use crate::services::tour_service::{Tour, TourPosition, MIN_STEP_DURATION_MS};
+
+/// A presenter's control of the tour playback, from REST or the WebSocket
+pub enum TourCommand {
+    Start(String),
+    /// Move forward or back this many steps
+    Step(isize),
+    Stop,
+}
+
+/// Apply a tour command in `room`, showing the resulting step to every session in it.
+pub async fn run(state: &web::Data<AppState>, room: &Arc<Room>, command: TourCommand) -> Result<(), String> {
+    match command {
+        TourCommand::Start(name) => {
+            let tour = state.tours.get(&name)
+                .map_err(|e| format!("Failed to load tour: {}", e))?
+                .ok_or_else(|| format!("Unknown tour: {}", name))?;
+            info!("Starting tour {} with {} steps in room {}", tour.name, tour.steps.len(), room.id);
+            present(state, room, room.tour_player.start(tour)).await;
+        },
+        TourCommand::Step(by) => {
+            let tour = room.tour_player.tour_name();
+            match room.tour_player.step(by)? {
+                Some(position) => present(state, room, position).await,
+                None => end(state, room, tour.unwrap_or_default(), true),
+            }
+        },
+        TourCommand::Stop => {
+            let tour = room.tour_player.stop().ok_or_else(|| "No tour is being presented".to_string())?;
+            end(state, room, tour, false);
+        },
+    }
+    Ok(())
+}
+
+/// Show a step to the room's sessions, speak its narration and schedule the next step
+async fn present(state: &web::Data<AppState>, room: &Arc<Room>, position: TourPosition) {
+    let position = Arc::new(position);
+    for (_, session) in state.websocket_manager.room_sessions(&room.id) {
+        session.do_send(ShowTourStep(position.clone()));
+    }
+
+    if position.speak {
+        if let Some(narration) = &position.step.narration {
+            if let Err(e) = state.speech_service.send_message(narration.clone()).await {
+                error!("Failed to speak tour narration: {}", e);
+            }
+        }
+    }
+
+    if let Some(duration_ms) = position.step.duration_ms {
+        let state = state.clone();
+        let room = room.clone();
+        let generation = position.generation;
+        // Boxed to break the cycle between this future and `run`'s
+        let advance: Pin<Box<dyn Future<Output = ()>>> = Box::pin(async move {
+            sleep(Duration::from_millis(duration_ms.max(MIN_STEP_DURATION_MS))).await;
+            // The presenter may have moved on meanwhile
+            if room.tour_player.is_current(generation) {
+                if let Err(e) = run(&state, &room, TourCommand::Step(1)).await {
+                    error!("Failed to advance tour: {}", e);
+                }
+            }
+        });
+        actix_web::rt::spawn(advance);
+    }
+}
+
+fn end(state: &AppState, room: &Room, tour: String, completed: bool) {
+    info!("Tour {} ended in room {}", tour, room.id);
+    for (_, session) in state.websocket_manager.room_sessions(&room.id) {
+        session.do_send(EndTour { tour: tour.clone(), completed });
+    }
+}
+
+/// Stored tours, without their steps, and the one being presented in `?room=`
+pub async fn list_tours(state: web::Data<AppState>, params: web::Query<RoomParams>) -> HttpResponse {
+    let room = match room_handler::find_room(&state, params.room.as_deref()) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    match state.tours.all() {
+        Ok(tours) => {
+            let tours: Vec<_> = tours.into_iter().map(|(tour, updated_at)| json!({
+                "name": tour.name,
+                "description": tour.description,
+                "steps": tour.steps.len(),
+                "speak": tour.speak,
+                "updated_at": updated_at
+            })).collect();
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "tours": tours,
+                "playing": room.tour_player.tour_name()
+            }))
+        },
+        Err(e) => store_error(e),
+    }
+}
+
+pub async fn get_tour(state: web::Data<AppState>, name: web::Path<String>) -> HttpResponse {
+    match state.tours.get(&name) {
+        Ok(Some(tour)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "tour": tour
+        })),
+        Ok(None) => not_found(&name),
+        Err(e) => store_error(e),
+    }
+}
+
+/// Create or replace a tour:
+/// `curl -X PUT -d '{"steps":[{"focus_node":"Rust","narration":"..."}]}' /api/tours/intro`
+pub async fn put_tour(state: web::Data<AppState>, name: web::Path<String>, tour: web::Json<Tour>) -> HttpResponse {
+    let mut tour = tour.into_inner();
+    tour.name = name.into_inner();
+    if let Err(e) = tour.validate() {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("Invalid tour: {}", e)
+        }));
+    }
+    match state.tours.put(&tour) {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "name": tour.name,
+            "steps": tour.steps.len()
+        })),
+        Err(e) => store_error(e),
+    }
+}
+
+pub async fn delete_tour(state: web::Data<AppState>, name: web::Path<String>) -> HttpResponse {
+    match state.tours.delete(&name) {
+        Ok(true) => {
+            for room in state.rooms.all() {
+                if room.tour_player.tour_name().as_deref() == Some(name.as_str()) {
+                    if let Err(e) = run(&state, &room, TourCommand::Stop).await {
+                        error!("Failed to stop deleted tour: {}", e);
+                    }
+                }
+            }
+            HttpResponse::Ok().json(json!({ "status": "success" }))
+        },
+        Ok(false) => not_found(&name),
+        Err(e) => store_error(e),
+    }
+}
+
+/// Present a tour in the default room, or the one given by `?room=`
+pub async fn play_tour(state: web::Data<AppState>, name: web::Path<String>, params: web::Query<RoomParams>) -> HttpResponse {
+    control(&state, params.room.as_deref(), None, TourCommand::Start(name.into_inner())).await
+}
+
+pub async fn next_step(state: web::Data<AppState>, name: web::Path<String>, params: web::Query<RoomParams>) -> HttpResponse {
+    control(&state, params.room.as_deref(), Some(&name), TourCommand::Step(1)).await
+}
+
+pub async fn previous_step(state: web::Data<AppState>, name: web::Path<String>, params: web::Query<RoomParams>) -> HttpResponse {
+    control(&state, params.room.as_deref(), Some(&name), TourCommand::Step(-1)).await
+}
+
+pub async fn stop_tour(state: web::Data<AppState>, name: web::Path<String>, params: web::Query<RoomParams>) -> HttpResponse {
+    control(&state, params.room.as_deref(), Some(&name), TourCommand::Stop).await
+}
+
+/// Apply a command in a room. With `playing`, that must be the tour being presented there.
+async fn control(state: &web::Data<AppState>, room: Option<&str>, playing: Option<&str>, command: TourCommand) -> HttpResponse {
+    let room = match room_handler::find_room(state, room) {
+        Ok(room) => room,
+        Err(response) => return response,
+    };
+    if let Some(name) = playing {
+        if room.tour_player.tour_name().as_deref() != Some(name) {
+            return HttpResponse::Conflict().json(json!({
+                "status": "error",
+                "message": format!("Tour {} is not being presented", name)
+            }));
+        }
+    }
+    match run(state, &room, command).await {
+        Ok(()) => {
+            let current = room.tour_player.current();
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "tour": current.as_ref().map(|position| &position.tour),
+                "step": current.as_ref().map(|position| position.index)
+            }))
+        },
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": e
+        })),
+    }
+}
+
+fn not_found(name: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "status": "error",
+        "message": format!("Unknown tour: {}", name)
+    }))
+}
+
+fn store_error(e: rusqlite::Error) -> HttpResponse {
+    error!("Tour store error: {}", e);
+    HttpResponse::InternalServerError().json(json!({
+        "status": "error",
+        "message": format!("Tour store error: {}", e)
+    }))
+}
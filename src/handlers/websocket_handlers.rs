@@ -1,123 +1,540 @@
 use actix::prelude::*;
-use actix::ResponseActFuture;
 use actix_web::web;
-use actix_web_actors::ws::WebsocketContext;
+use actix_web_actors::ws::{self, WebsocketContext};
 use bytes::Bytes;
 use bytestring::ByteString;
-use bytemuck;
 use futures::StreamExt;
 use log::{debug, error, info};
-use serde_json::json;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Instant;
+use tokio::sync::RwLock;
 use tokio::time::Duration;
 
 use crate::AppState;
 use crate::models::node::{GPUNode, GPUNodePositionUpdate};
 use crate::models::simulation_params::{SimulationMode, SimulationParams};
+use crate::models::graph::GraphData;
+use crate::handlers::history_handler::resolve_time_range;
+use crate::handlers::tour_handler::{self, TourCommand};
+use crate::services::analytics_service::AnalyticsCache;
+use crate::services::history_service::CommitFrame;
+use crate::services::node_lock_service::{self, NodeLock, NodeMove};
+use crate::services::presence_service::PresenceUpdate;
+use crate::services::query_service::{self, GraphQuery, QueryError, ViewFilter};
+use crate::services::recording_service::{Direction, Frame, SessionRecorder};
+use crate::services::room_service::{Room, RoomId};
+use crate::services::search_service::DEFAULT_RESULT_LIMIT;
+use crate::services::tour_service::TourPosition;
+use crate::utils::compression;
+use crate::utils::flow_control::{FlowControl, FlowLimits, FLOW_WINDOW};
 use crate::utils::websocket_messages::{
-    MessageHandler, OpenAIConnected, OpenAIConnectionFailed, OpenAIMessage, SendBinary, SendText,
+    ClientSettings, Compression, MessageHandler, OpenAIConnected, OpenAIConnectionFailed, OpenAIMessage, ProtocolOptions,
+    RequestId, SendBinary, SendMessage, ServerMessage,
 };
+use crate::utils::websocket_manager::SessionId;
 use crate::utils::websocket_openai::OpenAIWebSocket;
 
 pub const OPENAI_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 pub const GPU_UPDATE_INTERVAL: Duration = Duration::from_millis(16); // ~60fps for smooth updates
+pub const HISTORY_FRAME_INTERVAL: Duration = Duration::from_millis(500);
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
-// Message for GPU updates
+/// Positions of every node in graph order, narrowed to the session's view before sending
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct GpuUpdate;
+pub struct PositionFrame(pub Arc<Vec<GPUNode>>);
+
+/// Sent when the shared graph changed, so each session can re-apply its view
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct GraphChanged;
+
+/// Asks a session to describe itself for `/api/sessions`
+#[derive(Message)]
+#[rtype(result = "SessionInfo")]
+pub struct DescribeSession;
+
+/// Closes a session from the server side
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Disconnect;
+
+/// The presented tour moved to a step
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ShowTourStep(pub Arc<TourPosition>);
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct EndTour {
+    pub tour: String,
+    pub completed: bool,
+}
+
+/// A connected client as listed by `/api/sessions`
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub room: RoomId,
+    pub user: Option<String>,
+    pub address: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    /// Seconds since the client last answered a heartbeat
+    pub idle_secs: u64,
+    pub protocol_version: u32,
+    pub simulation_mode: SimulationMode,
+    /// Filters of the session's view; empty shows the whole graph
+    pub view: Vec<ViewFilter>,
+    pub cluster_view: bool,
+    pub history_playback: bool,
+    /// Id of the session's recording, when sessions are recorded
+    pub recording: Option<String>,
+}
+
+/// The filters a session narrowed its view to and the graph indices they select.
+/// The session is sent the induced subgraph, so position frames carry just these
+/// nodes in the same order.
+pub struct SessionView {
+    pub filters: Vec<ViewFilter>,
+    /// Graph indices of the visible nodes, ascending
+    pub indices: Vec<usize>,
+}
+
+impl SessionView {
+    /// Evaluate `filters` against `graph`, giving the view and the subgraph it shows.
+    /// No filters means no view: the whole graph. The graph isn't locked while
+    /// analytics are computed.
+    pub async fn evaluate(graph: &RwLock<GraphData>, filters: Vec<ViewFilter>, analytics: &Arc<AnalyticsCache>) -> Result<(Option<Self>, GraphData), QueryError> {
+        if filters.is_empty() {
+            return Ok((None, graph.read().await.clone()));
+        }
+        let analytics = query_service::view_analytics(graph, &filters, analytics).await;
+        let graph = graph.read().await;
+        let node_ids = query_service::select_view(&graph, &filters, analytics.as_deref())?;
+        let visible: HashSet<&str> = node_ids.iter().map(String::as_str).collect();
+        let indices = graph.nodes.iter()
+            .enumerate()
+            .filter(|(_, n)| visible.contains(n.id.as_str()))
+            .map(|(i, _)| i)
+            .collect();
+        Ok((Some(Self { filters, indices }), query_service::induced_subgraph(&graph, &node_ids)))
+    }
+}
+
+/// Re-apply a session's filters to the current graph. Filters were validated when set,
+/// so one that no longer evaluates falls back to the whole graph.
+async fn refresh_view(graph: &RwLock<GraphData>, filters: Option<Vec<ViewFilter>>, analytics: &Arc<AnalyticsCache>) -> (Option<SessionView>, GraphData) {
+    match SessionView::evaluate(graph, filters.unwrap_or_default(), analytics).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to re-apply session view: {}", e);
+            (None, graph.read().await.clone())
+        }
+    }
+}
+
+/// In cluster mode, aggregate the visible graph into meta-nodes, showing the contents
+/// of the expanded clusters only.
+async fn apply_clusters(state: &AppState, room: &Room, graph: GraphData, expanded: Option<HashSet<String>>) -> GraphData {
+    let Some(expanded) = expanded else {
+        return graph;
+    };
+    let settings = room.effective_settings(&*state.settings.read().await).clusters;
+    room.clusters.build(&graph, &settings).await.cut(&graph, &expanded)
+}
 
 /// WebSocket session actor.
 pub struct WebSocketSession {
+    pub id: SessionId,
+    /// Name the client connected with, `/ws?user=...`
+    pub user: Option<String>,
+    pub address: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    /// Last ping or pong from the client
+    pub hb: Instant,
     pub state: web::Data<AppState>,
+    /// Room the session joined, whose graph and simulation it shows
+    pub room: Arc<Room>,
     pub tts_method: String,
     pub openai_ws: Option<Addr<OpenAIWebSocket>>,
     pub simulation_mode: SimulationMode,
     pub conversation_id: Option<Arc<Mutex<Option<String>>>>,
+    pub view: Option<SessionView>,
+    /// Expanded cluster ids while the session is in cluster mode
+    pub clusters: Option<HashSet<String>>,
+    /// Running history playback
+    pub playback: Option<SpawnHandle>,
+    /// What the client agreed to in its `hello`
+    pub protocol: ProtocolOptions,
+    /// Id of the client request being dispatched, echoed in its responses
+    pub request_id: Option<RequestId>,
+    /// Frames of at least this many bytes are compressed, if the client negotiated it
+    pub compression_threshold: usize,
+    /// During a tour, the view filters to restore when it ends
+    pub tour_view: Option<Vec<ViewFilter>>,
+    /// Writes the session's frames to disk when `record_sessions` is on
+    pub recorder: RefCell<Option<SessionRecorder>>,
+    pub flow_limits: FlowLimits,
+    /// Pacing of position frames, when the client negotiated flow control
+    pub flow: Option<FlowControl<Arc<Vec<GPUNode>>>>,
+    /// Reports the frame rate each window while flow control is on
+    pub flow_report: Option<SpawnHandle>,
+    /// Sends the frame the frame rate held back once its interval has passed
+    pub flow_retry: Option<SpawnHandle>,
 }
 
 impl Actor for WebSocketSession {
     type Context = WebsocketContext<Self>;
-}
 
-impl MessageHandler for WebSocketSession {}
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+        let manager = &self.state.websocket_manager;
+        let room = &self.room;
+        let presence = room.presence.join(self.id, self.user.as_deref().unwrap_or_default());
+        let peers = room.presence.peers().into_iter().filter(|peer| peer.session != self.id).collect();
+        let locks = room.locks.locks();
+        self.send_server_message(ServerMessage::PresenceRoster { session: self.id, peers, locks }, ctx);
+        manager.broadcast_except(&room.id, self.id, ServerMessage::PeerJoined(presence));
+        manager.register(self.id, &room.id, ctx.address());
+        // Join a tour in progress at its current step
+        if let Some(position) = self.room.tour_player.current() {
+            ctx.notify(ShowTourStep(Arc::new(position)));
+        }
+    }
 
-/// Helper function to convert hex color to proper format
-pub fn format_color(color: &str) -> String {
-    let color = color.trim_matches('"')
-        .trim_start_matches("0x")
-        .trim_start_matches('#');
-    format!("#{}", color)
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        let manager = &self.state.websocket_manager;
+        manager.deregister(self.id);
+        self.room.set_remote_simulation(self.id, false);
+        manager.release_locks(&self.room, self.id);
+        if self.room.presence.leave(self.id) {
+            manager.broadcast_except(&self.room.id, self.id, ServerMessage::PeerLeft { session: self.id });
+        }
+    }
 }
 
-/// Helper function to convert GPU nodes to binary position updates
-pub fn positions_to_binary(nodes: &[GPUNode]) -> Vec<u8> {
-    let mut binary_data = Vec::with_capacity(nodes.len() * std::mem::size_of::<GPUNodePositionUpdate>());
-    for node in nodes {
-        // Convert to position update format (24 bytes)
-        let update = GPUNodePositionUpdate {
-            x: node.x,
-            y: node.y,
-            z: node.z,
-            vx: node.vx,
-            vy: node.vy,
-            vz: node.vz,
-        };
-        // Use as_bytes() since GPUNodePositionUpdate is Pod
-        binary_data.extend_from_slice(bytemuck::bytes_of(&update));
+impl MessageHandler for WebSocketSession {
+    fn write_text(&self, text: String, ctx: &mut WebsocketContext<Self>) {
+        self.record(Direction::Sent, Frame::Text(&text));
+        match self.protocol.compression {
+            Some(Compression::Deflate) if text.len() >= self.compression_threshold => {
+                match compression::compress_message(&text) {
+                    Ok(compressed) => ctx.binary(Bytes::from(compressed)),
+                    Err(e) => {
+                        error!("Failed to compress message: {}", e);
+                        ctx.text(ByteString::from(text));
+                    }
+                }
+            },
+            _ => ctx.text(ByteString::from(text)),
+        }
+    }
+}
+
+impl WebSocketSession {
+    /// Write a binary frame, compressed when large and the client negotiated it
+    pub fn write_binary(&self, data: Vec<u8>, ctx: &mut WebsocketContext<Self>) {
+        self.record(Direction::Sent, Frame::Binary(&data));
+        match self.protocol.compression {
+            Some(Compression::Deflate) if data.len() >= self.compression_threshold => {
+                ctx.binary(Bytes::from(compression::compress_binary(&data)));
+            },
+            _ => ctx.binary(Bytes::from(data)),
+        }
+    }
+
+    /// Append a frame to the session's recording, if it has one. Frames are recorded
+    /// uncompressed. A recording that fails to write is abandoned.
+    pub fn record(&self, direction: Direction, frame: Frame) {
+        let mut recorder = self.recorder.borrow_mut();
+        if let Some(recording) = recorder.as_mut() {
+            if let Err(e) = recording.record(direction, frame) {
+                error!("Failed to record session {}, recording stopped: {}", self.id, e);
+                *recorder = None;
+            }
+        }
     }
-    binary_data
 }
 
 pub trait WebSocketSessionHandler {
-    fn start_gpu_updates(&self, ctx: &mut WebsocketContext<WebSocketSession>);
+    fn handle_hello(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, version: u32, compression: Vec<String>, binary_formats: Vec<String>, flow_control: bool);
     fn handle_chat_message(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, message: String, use_openai: bool);
     fn handle_simulation_mode(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, mode: &str);
     fn handle_layout(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, params: SimulationParams);
     fn handle_initial_data(&mut self, ctx: &mut WebsocketContext<WebSocketSession>);
     fn handle_fisheye_settings(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, enabled: bool, strength: f32, focus_point: [f32; 3], radius: f32);
+    fn handle_search(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, query: String, limit: Option<usize>);
+    fn handle_query(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, query: String, subgraph: bool, filter: bool);
+    fn handle_set_view(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, filters: Vec<ViewFilter>);
+    fn handle_set_cluster_view(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, enabled: bool);
+    fn handle_expand_cluster(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, cluster: String, expand: bool);
+    fn handle_play_history(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, from: Option<String>, to: Option<String>, interval_ms: Option<u64>);
+    fn handle_stop_history(&mut self, ctx: &mut WebsocketContext<WebSocketSession>);
+    fn handle_presence(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, update: PresenceUpdate);
+    fn handle_grab_node(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, node: String);
+    fn handle_move_node(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, node: String, position: [f32; 3]);
+    fn handle_release_node(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, node: String);
+    fn handle_tour(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, command: TourCommand);
+    fn handle_frames_received(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, count: u64);
 }
 
-impl Handler<GpuUpdate> for WebSocketSession {
-    type Result = ResponseActFuture<Self, ()>;
+impl WebSocketSession {
+    /// Ping the client every `HEARTBEAT_INTERVAL`, closing the session once it stops answering
+    fn hb(&self, ctx: &mut WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                info!("WebSocket session {} heartbeat failed, disconnecting", act.id);
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+            if let Some(recording) = act.recorder.get_mut() {
+                if let Err(e) = recording.flush() {
+                    error!("Failed to flush recording of session {}: {}", act.id, e);
+                }
+            }
+        });
+    }
+
+    /// Send a position frame, narrowed to the session's view
+    fn send_positions(&self, nodes: &[GPUNode], ctx: &mut WebsocketContext<Self>) {
+        let format = self.protocol.binary_format;
+        let binary_data = match &self.view {
+            Some(view) => {
+                let visible: Vec<GPUNode> = view.indices.iter()
+                    .filter_map(|&i| nodes.get(i).copied())
+                    .collect();
+                format.encode(&visible)
+            },
+            None => format.encode(nodes),
+        };
+        self.write_binary(binary_data, ctx);
+    }
+
+    /// Send a frame flow control released, and make sure one held back only by the
+    /// frame rate goes out after its interval even if no newer frame or ack comes
+    fn pace_positions(&mut self, frame: Option<Arc<Vec<GPUNode>>>, ctx: &mut WebsocketContext<Self>) {
+        if let Some(frame) = frame {
+            self.send_positions(&frame, ctx);
+        }
+        if self.flow_retry.is_some() {
+            return;
+        }
+        let Some(delay) = self.flow.as_ref().and_then(|flow| flow.retry_in(Instant::now())) else {
+            return;
+        };
+        self.flow_retry = Some(ctx.run_later(delay, |act, ctx| {
+            act.flow_retry = None;
+            let frame = act.flow.as_mut().and_then(|flow| flow.poll(Instant::now()));
+            act.pace_positions(frame, ctx);
+        }));
+    }
+
+    /// Whether the session's room serves the vault; replies with an error if not
+    fn require_vault(&mut self, ctx: &mut WebsocketContext<Self>) -> bool {
+        if self.room.serves_vault() {
+            return true;
+        }
+        self.send_reply(self.request_id.clone(), ServerMessage::Error {
+            message: format!("Room {} doesn't serve the vault", self.room.id),
+            code: Some("NO_VAULT".to_string()),
+        }, ctx);
+        false
+    }
 
-    fn handle(&mut self, _: GpuUpdate, ctx: &mut Self::Context) -> Self::Result {
+    /// Report the position frame rate of the last window to the client
+    fn report_frame_rate(&mut self, ctx: &mut WebsocketContext<Self>) {
+        let Some(rate) = self.flow.as_mut().and_then(|flow| flow.measure(Instant::now())) else {
+            return;
+        };
+        debug!("Session {} acknowledged {:.1} fps, now sending at most {:.1}", self.id, rate.fps, rate.target_fps);
+        self.send_server_message(ServerMessage::FrameRate {
+            fps: rate.fps,
+            target_fps: rate.target_fps,
+            dropped: rate.dropped,
+        }, ctx);
+    }
+
+    /// Filters of the current view, for re-evaluation in a spawned future
+    fn view_filters(&self) -> Option<Vec<ViewFilter>> {
+        self.view.as_ref().map(|view| view.filters.clone())
+    }
+
+    /// Store a re-evaluated view unless the client changed its view meanwhile
+    fn update_view(&mut self, evaluated: Option<Vec<ViewFilter>>, view: Option<SessionView>) {
+        if self.view_filters() == evaluated {
+            self.view = view;
+        }
+    }
+
+    /// Send one commit frame per interval, then end the playback
+    fn start_playback(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, frames: Vec<CommitFrame>, interval: Duration) {
+        if let Some(handle) = self.playback.take() {
+            ctx.cancel_future(handle);
+        }
+        let mut frames = frames.into_iter();
+        let handle = ctx.run_interval(interval, move |act, ctx| match frames.next() {
+            Some(frame) => act.send_server_message(ServerMessage::HistoryFrame(frame), ctx),
+            None => act.stop_playback(ctx, true),
+        });
+        self.playback = Some(handle);
+    }
+
+    /// End the playback and return the client to the live graph
+    fn stop_playback(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, completed: bool) {
+        if let Some(handle) = self.playback.take() {
+            ctx.cancel_future(handle);
+            self.send_reply(self.request_id.clone(), ServerMessage::HistoryEnd { completed }, ctx);
+            ctx.notify(GraphChanged);
+        }
+    }
+
+    /// Recompute the cluster cut of this session's view and send it in reply to request `id`
+    fn send_cluster_view(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, id: Option<RequestId>) {
         let state = self.state.clone();
-        let gpu_compute = if let Some(gpu) = &state.gpu_compute {
-            gpu.clone()
-        } else {
-            return Box::pin(futures::future::ready(()).into_actor(self));
+        let room = self.room.clone();
+        let filters = self.view_filters();
+        let clusters = self.clusters.clone();
+
+        let fut = {
+            let filters = filters.clone();
+            async move {
+                let (view, visible_graph) = refresh_view(&room.graph_data, filters, &room.analytics).await;
+                (view, apply_clusters(&state, &room, visible_graph, clusters).await)
+            }
         };
+
+        ctx.spawn(fut.into_actor(self).map(move |(view, graph), act, ctx| {
+            act.update_view(filters, view);
+            let mut expanded: Vec<String> = act.clusters.iter().flatten().cloned().collect();
+            expanded.sort();
+            act.send_reply(id.clone(), ServerMessage::ClusterView {
+                enabled: act.clusters.is_some(),
+                expanded,
+                node_count: graph.nodes.len(),
+            }, ctx);
+            act.send_reply(id, ServerMessage::GraphUpdate { graph_data: graph }, ctx);
+        }));
+    }
+}
+
+impl Handler<PositionFrame> for WebSocketSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: PositionFrame, ctx: &mut Self::Context) {
+        // Cluster mode and history playback show their own layouts, which live positions don't map onto
+        if self.clusters.is_some() || self.playback.is_some() {
+            return;
+        }
+        match self.flow.as_mut() {
+            Some(flow) => {
+                let frame = flow.offer(msg.0, Instant::now());
+                self.pace_positions(frame, ctx);
+            },
+            None => self.send_positions(&msg.0, ctx),
+        }
+    }
+}
+
+impl Handler<GraphChanged> for WebSocketSession {
+    type Result = ();
+
+    fn handle(&mut self, _: GraphChanged, ctx: &mut Self::Context) {
+        let state = self.state.clone();
+        let room = self.room.clone();
+        let filters = self.view_filters();
+        let clusters = self.clusters.clone();
         let ctx_addr = ctx.address();
 
-        Box::pin(async move {
-            let mut gpu = gpu_compute.write().await;
-            if let Err(e) = gpu.step() {
-                error!("GPU compute step failed: {}", e);
-                return;
+        let fut = {
+            let filters = filters.clone();
+            async move {
+                let (view, visible_graph) = refresh_view(&room.graph_data, filters, &room.analytics).await;
+                let graph_data = apply_clusters(&state, &room, visible_graph, clusters).await;
+                ctx_addr.do_send(SendMessage::new(ServerMessage::GraphUpdate { graph_data }));
+                view
             }
+        };
 
-            if let Ok(nodes) = gpu.get_node_positions().await {
-                let binary_data = positions_to_binary(&nodes);
+        ctx.spawn(fut.into_actor(self).map(move |view, act, _| act.update_view(filters, view)));
+    }
+}
 
-                if let Ok(sessions) = state.websocket_manager.sessions.lock() {
-                    for session in sessions.iter() {
-                        if session != &ctx_addr {
-                            let _ = session.do_send(SendBinary(binary_data.clone()));
-                        }
-                    }
-                }
+impl Handler<DescribeSession> for WebSocketSession {
+    type Result = MessageResult<DescribeSession>;
+
+    fn handle(&mut self, _: DescribeSession, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(SessionInfo {
+            id: self.id,
+            room: self.room.id.clone(),
+            user: self.user.clone(),
+            address: self.address.clone(),
+            connected_at: self.connected_at,
+            idle_secs: self.hb.elapsed().as_secs(),
+            protocol_version: self.protocol.version,
+            simulation_mode: self.simulation_mode,
+            view: self.view_filters().unwrap_or_default(),
+            cluster_view: self.clusters.is_some(),
+            history_playback: self.playback.is_some(),
+            recording: self.recorder.borrow().as_ref().map(|recording| recording.id().to_string()),
+        })
+    }
+}
+
+impl Handler<Disconnect> for WebSocketSession {
+    type Result = ();
+
+    fn handle(&mut self, _: Disconnect, ctx: &mut Self::Context) {
+        info!("Disconnecting WebSocket session {}", self.id);
+        ctx.close(Some(ws::CloseCode::Normal.into()));
+        ctx.stop();
+    }
+}
+
+impl Handler<ShowTourStep> for WebSocketSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: ShowTourStep, ctx: &mut Self::Context) {
+        let position = &msg.0;
+        let current = self.view_filters().unwrap_or_default();
+        if self.tour_view.is_none() {
+            self.tour_view = Some(current.clone());
+        }
+        self.send_server_message(ServerMessage::TourStep {
+            tour: position.tour.clone(),
+            index: position.index,
+            step_count: position.step_count,
+            step: position.step.clone(),
+        }, ctx);
+        if current != position.step.filters {
+            WebSocketSessionHandler::handle_set_view(self, ctx, position.step.filters.clone());
+        }
+    }
+}
+
+impl Handler<EndTour> for WebSocketSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: EndTour, ctx: &mut Self::Context) {
+        self.send_server_message(ServerMessage::TourEnd { tour: msg.tour, completed: msg.completed }, ctx);
+        if let Some(filters) = self.tour_view.take() {
+            if self.view_filters().unwrap_or_default() != filters {
+                WebSocketSessionHandler::handle_set_view(self, ctx, filters);
             }
         }
-        .into_actor(self))
     }
 }
 
-impl Handler<SendText> for WebSocketSession {
+impl Handler<SendMessage> for WebSocketSession {
     type Result = ();
 
-    fn handle(&mut self, msg: SendText, ctx: &mut Self::Context) {
-        ctx.text(ByteString::from(msg.0));
+    fn handle(&mut self, msg: SendMessage, ctx: &mut Self::Context) {
+        self.send_reply(msg.id, msg.message, ctx);
     }
 }
 
@@ -125,7 +542,7 @@ impl Handler<SendBinary> for WebSocketSession {
     type Result = ();
 
     fn handle(&mut self, msg: SendBinary, ctx: &mut Self::Context) {
-        ctx.binary(Bytes::from(msg.0));
+        self.write_binary(msg.0, ctx);
     }
 }
 
@@ -157,11 +574,36 @@ impl Handler<OpenAIConnectionFailed> for WebSocketSession {
 }
 
 impl WebSocketSessionHandler for WebSocketSession {
-    fn start_gpu_updates(&self, ctx: &mut WebsocketContext<WebSocketSession>) {
-        let addr = ctx.address();
-        ctx.run_interval(GPU_UPDATE_INTERVAL, move |_, _| {
-            addr.do_send(GpuUpdate);
-        });
+    fn handle_hello(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, version: u32, compression: Vec<String>, binary_formats: Vec<String>, flow_control: bool) {
+        match ProtocolOptions::negotiate(version, &compression, &binary_formats, flow_control) {
+            Ok(protocol) => {
+                info!("Client speaks protocol version {} with {:?} position frames", protocol.version, protocol.binary_format);
+                self.send_reply(self.request_id.clone(), ServerMessage::Hello {
+                    version: protocol.version,
+                    compression: protocol.compression,
+                    binary_format: protocol.binary_format,
+                    flow_control: protocol.flow_control,
+                }, ctx);
+                // The client counts frames from this reply on, so pacing starts over
+                for handle in [self.flow_report.take(), self.flow_retry.take()].into_iter().flatten() {
+                    ctx.cancel_future(handle);
+                }
+                self.flow = protocol.flow_control.then(|| FlowControl::new(self.flow_limits, Instant::now()));
+                if self.flow.is_some() {
+                    self.flow_report = Some(ctx.run_interval(FLOW_WINDOW, |act, ctx| act.report_frame_rate(ctx)));
+                }
+                self.protocol = protocol;
+            },
+            Err(message) => {
+                error!("{}", message);
+                self.send_reply(self.request_id.clone(), ServerMessage::Error {
+                    message: message.clone(),
+                    code: Some("UNSUPPORTED_VERSION".to_string()),
+                }, ctx);
+                ctx.close(Some(ws::CloseReason { code: ws::CloseCode::Protocol, description: Some(message) }));
+                ctx.stop();
+            }
+        }
     }
 
     fn handle_chat_message(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, message: String, use_openai: bool) {
@@ -170,14 +612,24 @@ impl WebSocketSessionHandler for WebSocketSession {
         let ctx_addr = ctx.address();
         let settings = self.state.settings.clone();
         let weak_addr = ctx.address().downgrade();
+        let id = self.request_id.clone();
 
         let fut = async move {
+            let send_error = |message: String| ctx_addr.do_send(SendMessage::reply(id.clone(), ServerMessage::Error {
+                message,
+                code: Some("CHAT_ERROR".to_string()),
+            }));
+
             let conv_id = if let Some(conv_arc) = conversation_id {
                 if let Some(id) = conv_arc.lock().unwrap().clone() {
                     id
                 } else {
                     match state.ragflow_service.create_conversation("default_user".to_string()).await {
-                        Ok(new_id) => new_id,
+                        Ok(new_id) => {
+                            // Later messages in the room continue the same conversation
+                            *conv_arc.lock().unwrap() = Some(new_id.clone());
+                            new_id
+                        },
                         Err(e) => {
                             error!("Failed to create conversation: {}", e);
                             return;
@@ -218,44 +670,26 @@ impl WebSocketSessionHandler for WebSocketSession {
                                     debug!("Using local TTS service");
                                     if let Err(e) = state.speech_service.send_message(text).await {
                                         error!("Failed to generate speech: {}", e);
-                                        let error_message = json!({
-                                            "type": "error",
-                                            "message": format!("Failed to generate speech: {}", e)
-                                        });
-                                        if let Ok(error_str) = serde_json::to_string(&error_message) {
-                                            ctx_addr.do_send(SendText(error_str));
-                                        }
+                                        send_error(format!("Failed to generate speech: {}", e));
                                     }
                                 }
                             },
                             Err(e) => {
                                 error!("Error processing RAGFlow response: {}", e);
-                                let error_message = json!({
-                                    "type": "error",
-                                    "message": format!("Error processing RAGFlow response: {}", e)
-                                });
-                                if let Ok(error_str) = serde_json::to_string(&error_message) {
-                                    ctx_addr.do_send(SendText(error_str));
-                                }
+                                send_error(format!("Error processing RAGFlow response: {}", e));
                             }
                         }
                     }
                 },
                 Err(e) => {
                     error!("Failed to send message to RAGFlow: {}", e);
-                    let error_message = json!({
-                        "type": "error",
-                        "message": format!("Failed to send message to RAGFlow: {}", e)
-                    });
-                    if let Ok(error_str) = serde_json::to_string(&error_message) {
-                        ctx_addr.do_send(SendText(error_str));
-                    }
+                    send_error(format!("Failed to send message to RAGFlow: {}", e));
                 }
             }
 
             // Only send completion message if the actor is still alive
             if let Some(addr) = weak_addr.upgrade() {
-                addr.do_send(SendText("Chat message handled".to_string()));
+                addr.do_send(SendMessage::reply(id, ServerMessage::Completed { request: "chatMessage".to_string() }));
             }
         };
 
@@ -266,10 +700,6 @@ impl WebSocketSessionHandler for WebSocketSession {
         self.simulation_mode = match mode {
             "remote" => {
                 info!("Simulation mode set to Remote (GPU-accelerated)");
-                // Start GPU position updates when switching to remote mode
-                if let Some(_) = &self.state.gpu_compute {
-                    self.start_gpu_updates(ctx);
-                }
                 SimulationMode::Remote
             },
             "gpu" => {
@@ -286,79 +716,62 @@ impl WebSocketSessionHandler for WebSocketSession {
             }
         };
 
-        let response = json!({
-            "type": "simulation_mode_set",
-            "mode": mode,
-            "gpu_enabled": matches!(self.simulation_mode, SimulationMode::Remote | SimulationMode::GPU)
-        });
-        <Self as MessageHandler>::send_json_response(self, response, ctx);
+        // The room steps its simulation while any of its sessions is in remote mode
+        self.room.set_remote_simulation(self.id, matches!(self.simulation_mode, SimulationMode::Remote));
+
+        let response = ServerMessage::SimulationModeSet {
+            mode: mode.to_string(),
+            gpu_enabled: matches!(self.simulation_mode, SimulationMode::Remote | SimulationMode::GPU)
+        };
+        self.send_reply(self.request_id.clone(), response, ctx);
     }
 
     fn handle_layout(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, params: SimulationParams) {
-        let state = self.state.clone();
+        let room = self.room.clone();
         let ctx_addr = ctx.address();
         let weak_addr = ctx.address().downgrade();
+        let id = self.request_id.clone();
 
         let fut = async move {
-            if let Some(gpu_compute) = &state.gpu_compute {
+            let send_error = |message: String| ctx_addr.do_send(SendMessage::reply(id.clone(), ServerMessage::Error {
+                message,
+                code: Some("LAYOUT_ERROR".to_string()),
+            }));
+
+            if let Some(gpu_compute) = &room.gpu_compute {
                 let mut gpu = gpu_compute.write().await;
                 
                 if let Err(e) = gpu.update_simulation_params(&params) {
                     error!("Failed to update simulation parameters: {}", e);
-                    let error_message = json!({
-                        "type": "error",
-                        "message": format!("Failed to update simulation parameters: {}", e)
-                    });
-                    if let Ok(error_str) = serde_json::to_string(&error_message) {
-                        ctx_addr.do_send(SendText(error_str));
-                    }
+                    send_error(format!("Failed to update simulation parameters: {}", e));
                     return;
                 }
 
                 for _ in 0..params.iterations {
                     if let Err(e) = gpu.step() {
                         error!("GPU compute step failed: {}", e);
-                        let error_message = json!({
-                            "type": "error",
-                            "message": format!("GPU compute step failed: {}", e)
-                        });
-                        if let Ok(error_str) = serde_json::to_string(&error_message) {
-                            ctx_addr.do_send(SendText(error_str));
-                        }
+                        send_error(format!("GPU compute step failed: {}", e));
                         return;
                     }
                 }
 
                 match gpu.get_node_positions().await {
                     Ok(nodes) => {
-                        let binary_data = positions_to_binary(&nodes);
-                        ctx_addr.do_send(SendBinary(binary_data));
+                        ctx_addr.do_send(PositionFrame(Arc::new(nodes)));
                     },
                     Err(e) => {
                         error!("Failed to get GPU node positions: {}", e);
-                        let error_message = json!({
-                            "type": "error",
-                            "message": format!("Failed to get GPU node positions: {}", e)
-                        });
-                        if let Ok(error_str) = serde_json::to_string(&error_message) {
-                            ctx_addr.do_send(SendText(error_str));
-                        }
+                        send_error(format!("Failed to get GPU node positions: {}", e));
                     }
                 }
             } else {
                 error!("GPU compute service not available");
-                let error_message = json!({
-                    "type": "error",
-                    "message": "GPU compute service not available"
-                });
-                if let Ok(error_str) = serde_json::to_string(&error_message) {
-                    ctx_addr.do_send(SendText(error_str));
-                }
+                send_error("GPU compute service not available".to_string());
             }
 
             // Only send completion message if the actor is still alive
             if let Some(addr) = weak_addr.upgrade() {
-                addr.do_send(SendText("Layout update complete".to_string()));
+                addr.do_send(SendMessage::reply(id, ServerMessage::Completed { request: "recalculateLayout".to_string() }));
             }
         };
 
@@ -367,107 +780,346 @@ impl WebSocketSessionHandler for WebSocketSession {
 
     fn handle_initial_data(&mut self, ctx: &mut WebsocketContext<WebSocketSession>) {
         let state = self.state.clone();
+        let room = self.room.clone();
         let ctx_addr = ctx.address();
-        let weak_addr = ctx.address().downgrade();
+        let filters = self.view_filters();
+        let evaluated = filters.clone();
+        let clusters = self.clusters.clone();
+        let id = self.request_id.clone();
 
         let fut = async move {
-            let graph_data = state.graph_data.read().await;
-            let settings = state.settings.read().await;
-            
-            let response = json!({
-                "type": "getInitialData",
-                "graph_data": &*graph_data,
-                "settings": {
-                    "visualization": {
-                        "nodeColor": format_color(&settings.visualization.node_color),
-                        "edgeColor": format_color(&settings.visualization.edge_color),
-                        "hologramColor": format_color(&settings.visualization.hologram_color),
-                        "nodeSizeScalingFactor": settings.visualization.node_size_scaling_factor,
-                        "hologramScale": settings.visualization.hologram_scale,
-                        "hologramOpacity": settings.visualization.hologram_opacity,
-                        "edgeOpacity": settings.visualization.edge_opacity,
-                        "labelFontSize": settings.visualization.label_font_size,
-                        "fogDensity": settings.visualization.fog_density,
-                        "forceDirectedIterations": settings.visualization.force_directed_iterations,
-                        "forceDirectedRepulsion": settings.visualization.force_directed_repulsion,
-                        "forceDirectedAttraction": settings.visualization.force_directed_attraction,
-                    },
-                    "bloom": {
-                        "nodeBloomStrength": settings.bloom.node_bloom_strength,
-                        "nodeBloomRadius": settings.bloom.node_bloom_radius,
-                        "nodeBloomThreshold": settings.bloom.node_bloom_threshold,
-                        "edgeBloomStrength": settings.bloom.edge_bloom_strength,
-                        "edgeBloomRadius": settings.bloom.edge_bloom_radius,
-                        "edgeBloomThreshold": settings.bloom.edge_bloom_threshold,
-                        "environmentBloomStrength": settings.bloom.environment_bloom_strength,
-                        "environmentBloomRadius": settings.bloom.environment_bloom_radius,
-                        "environmentBloomThreshold": settings.bloom.environment_bloom_threshold,
-                    },
-                    "fisheye": {
-                        "fisheye_enabled": settings.fisheye.fisheye_enabled,
-                        "fisheye_strength": settings.fisheye.fisheye_strength,
-                        "fisheye_focus_x": settings.fisheye.fisheye_focus_x,
-                        "fisheye_focus_y": settings.fisheye.fisheye_focus_y,
-                        "fisheye_focus_z": settings.fisheye.fisheye_focus_z,
-                        "fisheye_radius": settings.fisheye.fisheye_radius,
+            let (view, graph_data) = refresh_view(&room.graph_data, filters, &room.analytics).await;
+            let graph_data = apply_clusters(&state, &room, graph_data, clusters).await;
+            let settings = ClientSettings::from(&room.effective_settings(&*state.settings.read().await));
+
+            debug!("Sending initial data with {} nodes", graph_data.nodes.len());
+            ctx_addr.do_send(SendMessage::reply(id, ServerMessage::InitialData { graph_data, settings }));
+            view
+        };
+
+        ctx.spawn(fut.into_actor(self).map(move |view, act, _| act.update_view(evaluated, view)));
+    }
+
+    fn handle_fisheye_settings(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, enabled: bool, strength: f32, focus_point: [f32; 3], radius: f32) {
+        let room = self.room.clone();
+        let ctx_addr = ctx.address();
+        let id = self.request_id.clone();
+
+        let fut = async move {
+            let response = match &room.gpu_compute {
+                Some(gpu_compute) => {
+                    let mut gpu = gpu_compute.write().await;
+                    match gpu.update_fisheye_params(enabled, strength, focus_point, radius) {
+                        Ok(()) => ServerMessage::FisheyeSettingsUpdated { enabled, strength, focus_point, radius },
+                        Err(e) => {
+                            error!("Failed to update fisheye settings: {}", e);
+                            ServerMessage::Error {
+                                message: format!("Failed to update fisheye settings: {}", e),
+                                code: Some("FISHEYE_UPDATE_ERROR".to_string()),
+                            }
+                        }
+                    }
+                },
+                None => {
+                    error!("GPU compute service not available");
+                    ServerMessage::Error {
+                        message: "GPU compute service not available".to_string(),
+                        code: Some("FISHEYE_UPDATE_ERROR".to_string()),
                     }
                 }
-            });
+            };
+            ctx_addr.do_send(SendMessage::reply(id, response));
+        };
+
+        ctx.spawn(fut.into_actor(self));
+    }
+
+    fn handle_search(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, query: String, limit: Option<usize>) {
+        if !self.require_vault(ctx) {
+            return;
+        }
+        let state = self.state.clone();
+        let ctx_addr = ctx.address();
+        let id = self.request_id.clone();
 
-            debug!("Sending initial data response: {:?}", response);
+        let fut = async move {
+            let results = state.search_index.read().await.search(&query, limit.unwrap_or(DEFAULT_RESULT_LIMIT));
+            ctx_addr.do_send(SendMessage::reply(id, ServerMessage::SearchResults { query, results }));
+        };
+
+        ctx.spawn(fut.into_actor(self));
+    }
+
+    fn handle_query(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, query: String, subgraph: bool, filter: bool) {
+        let room = self.room.clone();
+        let id = self.request_id.clone();
 
-            if let Ok(response_str) = serde_json::to_string(&response) {
-                ctx_addr.do_send(SendText(response_str));
+        let fut = async move {
+            if filter {
+                // An empty filter query shows the whole graph again
+                let filters = match query.trim().is_empty() {
+                    true => Vec::new(),
+                    false => vec![ViewFilter::Query { query: query.clone() }],
+                };
+                let (view, visible_graph) = SessionView::evaluate(&room.graph_data, filters, &room.analytics).await?;
+                let node_ids = visible_graph.nodes.iter().map(|n| n.id.clone()).collect();
+                return Ok((query, node_ids, Some(visible_graph), Some(view)));
             }
 
-            // Only send completion message if the actor is still alive
-            if let Some(addr) = weak_addr.upgrade() {
-                addr.do_send(SendText("Initial data sent".to_string()));
+            let parsed = GraphQuery::parse(&query)?;
+            let analytics = match parsed.needs_analytics() {
+                true => Some(room.analytics.analyze(&room.graph_data).await),
+                false => None,
+            };
+            let graph = room.graph_data.read().await;
+            let node_ids = parsed.evaluate(&graph, analytics.as_deref());
+            let subgraph = subgraph.then(|| query_service::induced_subgraph(&graph, &node_ids));
+            Ok::<_, QueryError>((query, node_ids, subgraph, None))
+        };
+
+        ctx.spawn(fut.into_actor(self).map(move |result, act, ctx| {
+            let response = match result {
+                Ok((query, node_ids, graph, view)) => {
+                    if let Some(view) = view {
+                        act.view = view;
+                        if act.clusters.is_some() {
+                            act.send_reply(id.clone(), ServerMessage::QueryResult { query, node_ids, graph: None, filter }, ctx);
+                            act.send_cluster_view(ctx, id);
+                            return;
+                        }
+                    }
+                    ServerMessage::QueryResult { query, node_ids, graph, filter }
+                },
+                Err(e) => ServerMessage::Error {
+                    message: format!("Invalid query: {}", e),
+                    code: Some("QUERY_ERROR".to_string()),
+                },
+            };
+            act.send_reply(id, response, ctx);
+        }));
+    }
+
+    fn handle_set_view(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, filters: Vec<ViewFilter>) {
+        let room = self.room.clone();
+        let id = self.request_id.clone();
+
+        let fut = async move {
+            let result = SessionView::evaluate(&room.graph_data, filters.clone(), &room.analytics).await;
+            (filters, result)
+        };
+
+        ctx.spawn(fut.into_actor(self).map(move |(filters, result), act, ctx| {
+            match result {
+                Ok((view, visible_graph)) => {
+                    info!("Session view set to {} of its nodes", visible_graph.nodes.len());
+                    act.view = view;
+                    act.send_reply(id.clone(), ServerMessage::ViewSet {
+                        filters,
+                        node_count: visible_graph.nodes.len(),
+                    }, ctx);
+                    if act.clusters.is_some() {
+                        act.send_cluster_view(ctx, id);
+                        return;
+                    }
+                    act.send_reply(id, ServerMessage::GraphUpdate { graph_data: visible_graph }, ctx);
+                },
+                Err(e) => act.send_reply(id, ServerMessage::Error {
+                    message: format!("Invalid view: {}", e),
+                    code: Some("VIEW_ERROR".to_string()),
+                }, ctx),
             }
+        }));
+    }
+
+    fn handle_set_cluster_view(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, enabled: bool) {
+        self.clusters = enabled.then(|| self.clusters.take().unwrap_or_default());
+        self.send_cluster_view(ctx, self.request_id.clone());
+    }
+
+    fn handle_expand_cluster(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, cluster: String, expand: bool) {
+        // Expanding implies cluster mode; collapsing keeps expanded sub-clusters for next time
+        let clusters = self.clusters.get_or_insert_with(HashSet::new);
+        if expand {
+            clusters.insert(cluster);
+        } else {
+            clusters.remove(&cluster);
+        }
+        self.send_cluster_view(ctx, self.request_id.clone());
+    }
+
+    fn handle_play_history(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, from: Option<String>, to: Option<String>, interval_ms: Option<u64>) {
+        if !self.require_vault(ctx) {
+            return;
+        }
+        let state = self.state.clone();
+        let graph_data = self.room.graph_data.clone();
+        let interval = interval_ms.map_or(HISTORY_FRAME_INTERVAL, |ms| Duration::from_millis(ms.clamp(16, 10_000)));
+        let id = self.request_id.clone();
+
+        let fut = async move {
+            let history = state.history.read().await.clone();
+            let (from, to) = resolve_time_range(&history, from.as_deref(), to.as_deref())?;
+            let frames = history.frames_between(from, to).to_vec();
+            let (nodes, edges) = history.state_before(from);
+            let graph = history.time_range(from, to).to_graph(&*graph_data.read().await);
+            let start = ServerMessage::HistoryStart { from, to, commit_count: frames.len(), graph, nodes, edges };
+            Ok::<_, String>((start, frames))
         };
 
-        ctx.spawn(fut.into_actor(self));
+        ctx.spawn(fut.into_actor(self).map(move |result, act, ctx| match result {
+            Ok((start, frames)) => {
+                info!("Playing back {} commits of graph history", frames.len());
+                act.send_reply(id, start, ctx);
+                act.start_playback(ctx, frames, interval);
+            },
+            Err(message) => act.send_reply(id, ServerMessage::Error {
+                message,
+                code: Some("HISTORY_ERROR".to_string()),
+            }, ctx),
+        }));
     }
 
-    fn handle_fisheye_settings(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, enabled: bool, strength: f32, focus_point: [f32; 3], radius: f32) {
+    fn handle_stop_history(&mut self, ctx: &mut WebsocketContext<WebSocketSession>) {
+        self.stop_playback(ctx, false);
+    }
+
+    fn handle_presence(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, update: PresenceUpdate) {
+        // Relayed to the other sessions by the relay task in main
+        if let Err(e) = self.room.presence.update(self.id, update) {
+            self.send_reply(self.request_id.clone(), ServerMessage::Error {
+                message: e,
+                code: Some("INVALID_PRESENCE".to_string()),
+            }, ctx);
+        }
+    }
+
+    fn handle_grab_node(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, node: String) {
         let state = self.state.clone();
+        let room = self.room.clone();
+        let session = self.id;
         let ctx_addr = ctx.address();
-        let weak_addr = ctx.address().downgrade();
+        let id = self.request_id.clone();
 
         let fut = async move {
-            if let Some(gpu_compute) = &state.gpu_compute {
-                let mut gpu = gpu_compute.write().await;
-                gpu.update_fisheye_params(enabled, strength, focus_point, radius);
-                
-                let response = json!({
-                    "type": "fisheye_settings_updated",
-                    "fisheye_enabled": enabled,
-                    "fisheye_strength": strength,
-                    "fisheye_focus_x": focus_point[0],
-                    "fisheye_focus_y": focus_point[1],
-                    "fisheye_focus_z": focus_point[2],
-                    "fisheye_radius": radius
-                });
-                if let Ok(response_str) = serde_json::to_string(&response) {
-                    ctx_addr.do_send(SendText(response_str));
+            let exists = room.graph_data.read().await.nodes.iter().any(|n| n.id == node);
+            let response = if !exists {
+                ServerMessage::Error {
+                    message: format!("Unknown node: {}", node),
+                    code: Some("NODE_NOT_FOUND".to_string()),
                 }
             } else {
-                error!("GPU compute service not available");
-                let error_message = json!({
-                    "type": "error",
-                    "message": "GPU compute service not available"
-                });
-                if let Ok(error_str) = serde_json::to_string(&error_message) {
-                    ctx_addr.do_send(SendText(error_str));
+                match room.locks.grab(&node, session) {
+                    Ok(()) => {
+                        let lock = NodeLock { node, session };
+                        state.websocket_manager.broadcast_except(&room.id, session, ServerMessage::NodeLocked(lock.clone()));
+                        ServerMessage::NodeLocked(lock)
+                    },
+                    Err(owner) => ServerMessage::Error {
+                        message: format!("Node {} is held by session {}", node, owner),
+                        code: Some("NODE_LOCKED".to_string()),
+                    },
+                }
+            };
+            ctx_addr.do_send(SendMessage::reply(id, response));
+        };
+
+        ctx.spawn(fut.into_actor(self));
+    }
+
+    fn handle_move_node(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, node: String, position: [f32; 3]) {
+        let id = self.request_id.clone();
+        if self.room.locks.owner(&node) != Some(self.id) {
+            self.send_reply(id, ServerMessage::Error {
+                message: format!("Grab node {} before moving it", node),
+                code: Some("NODE_NOT_GRABBED".to_string()),
+            }, ctx);
+            return;
+        }
+        let position = match node_lock_service::validate_position(position) {
+            Ok(position) => position,
+            Err(e) => {
+                self.send_reply(id, ServerMessage::Error {
+                    message: e,
+                    code: Some("INVALID_POSITION".to_string()),
+                }, ctx);
+                return;
+            }
+        };
+
+        let room = self.room.clone();
+        let session = self.id;
+        let fut = async move {
+            let index = {
+                let mut graph = room.graph_data.write().await;
+                let Some(index) = graph.nodes.iter().position(|n| n.id == node) else {
+                    return;
+                };
+                let graph_node = &mut graph.nodes[index];
+                [graph_node.x, graph_node.y, graph_node.z] = position;
+                [graph_node.vx, graph_node.vy, graph_node.vz] = [0.0; 3];
+                index
+            };
+            if let Some(gpu_compute) = &room.gpu_compute {
+                let [x, y, z] = position;
+                let update = GPUNodePositionUpdate { x, y, z, vx: 0.0, vy: 0.0, vz: 0.0 };
+                if let Err(e) = gpu_compute.write().await.merge_positions(&[(index, update)]) {
+                    error!("Failed to move node {}: {}", node, e);
                 }
             }
+            // Relayed to the other sessions by the relay task in main
+            room.locks.record_move(NodeMove { node, position, session });
+        };
 
-            // Only send completion message if the actor is still alive
-            if let Some(addr) = weak_addr.upgrade() {
-                addr.do_send(SendText("Fisheye settings updated".to_string()));
+        ctx.spawn(fut.into_actor(self));
+    }
+
+    fn handle_release_node(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, node: String) {
+        let id = self.request_id.clone();
+        let response = if self.room.locks.release(&node, self.id) {
+            self.state.websocket_manager.broadcast_except(&self.room.id, self.id, ServerMessage::NodeUnlocked { node: node.clone() });
+            ServerMessage::NodeUnlocked { node }
+        } else {
+            ServerMessage::Error {
+                message: format!("Node {} is not held by this session", node),
+                code: Some("NODE_NOT_GRABBED".to_string()),
             }
         };
+        self.send_reply(id, response, ctx);
+    }
+
+    fn handle_tour(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, command: TourCommand) {
+        let state = self.state.clone();
+        let room = self.room.clone();
+        let ctx_addr = ctx.address();
+        let id = self.request_id.clone();
+        let request = match &command {
+            TourCommand::Start(_) => "startTour",
+            TourCommand::Step(by) if *by < 0 => "previousTourStep",
+            TourCommand::Step(_) => "nextTourStep",
+            TourCommand::Stop => "stopTour",
+        };
+
+        let fut = async move {
+            let response = match tour_handler::run(&state, &room, command).await {
+                Ok(()) => ServerMessage::Completed { request: request.to_string() },
+                Err(e) => ServerMessage::Error {
+                    message: e,
+                    code: Some("TOUR_ERROR".to_string()),
+                },
+            };
+            ctx_addr.do_send(SendMessage::reply(id, response));
+        };
 
         ctx.spawn(fut.into_actor(self));
     }
+
+    fn handle_frames_received(&mut self, ctx: &mut WebsocketContext<WebSocketSession>, count: u64) {
+        let Some(flow) = self.flow.as_mut() else {
+            // Frames acknowledged after a later hello turned flow control off
+            debug!("Ignoring frame acknowledgement without flow control");
+            return;
+        };
+        let frame = flow.acknowledge(count, Instant::now());
+        self.pace_positions(frame, ctx);
+    }
 }
@@ -2,24 +2,39 @@ use actix_files::Files;
 use actix_web::{web, App, HttpServer, middleware, HttpResponse};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use std::collections::HashMap;
 use std::env;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, MissedTickBehavior};
 
 use crate::app_state::AppState;
 use crate::config::Settings;
 use crate::handlers::{
+    analytics_handler,
+    cluster_handler,
     file_handler, 
     graph_handler, 
+    history_handler,
+    import_handler,
     ragflow_handler, 
     visualization_handler,
     perplexity_handler,
+    query_handler,
+    recording_handler,
+    room_handler,
+    search_handler,
+    session_handler,
+    tour_handler,
 };
+use crate::handlers::websocket_handlers::GPU_UPDATE_INTERVAL;
 use crate::models::graph::GraphData;
+use crate::services::content_store::{ContentStore, CONTENT_STORE_PATH};
+use crate::services::embedding_service;
+use crate::services::import_service;
 use crate::services::file_service::{GitHubService, RealGitHubService, FileService};
 use crate::services::perplexity_service::{PerplexityService, PerplexityServiceImpl};
 use crate::services::ragflow_service::RAGFlowService;
+use crate::services::room_service::DEFAULT_ROOM;
 use crate::services::speech_service::SpeechService;
+use crate::services::tour_service::{TourStore, TOUR_STORE_PATH};
 use crate::services::graph_service::GraphService;
 use crate::services::github_service::{GitHubPRService, RealGitHubPRService};
 use crate::utils::websocket_manager::WebSocketManager;
@@ -34,44 +49,62 @@ mod utils;
 
 async fn initialize_graph_data(app_state: &web::Data<AppState>) -> std::io::Result<()> {
     log::info!("Starting graph data initialization...");
-    
-    let mut metadata_map = HashMap::new();
+
+    // Build from local state first so the server comes up without network access
+    rebuild_graph(app_state).await?;
+    if app_state.graph_source.read().await.is_some() {
+        return Ok(());
+    }
+
+    let mut metadata_map = app_state.content_store.blocking(|store| store.metadata()).await
+        .map_err(|e| std::io::Error::other(format!("Failed to read content store: {}", e)))?;
     log::info!("Fetching and processing files from GitHub...");
     match FileService::fetch_and_process_files(&*app_state.github_service, app_state.settings.clone(), &mut metadata_map).await {
         Ok(processed_files) => {
             log::info!("Successfully processed {} files", processed_files.len());
             log::debug!("Processed files: {:?}", processed_files.iter().map(|f| &f.file_name).collect::<Vec<_>>());
 
-            let mut file_cache = app_state.file_cache.write().await;
-            for processed_file in &processed_files {
-                log::debug!("Caching file: {}", processed_file.file_name);
-                file_cache.insert(processed_file.file_name.clone(), processed_file.content.clone());
+            let stored = app_state.content_store.blocking(move |store| {
+                FileService::store_processed_files(store, &processed_files, &metadata_map)
+            }).await;
+            if let Err(e) = stored {
+                log::error!("Failed to update content store: {}", e);
+                return Err(std::io::Error::other(format!("Failed to update content store: {}", e)));
             }
-            drop(file_cache); // Explicitly drop the write lock
 
             log::info!("Building graph from processed files...");
-            match GraphService::build_graph(&app_state).await {
-                Ok(graph_data) => {
-                    let mut graph = app_state.graph_data.write().await;
-                    *graph = graph_data;
-                    log::info!("Graph data structure initialized successfully");
-                    log::debug!("Graph stats: {} nodes, {} edges", 
-                        graph.nodes.len(), 
-                        graph.edges.len()
-                    );
-                    Ok(())
-                },
-                Err(e) => {
-                    log::error!("Failed to build graph data: {}", e);
-                    log::error!("Error details: {:?}", e);
-                    Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to build graph data: {}", e)))
-                }
-            }
+            rebuild_graph(app_state).await
+        },
+        Err(e) => {
+            log::warn!("Could not fetch updates from GitHub, serving local content: {:?}", e);
+            Ok(())
+        }
+    }
+}
+
+async fn rebuild_graph(app_state: &web::Data<AppState>) -> std::io::Result<()> {
+    if let Err(e) = app_state.refresh_search_index().await {
+        log::error!("Failed to update search index: {}", e);
+    }
+    if let Err(e) = app_state.refresh_embeddings().await {
+        log::error!("Failed to update page embeddings: {}", e);
+    }
+
+    match GraphService::build_graph(app_state).await {
+        Ok(graph_data) => {
+            let mut graph = app_state.graph_data.write().await;
+            *graph = graph_data;
+            log::info!(
+                "Graph data built with {} nodes and {} edges",
+                graph.nodes.len(),
+                graph.edges.len()
+            );
+            Ok(())
         },
         Err(e) => {
-            log::error!("Error processing files: {:?}", e);
+            log::error!("Failed to build graph data: {}", e);
             log::error!("Error details: {:?}", e);
-            Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Error processing files: {:?}", e)))
+            Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to build graph data: {}", e)))
         }
     }
 }
@@ -83,11 +116,65 @@ async fn test_speech_service(app_state: web::Data<AppState>) -> HttpResponse {
     }
 }
 
+fn validate_metadata_command(repair: bool) -> std::io::Result<()> {
+    let store = ContentStore::open(CONTENT_STORE_PATH)
+        .map_err(|e| std::io::Error::other(format!("Failed to open content store: {}", e)))?;
+    let report = FileService::validate_metadata(&store, repair)
+        .map_err(|e| std::io::Error::other(format!("Failed to validate metadata: {}", e)))?;
+
+    for file_name in &report.missing_files {
+        println!("missing:   {}", file_name);
+    }
+    for file_name in &report.untracked_files {
+        println!("untracked: {}", file_name);
+    }
+    for file_name in &report.stale_files {
+        println!("stale:     {}", file_name);
+    }
+    if report.outdated_metadata_json {
+        println!("metadata.json differs from the content store");
+    }
+
+    if report.is_consistent() {
+        println!("The content store and metadata.json are consistent with the markdown files");
+    } else if report.repaired {
+        println!("Content store and metadata.json repaired");
+    } else {
+        println!("The content store is inconsistent; rerun with --repair to fix it");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 // Simple health check endpoint that returns 200 OK when the service is running
 async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
 
+async fn relay_changes_periodically(app_state: web::Data<AppState>, period: Duration) {
+    let mut interval = interval(period);
+
+    loop {
+        interval.tick().await;
+        for room in app_state.rooms.all() {
+            app_state.websocket_manager.relay_changes(&room);
+        }
+    }
+}
+
+/// Step every room's simulation on one shared clock, however many sessions watch it
+async fn step_simulations_periodically(app_state: web::Data<AppState>) {
+    let mut interval = interval(GPU_UPDATE_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        for room in app_state.rooms.all().iter().filter(|room| room.is_simulating()) {
+            app_state.websocket_manager.step_simulation(room).await;
+        }
+    }
+}
+
 async fn randomize_nodes_periodically(app_state: web::Data<AppState>) {
     let mut interval = interval(Duration::from_secs(30));
 
@@ -102,8 +189,7 @@ async fn randomize_nodes_periodically(app_state: web::Data<AppState>) {
         }
 
         // Notify WebSocket clients about the updated graph data
-        let graph_data = app_state.graph_data.read().await;
-        if let Err(e) = app_state.websocket_manager.broadcast_graph_update(&graph_data).await {
+        if let Err(e) = app_state.websocket_manager.broadcast_graph_update(DEFAULT_ROOM).await {
             log::error!("Failed to broadcast graph update: {}", e);
         }
         log::debug!("Completed periodic graph rebuild");
@@ -115,6 +201,13 @@ async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
     std::env::set_var("RUST_LOG", "debug");
     env_logger::init();
+
+    // `webxr-graph validate-metadata [--repair]` reconciles the content store with disk and exits
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("validate-metadata") {
+        return validate_metadata_command(args.iter().any(|arg| arg == "--repair"));
+    }
+
     log::info!("Starting WebXR Graph Server");
 
     log::info!("Loading settings...");
@@ -129,7 +222,24 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
-    let file_cache = Arc::new(RwLock::new(HashMap::new()));
+    log::info!("Opening content store...");
+    let content_store = match ContentStore::open(CONTENT_STORE_PATH) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            log::error!("Failed to open content store: {:?}", e);
+            return Err(std::io::Error::other(format!("Failed to open content store: {:?}", e)));
+        }
+    };
+    let tours = match TourStore::open(TOUR_STORE_PATH) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            log::error!("Failed to open tour store: {:?}", e);
+            return Err(std::io::Error::other(format!("Failed to open tour store: {:?}", e)));
+        }
+    };
+    if let Err(e) = FileService::seed_content_store(&content_store) {
+        log::error!("Failed to import local files into content store: {}", e);
+    }
     let graph_data = Arc::new(RwLock::new(GraphData::default()));
     
     log::info!("Initializing GitHub service...");
@@ -215,9 +325,11 @@ async fn main() -> std::io::Result<()> {
         return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to initialize SpeechService: {:?}", e)));
     }
 
+    let embedder = embedding_service::embedder_from_settings(&*settings.read().await);
+
     let app_state = web::Data::new(AppState::new(
         graph_data,
-        file_cache,
+        content_store,
         settings.clone(),
         github_service,
         perplexity_service,
@@ -227,8 +339,22 @@ async fn main() -> std::io::Result<()> {
         gpu_compute,
         ragflow_conversation_id,
         github_pr_service,
+        embedder,
+        tours,
     ));
 
+    match import_service::graph_source_from_settings(&settings.read().await.import) {
+        Ok(Some(source)) => {
+            log::info!("Serving graph imported from {}", source.name());
+            *app_state.graph_source.write().await = Some(source);
+        },
+        Ok(None) => {},
+        Err(e) => {
+            log::error!("Invalid import settings: {}", e);
+            return Err(std::io::Error::other(format!("Invalid import settings: {}", e)));
+        }
+    }
+
     log::info!("Initializing graph data...");
     if let Err(e) = initialize_graph_data(&app_state).await {
         log::error!("Failed to initialize graph data: {:?}", e);
@@ -241,6 +367,28 @@ async fn main() -> std::io::Result<()> {
         return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to initialize RAGflow conversation: {:?}", e)));
     }
 
+    if settings.read().await.history.enabled {
+        // Ingesting history can take many requests, so don't hold up the server for it
+        let history_state = app_state.clone();
+        tokio::spawn(async move {
+            match history_state.refresh_history().await {
+                Ok(frames) => log::info!("Loaded graph history with {} changes", frames),
+                Err(e) => log::error!("Failed to load graph history: {}", e),
+            }
+        });
+    }
+
+    let relay_interval = Duration::from_millis(settings.read().await.websocket.presence_interval_ms.max(10));
+    let relay_state = app_state.clone();
+    tokio::spawn(async move {
+        relay_changes_periodically(relay_state, relay_interval).await;
+    });
+
+    let simulation_state = app_state.clone();
+    tokio::spawn(async move {
+        step_simulations_periodically(simulation_state).await;
+    });
+
     // Spawn the randomization task
     let randomization_state = app_state.clone();
     tokio::spawn(async move {
@@ -260,10 +408,66 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/api/files")
                     .route("/fetch", web::get().to(file_handler::fetch_and_process_files))
+                    .route("/publish-preview", web::get().to(file_handler::preview_publish_filter))
+                    .route("/content/{file_name:.*}", web::get().to(file_handler::get_file_content))
             )
             .service(
                 web::scope("/api/graph")
                     .route("/data", web::get().to(graph_handler::get_graph_data))
+                    .route("/nodes", web::get().to(graph_handler::query_nodes))
+                    .route("/export", web::get().to(graph_handler::export_graph))
+                    .route("/expand/{page:.*}", web::post().to(graph_handler::expand_page))
+                    .route("/collapse/{page:.*}", web::post().to(graph_handler::collapse_page))
+                    .route("/analytics", web::get().to(analytics_handler::get_analytics))
+                    .route("/analytics/apply", web::post().to(analytics_handler::apply_analytics))
+                    .route("/path", web::get().to(analytics_handler::shortest_path))
+                    .route("/neighbourhood", web::get().to(analytics_handler::neighbourhood))
+                    .route("/query", web::get().to(query_handler::query_graph))
+                    .route("/clusters", web::get().to(cluster_handler::get_clusters))
+                    .route("/history", web::get().to(history_handler::get_history))
+                    .route("/history/refresh", web::post().to(history_handler::refresh_history))
+                    .service(
+                        web::resource("/import")
+                            .app_data(web::PayloadConfig::new(import_handler::MAX_IMPORT_SIZE))
+                            .route(web::post().to(import_handler::import_graph))
+                            .route(web::delete().to(import_handler::clear_import))
+                    )
+            )
+            .route("/api/search", web::get().to(search_handler::search))
+            .service(
+                web::scope("/api/tours")
+                    .route("", web::get().to(tour_handler::list_tours))
+                    .route("/{name}", web::get().to(tour_handler::get_tour))
+                    .route("/{name}", web::put().to(tour_handler::put_tour))
+                    .route("/{name}", web::delete().to(tour_handler::delete_tour))
+                    .route("/{name}/play", web::post().to(tour_handler::play_tour))
+                    .route("/{name}/next", web::post().to(tour_handler::next_step))
+                    .route("/{name}/previous", web::post().to(tour_handler::previous_step))
+                    .route("/{name}/stop", web::post().to(tour_handler::stop_tour))
+            )
+            .service(
+                web::scope("/api/rooms")
+                    .route("", web::get().to(room_handler::list_rooms))
+                    .route("", web::post().to(room_handler::create_room))
+                    .route("/{id}", web::get().to(room_handler::get_room))
+                    .route("/{id}", web::delete().to(room_handler::delete_room))
+                    .service(
+                        web::resource("/{id}/import")
+                            .app_data(web::PayloadConfig::new(import_handler::MAX_IMPORT_SIZE))
+                            .route(web::post().to(room_handler::import_into_room))
+                    )
+            )
+            .service(
+                web::scope("/api/recordings")
+                    .route("", web::get().to(recording_handler::list_recordings))
+                    .route("/{id}", web::get().to(recording_handler::download_recording))
+                    .route("/{id}", web::delete().to(recording_handler::delete_recording))
+            )
+            .service(
+                web::scope("/api/sessions")
+                    .route("", web::get().to(session_handler::list_sessions))
+                    .route("/{id}", web::delete().to(session_handler::disconnect_session))
+                    .route("/{id}/message", web::post().to(session_handler::send_to_session))
             )
             .service(
                 web::scope("/api/chat")
@@ -280,6 +484,8 @@ async fn main() -> std::io::Result<()> {
                     .route("/process", web::post().to(perplexity_handler::process_files))
             )
             .route("/ws", web::get().to(WebSocketManager::handle_websocket))
+            .route("/ws/schema", web::get().to(WebSocketManager::handle_schema))
+            .route("/ws/replay/{id}", web::get().to(recording_handler::replay))
             .route("/test_speech", web::get().to(test_speech_service))
             .service(
                 Files::new("/", "/app/data/public/dist").index_file("index.html")
@@ -1,12 +1,54 @@
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use crate::models::node::Node;
+use crate::models::reference::ReferenceKind;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Semantic type of an edge. The discriminant is what the shader sees in `GPUEdge::kind`.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    #[default]
+    Wikilink = 0,
+    Tag = 1,
+    Embed = 2,
+    BlockRef = 3,
+    Mention = 4,
+    SemanticSimilarity = 5,
+    AiSuggested = 6,
+    /// Page or block to a block nested in it
+    Contains = 7,
+}
+
+impl From<ReferenceKind> for EdgeKind {
+    fn from(kind: ReferenceKind) -> Self {
+        match kind {
+            ReferenceKind::Link => EdgeKind::Wikilink,
+            ReferenceKind::Tag => EdgeKind::Tag,
+            ReferenceKind::Embed => EdgeKind::Embed,
+            ReferenceKind::BlockRef => EdgeKind::BlockRef,
+            ReferenceKind::Mention => EdgeKind::Mention,
+        }
+    }
+}
+
+fn default_directed() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Edge {
     pub source: String,
     pub target_node: String,
     pub weight: f32,
+    #[serde(default)]
+    pub kind: EdgeKind,
+    /// Whether `source -> target_node` is meaningful, e.g. a link from one page to another
+    #[serde(default = "default_directed")]
+    pub directed: bool,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
 }
 
 // GPU representation of an edge, must match the shader's Edge struct
@@ -16,22 +58,41 @@ pub struct GPUEdge {
     pub source: u32,      // 4 bytes
     pub target_idx: u32,  // 4 bytes
     pub weight: f32,      // 4 bytes
-    pub padding1: u32,    // 4 bytes
-    pub padding2: u32,    // 4 bytes
+    pub kind: u32,        // 4 bytes (EdgeKind discriminant)
+    pub flags: u32,       // 4 bytes (bit 0: directed)
     pub padding3: u32,    // 4 bytes
     pub padding4: u32,    // 4 bytes
     pub padding5: u32,    // 4 bytes
 }
 
 impl Edge {
+    /// Creates a directed wikilink edge
     pub fn new(source: String, target_node: String, weight: f32) -> Self {
         Self {
             source,
             target_node,
             weight,
+            kind: EdgeKind::default(),
+            directed: true,
+            attributes: HashMap::new(),
         }
     }
 
+    pub fn with_kind(mut self, kind: EdgeKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_directed(mut self, directed: bool) -> Self {
+        self.directed = directed;
+        self
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: impl ToString) -> Self {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
     pub fn to_gpu_edge(&self, nodes: &[Node]) -> GPUEdge {
         // Create a temporary HashMap for efficient lookups
         let node_map: HashMap<_, _> = nodes.iter()
@@ -46,8 +107,8 @@ impl Edge {
             source: source_idx,
             target_idx,
             weight: self.weight,
-            padding1: 0,
-            padding2: 0,
+            kind: self.kind as u32,
+            flags: self.directed as u32,
             padding3: 0,
             padding4: 0,
             padding5: 0,
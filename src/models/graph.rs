@@ -4,10 +4,11 @@ use super::node::Node;
 use super::edge::Edge;
 use super::metadata::Metadata;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::collections::HashMap;
 
 /// Represents the graph data structure containing nodes and edges.
-#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[derive(Default, Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct GraphData {
     /// List of nodes in the graph.
     pub nodes: Vec<Node>,
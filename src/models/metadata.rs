@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::collections::HashMap;
 
+use super::reference::ReferenceKind;
+
 /// Stores metadata about a processed file.
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 pub struct Metadata {
     pub file_name: String,
     pub file_size: usize,
@@ -14,4 +17,71 @@ pub struct Metadata {
     pub last_perplexity_process: Option<DateTime<Utc>>,
     pub last_modified: DateTime<Utc>,
     pub topic_counts: HashMap<String, usize>,
+    /// Reference counts per target page, broken down by reference type
+    #[serde(default)]
+    pub reference_counts: HashMap<String, HashMap<ReferenceKind, usize>>,
+    /// Alternative names declared with `alias::`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Block uuids declared with `id::` in this page
+    #[serde(default)]
+    pub block_ids: Vec<String>,
+    /// Page properties and front matter, keys lowercased
+    #[serde(default)]
+    pub properties: HashMap<String, PropertyValue>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `title` property or front matter, falling back to the first heading
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated: Option<DateTime<Utc>>,
+    /// Vector used for semantic similarity edges
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<PageEmbedding>,
+}
+
+/// A page's embedding and what it was computed from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct PageEmbedding {
+    /// `Embedder::model_id` of the model that produced the vector
+    pub model: String,
+    /// Content version the vector was computed from
+    pub version: String,
+    pub vector: Vec<f32>,
+}
+
+/// A typed page property or node attribute value.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Bool(bool),
+    Number(f64),
+    Date(DateTime<Utc>),
+    Text(String),
+    List(Vec<String>),
+}
+
+impl PropertyValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Case-insensitive match against a query string. Lists match if any item
+    /// matches, dates match on their `YYYY-MM-DD` prefix.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim();
+        match self {
+            PropertyValue::Bool(b) => query.parse::<bool>().is_ok_and(|q| q == *b),
+            PropertyValue::Number(n) => query.parse::<f64>().is_ok_and(|q| q == *n),
+            PropertyValue::Date(d) => d.to_rfc3339().starts_with(query),
+            PropertyValue::Text(s) => s.eq_ignore_ascii_case(query),
+            PropertyValue::List(items) => items.iter().any(|item| item.eq_ignore_ascii_case(query)),
+        }
+    }
 }
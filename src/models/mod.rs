@@ -3,4 +3,5 @@ pub mod graph;
 pub mod node;
 pub mod edge;
 pub mod metadata;
+pub mod reference;
 pub mod simulation_params; // Add this line
\ No newline at end of file
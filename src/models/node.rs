@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::collections::HashMap;
 use bytemuck::{Pod, Zeroable};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use super::metadata::PropertyValue;
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct Node {
     pub id: String,
     pub label: String,
-    pub metadata: HashMap<String, String>,
+    /// Typed attributes: page properties, tags, dates, and analysis results
+    pub metadata: HashMap<String, PropertyValue>,
     pub x: f32,
     pub y: f32,
     pub z: f32,
@@ -36,6 +40,17 @@ impl Node {
         }
     }
 
+    /// Whether every `key=value` pair matches this node's attributes
+    pub fn matches_attributes<'a>(&self, filters: impl IntoIterator<Item = (&'a String, &'a String)>) -> bool {
+        filters.into_iter().all(|(key, value)| {
+            match key.as_str() {
+                "id" => self.id.eq_ignore_ascii_case(value),
+                "label" => self.label.eq_ignore_ascii_case(value),
+                _ => self.metadata.get(&key.to_lowercase()).is_some_and(|v| v.matches(value)),
+            }
+        })
+    }
+
     /// Convert file size to quantized mass value (0-255)
     fn calculate_mass(&self) -> u8 {
         // Scale file size logarithmically to 0-255 range
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// The syntactic form a reference between pages takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    /// `[[page]]`, `[[page|alias]]` or `[alias](page.md)`
+    Link,
+    /// `#tag`, `#[[multi word]]` or a `tags::` property
+    Tag,
+    /// `{{embed [[page]]}}`, `{{embed ((uuid))}}` or `![[page]]`
+    Embed,
+    /// `((uuid))`
+    BlockRef,
+    /// Plain-text occurrence of a known page name
+    Mention,
+}
+
+/// A single typed reference found in a page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    /// Target page name. For unresolved block references this is empty.
+    pub target: String,
+    /// Referenced block uuid for block references and block embeds.
+    pub block: Option<String>,
+    /// Display text when it differs from the target.
+    pub alias: Option<String>,
+}
+
+impl Reference {
+    pub fn page(kind: ReferenceKind, target: impl Into<String>) -> Self {
+        Self {
+            kind,
+            target: target.into(),
+            block: None,
+            alias: None,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 /// Enum defining different simulation computation modes
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -16,7 +17,7 @@ pub enum SimulationPhase {
 }
 
 /// Parameters controlling the force-directed graph layout simulation
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[repr(C)]
 pub struct SimulationParams {
     pub iterations: u32,           // Range: 1-500, Default: varies by phase
@@ -0,0 +1,594 @@
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+use crate::models::graph::GraphData;
+use crate::models::metadata::PropertyValue;
+
+const PAGERANK_DAMPING: f64 = 0.85;
+const PAGERANK_MAX_ITERATIONS: usize = 100;
+const PAGERANK_TOLERANCE: f64 = 1e-9;
+
+/// Importance and grouping of a single node.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeMetrics {
+    pub in_degree: usize,
+    pub out_degree: usize,
+    /// Distinct neighbours, ignoring direction
+    pub degree: usize,
+    pub pagerank: f64,
+    /// Normalized to [0, 1]
+    pub betweenness: f64,
+    /// Louvain community, numbered by size with 0 the largest
+    pub community: usize,
+    /// Connected component, numbered by size with 0 the largest
+    pub component: usize,
+}
+
+/// Whole-graph analytics for one topology version.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphAnalytics {
+    pub version: u64,
+    pub nodes: HashMap<String, NodeMetrics>,
+    pub community_count: usize,
+    pub modularity: f64,
+    pub component_count: usize,
+}
+
+impl GraphAnalytics {
+    /// Write the metrics into node attributes so the client can size and colour by them.
+    pub fn apply_to_nodes(&self, graph: &mut GraphData) {
+        for node in &mut graph.nodes {
+            let Some(metrics) = self.nodes.get(&node.id) else {
+                continue;
+            };
+            let attributes = [
+                ("in_degree", metrics.in_degree as f64),
+                ("out_degree", metrics.out_degree as f64),
+                ("degree", metrics.degree as f64),
+                ("pagerank", metrics.pagerank),
+                ("betweenness", metrics.betweenness),
+                ("community", metrics.community as f64),
+                ("component", metrics.component as f64),
+            ];
+            for (key, value) in attributes {
+                node.metadata.insert(key.to_string(), PropertyValue::Number(value));
+            }
+        }
+    }
+}
+
+/// Analytics of the most recently analysed topology. Positions change every frame,
+/// so results are keyed by `topology_version` rather than recomputed per request.
+#[derive(Default)]
+pub struct AnalyticsCache {
+    latest: Mutex<Option<Arc<GraphAnalytics>>>,
+}
+
+impl AnalyticsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Analytics of `graph`, computed on the calling thread if its topology changed.
+    /// The lock is only held to look up and store results, not while computing.
+    pub fn get_or_compute(&self, graph: &GraphData) -> Arc<GraphAnalytics> {
+        let version = topology_version(graph);
+        if let Some(analytics) = self.cached(version) {
+            return analytics;
+        }
+        let analytics = Arc::new(analyze(graph, version));
+        *self.latest.lock().unwrap() = Some(analytics.clone());
+        analytics
+    }
+
+    /// Analytics of the shared graph for async callers. Betweenness alone is O(V·E), so
+    /// a cache miss is computed on the blocking thread pool from a copy of the graph,
+    /// without holding its lock meanwhile.
+    pub async fn analyze(self: &Arc<Self>, graph: &RwLock<GraphData>) -> Arc<GraphAnalytics> {
+        let graph = {
+            let graph = graph.read().await;
+            if let Some(analytics) = self.cached(topology_version(&graph)) {
+                return analytics;
+            }
+            graph.clone()
+        };
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || cache.get_or_compute(&graph))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    fn cached(&self, version: u64) -> Option<Arc<GraphAnalytics>> {
+        self.latest.lock().unwrap().as_ref().filter(|a| a.version == version).cloned()
+    }
+}
+
+/// How many edges a node has, without the whole-graph metrics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Degrees {
+    pub in_degree: usize,
+    pub out_degree: usize,
+    /// Distinct neighbours, ignoring direction
+    pub degree: usize,
+}
+
+/// Degrees of every node, in O(V + E); for callers that need no other metric.
+pub fn degrees(graph: &GraphData) -> HashMap<String, Degrees> {
+    let adjacency = Adjacency::new(graph);
+    adjacency.degrees().into_iter()
+        .enumerate()
+        .map(|(i, degrees)| (adjacency.ids[i].to_string(), degrees))
+        .collect()
+}
+
+/// Fingerprint of node ids and edges; node positions and attributes don't affect it.
+pub fn topology_version(graph: &GraphData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for node in &graph.nodes {
+        node.id.hash(&mut hasher);
+    }
+    for edge in &graph.edges {
+        edge.source.hash(&mut hasher);
+        edge.target_node.hash(&mut hasher);
+        edge.kind.hash(&mut hasher);
+        edge.directed.hash(&mut hasher);
+        edge.weight.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Index-based view of a graph. Self-loops and edges to unknown nodes are dropped.
+struct Adjacency<'a> {
+    ids: Vec<&'a str>,
+    index: HashMap<&'a str, usize>,
+    /// Weighted out-edges; undirected edges appear in both directions
+    outgoing: Vec<Vec<(usize, f64)>>,
+    /// Neighbours ignoring direction, with parallel edges merged by summing weights
+    neighbours: Vec<BTreeMap<usize, f64>>,
+}
+
+impl<'a> Adjacency<'a> {
+    fn new(graph: &'a GraphData) -> Self {
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        let index: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let mut outgoing = vec![Vec::new(); ids.len()];
+        let mut neighbours = vec![BTreeMap::new(); ids.len()];
+
+        for edge in &graph.edges {
+            let (Some(&s), Some(&t)) = (index.get(edge.source.as_str()), index.get(edge.target_node.as_str())) else {
+                continue;
+            };
+            if s == t {
+                continue;
+            }
+            let weight = (edge.weight as f64).max(0.0);
+            outgoing[s].push((t, weight));
+            if !edge.directed {
+                outgoing[t].push((s, weight));
+            }
+            *neighbours[s].entry(t).or_insert(0.0) += weight;
+            *neighbours[t].entry(s).or_insert(0.0) += weight;
+        }
+
+        Self { ids, index, outgoing, neighbours }
+    }
+
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn degrees(&self) -> Vec<Degrees> {
+        let mut in_degree = vec![0; self.len()];
+        for targets in &self.outgoing {
+            for &(t, _) in targets {
+                in_degree[t] += 1;
+            }
+        }
+        (0..self.len())
+            .map(|i| Degrees {
+                in_degree: in_degree[i],
+                out_degree: self.outgoing[i].len(),
+                degree: self.neighbours[i].len(),
+            })
+            .collect()
+    }
+
+    /// Breadth-first hop distances from the nearest of `starts`, ignoring direction, up to `max_hops`.
+    fn bfs(&self, starts: &[usize], max_hops: usize) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let mut distance = vec![None; self.len()];
+        let mut previous = vec![None; self.len()];
+        let mut queue = VecDeque::from(starts.to_vec());
+        for &start in starts {
+            distance[start] = Some(0);
+        }
+
+        while let Some(v) = queue.pop_front() {
+            let d = distance[v].unwrap();
+            if d == max_hops {
+                continue;
+            }
+            for &w in self.neighbours[v].keys() {
+                if distance[w].is_none() {
+                    distance[w] = Some(d + 1);
+                    previous[w] = Some(v);
+                    queue.push_back(w);
+                }
+            }
+        }
+        (distance, previous)
+    }
+}
+
+/// Compute every whole-graph metric.
+pub fn analyze(graph: &GraphData, version: u64) -> GraphAnalytics {
+    let adjacency = Adjacency::new(graph);
+    let n = adjacency.len();
+
+    let degrees = adjacency.degrees();
+    let pagerank = pagerank(&adjacency);
+    let betweenness = betweenness(&adjacency);
+    let (communities, modularity) = louvain(&adjacency);
+    let components = connected_components(&adjacency);
+
+    let nodes = (0..n)
+        .map(|i| (adjacency.ids[i].to_string(), NodeMetrics {
+            in_degree: degrees[i].in_degree,
+            out_degree: degrees[i].out_degree,
+            degree: degrees[i].degree,
+            pagerank: pagerank[i],
+            betweenness: betweenness[i],
+            community: communities[i],
+            component: components[i],
+        }))
+        .collect();
+
+    GraphAnalytics {
+        version,
+        nodes,
+        community_count: communities.iter().max().map_or(0, |c| c + 1),
+        modularity,
+        component_count: components.iter().max().map_or(0, |c| c + 1),
+    }
+}
+
+/// Shortest path by hop count, ignoring edge direction. `None` if either node is
+/// unknown or they are not connected.
+pub fn shortest_path(graph: &GraphData, from: &str, to: &str) -> Option<Vec<String>> {
+    let adjacency = Adjacency::new(graph);
+    let start = *adjacency.index.get(from)?;
+    let goal = *adjacency.index.get(to)?;
+    let (distance, previous) = adjacency.bfs(&[start], usize::MAX);
+    distance[goal]?;
+
+    let mut path = vec![goal];
+    while let Some(v) = previous[*path.last().unwrap()] {
+        path.push(v);
+    }
+    path.reverse();
+    Some(path.into_iter().map(|i| adjacency.ids[i].to_string()).collect())
+}
+
+/// Nodes within `k` hops of `center`, ignoring edge direction, with their distance.
+/// `None` if the center node is unknown.
+pub fn k_hop_neighbourhood(graph: &GraphData, center: &str, k: usize) -> Option<Vec<(String, usize)>> {
+    let adjacency = Adjacency::new(graph);
+    let start = *adjacency.index.get(center)?;
+    let (distance, _) = adjacency.bfs(&[start], k);
+
+    let mut nodes: Vec<(String, usize)> = distance.into_iter()
+        .enumerate()
+        .filter_map(|(i, d)| d.map(|d| (adjacency.ids[i].to_string(), d)))
+        .collect();
+    nodes.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    Some(nodes)
+}
+
+/// Louvain community of each node in graph order, numbered by size with 0 the largest.
+pub fn communities(graph: &GraphData) -> Vec<usize> {
+    louvain(&Adjacency::new(graph)).0
+}
+
+/// Nodes within `k` hops of any of `sources`, ignoring edge direction, sources included.
+pub fn nodes_within_hops(graph: &GraphData, sources: &HashSet<&str>, k: usize) -> HashSet<String> {
+    let adjacency = Adjacency::new(graph);
+    let starts: Vec<usize> = sources.iter().filter_map(|id| adjacency.index.get(id).copied()).collect();
+    let (distance, _) = adjacency.bfs(&starts, k);
+    distance.iter()
+        .enumerate()
+        .filter(|(_, d)| d.is_some())
+        .map(|(i, _)| adjacency.ids[i].to_string())
+        .collect()
+}
+
+/// Weighted PageRank by power iteration; dangling nodes spread their rank evenly.
+fn pagerank(adjacency: &Adjacency) -> Vec<f64> {
+    let n = adjacency.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let out_weight: Vec<f64> = adjacency.outgoing.iter()
+        .map(|targets| targets.iter().map(|(_, w)| w).sum())
+        .collect();
+    let mut rank = vec![1.0 / n as f64; n];
+
+    for _ in 0..PAGERANK_MAX_ITERATIONS {
+        let dangling: f64 = (0..n).filter(|&i| out_weight[i] == 0.0).map(|i| rank[i]).sum();
+        let base = (1.0 - PAGERANK_DAMPING) / n as f64 + PAGERANK_DAMPING * dangling / n as f64;
+        let mut next = vec![base; n];
+        for (v, targets) in adjacency.outgoing.iter().enumerate() {
+            if out_weight[v] == 0.0 {
+                continue;
+            }
+            for &(t, w) in targets {
+                next[t] += PAGERANK_DAMPING * rank[v] * w / out_weight[v];
+            }
+        }
+        let delta: f64 = next.iter().zip(&rank).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if delta < PAGERANK_TOLERANCE {
+            break;
+        }
+    }
+    rank
+}
+
+/// Brandes' betweenness centrality on the undirected, unweighted graph.
+fn betweenness(adjacency: &Adjacency) -> Vec<f64> {
+    let n = adjacency.len();
+    let mut centrality = vec![0.0; n];
+
+    for s in 0..n {
+        let mut stack = Vec::with_capacity(n);
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut paths = vec![0.0f64; n];
+        let mut distance: Vec<i64> = vec![-1; n];
+        paths[s] = 1.0;
+        distance[s] = 0;
+
+        let mut queue = VecDeque::from([s]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            for &w in adjacency.neighbours[v].keys() {
+                if distance[w] < 0 {
+                    distance[w] = distance[v] + 1;
+                    queue.push_back(w);
+                }
+                if distance[w] == distance[v] + 1 {
+                    paths[w] += paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+
+        let mut dependency = vec![0.0; n];
+        while let Some(w) = stack.pop() {
+            for &v in &predecessors[w] {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+            }
+            if w != s {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+
+    // Every pair was counted from both ends
+    let pairs = if n > 2 { ((n - 1) * (n - 2)) as f64 } else { 1.0 };
+    centrality.iter().map(|c| c / pairs).collect()
+}
+
+/// Louvain community detection on the undirected weighted graph.
+/// Returns each node's community and the modularity of the partition.
+fn louvain(adjacency: &Adjacency) -> (Vec<usize>, f64) {
+    let n = adjacency.len();
+    let mut membership: Vec<usize> = (0..n).collect();
+    // Current level's graph; self-loops hold the weight inside merged communities
+    let mut level: Vec<BTreeMap<usize, f64>> = adjacency.neighbours.clone();
+
+    loop {
+        let communities = louvain_local_moves(&level);
+        let community_count = communities.iter().max().map_or(0, |c| c + 1);
+        if community_count == level.len() {
+            break;
+        }
+        for community in membership.iter_mut() {
+            *community = communities[*community];
+        }
+
+        let mut aggregated = vec![BTreeMap::new(); community_count];
+        for (i, neighbours) in level.iter().enumerate() {
+            for (&j, &w) in neighbours {
+                let (ci, cj) = (communities[i], communities[j]);
+                // Edges between distinct nodes are listed from both ends; loops once
+                let weight = if ci == cj && i != j { w / 2.0 } else { w };
+                *aggregated[ci].entry(cj).or_insert(0.0) += weight;
+            }
+        }
+        level = aggregated;
+    }
+
+    let membership = renumber_by_size(&membership);
+    let modularity = modularity(&adjacency.neighbours, &membership);
+    (membership, modularity)
+}
+
+/// One Louvain pass: move nodes to the neighbouring community with the best
+/// modularity gain until nothing moves. Communities are renumbered from 0.
+fn louvain_local_moves(graph: &[BTreeMap<usize, f64>]) -> Vec<usize> {
+    let n = graph.len();
+    let degree: Vec<f64> = graph.iter().enumerate()
+        .map(|(i, neighbours)| neighbours.iter().map(|(&j, &w)| if i == j { 2.0 * w } else { w }).sum())
+        .collect();
+    let total: f64 = degree.iter().sum();
+    let mut community: Vec<usize> = (0..n).collect();
+    if total == 0.0 {
+        return community;
+    }
+    let mut community_degree = degree.clone();
+
+    let mut moved = true;
+    while moved {
+        moved = false;
+        for i in 0..n {
+            let current = community[i];
+            let mut links: BTreeMap<usize, f64> = BTreeMap::new();
+            for (&j, &w) in &graph[i] {
+                if j != i {
+                    *links.entry(community[j]).or_insert(0.0) += w;
+                }
+            }
+
+            community_degree[current] -= degree[i];
+            let gain = |c: usize, w: f64| w - community_degree[c] * degree[i] / total;
+            let mut best = current;
+            let mut best_gain = gain(current, links.get(&current).copied().unwrap_or(0.0));
+            for (&c, &w) in &links {
+                let g = gain(c, w);
+                if g > best_gain + 1e-12 {
+                    best = c;
+                    best_gain = g;
+                }
+            }
+            community_degree[best] += degree[i];
+            if best != current {
+                community[i] = best;
+                moved = true;
+            }
+        }
+    }
+
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    community.iter()
+        .map(|c| {
+            let next = ids.len();
+            *ids.entry(*c).or_insert(next)
+        })
+        .collect()
+}
+
+fn modularity(neighbours: &[BTreeMap<usize, f64>], community: &[usize]) -> f64 {
+    let total: f64 = neighbours.iter().flat_map(|n| n.values()).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let count = community.iter().max().map_or(0, |c| c + 1);
+    let mut internal = vec![0.0; count];
+    let mut degree = vec![0.0; count];
+    for (i, links) in neighbours.iter().enumerate() {
+        for (&j, &w) in links {
+            degree[community[i]] += w;
+            if community[i] == community[j] {
+                internal[community[i]] += w;
+            }
+        }
+    }
+    (0..count).map(|c| internal[c] / total - (degree[c] / total).powi(2)).sum()
+}
+
+fn connected_components(adjacency: &Adjacency) -> Vec<usize> {
+    let n = adjacency.len();
+    let mut component = vec![usize::MAX; n];
+    let mut next = 0;
+    for start in 0..n {
+        if component[start] != usize::MAX {
+            continue;
+        }
+        let (distance, _) = adjacency.bfs(&[start], usize::MAX);
+        for (i, d) in distance.iter().enumerate() {
+            if d.is_some() {
+                component[i] = next;
+            }
+        }
+        next += 1;
+    }
+    renumber_by_size(&component)
+}
+
+/// Renumber group labels so 0 is the largest group, ties broken by first member.
+fn renumber_by_size(labels: &[usize]) -> Vec<usize> {
+    let mut groups: HashMap<usize, (usize, usize)> = HashMap::new();
+    for (i, &label) in labels.iter().enumerate() {
+        groups.entry(label).or_insert((0, i)).0 += 1;
+    }
+    let mut order: Vec<(usize, (usize, usize))> = groups.into_iter().collect();
+    order.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.1.1.cmp(&b.1.1)));
+    let rank: HashMap<usize, usize> = order.iter().enumerate().map(|(r, (label, _))| (*label, r)).collect();
+    labels.iter().map(|label| rank[label]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::edge::Edge;
+    use crate::models::node::Node;
+
+    fn graph(nodes: &[&str], edges: &[(&str, &str)]) -> GraphData {
+        GraphData {
+            nodes: nodes.iter().map(|id| Node::new(id.to_string())).collect(),
+            edges: edges.iter().map(|(s, t)| Edge::new(s.to_string(), t.to_string(), 1.0)).collect(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Two triangles joined by the bridge c - d, plus an isolated node
+    fn two_triangles() -> GraphData {
+        graph(
+            &["a", "b", "c", "d", "e", "f", "g"],
+            &[("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e"), ("e", "f"), ("f", "d")],
+        )
+    }
+
+    #[test]
+    fn test_communities_components_and_centrality() {
+        let graph = two_triangles();
+        let analytics = analyze(&graph, topology_version(&graph));
+        let m = &analytics.nodes;
+
+        assert_eq!(analytics.component_count, 2);
+        assert_eq!(m["a"].component, 0);
+        assert_eq!(m["g"].component, 1);
+
+        assert_eq!(analytics.community_count, 3);
+        assert_eq!(m["a"].community, m["c"].community);
+        assert_eq!(m["d"].community, m["f"].community);
+        assert_ne!(m["a"].community, m["d"].community);
+        assert!(analytics.modularity > 0.3);
+
+        // The bridge endpoints carry every path between the triangles
+        assert!(m["c"].betweenness > m["a"].betweenness);
+        assert_eq!(m["c"].degree, 3);
+        assert_eq!(m["c"].in_degree, 1);
+        assert_eq!(m["c"].out_degree, 2);
+
+        let total_rank: f64 = m.values().map(|x| x.pagerank).sum();
+        assert!((total_rank - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_paths_and_neighbourhoods() {
+        let graph = two_triangles();
+        assert_eq!(shortest_path(&graph, "a", "e").unwrap(), vec!["a", "c", "d", "e"]);
+        assert!(shortest_path(&graph, "a", "g").is_none());
+        assert!(shortest_path(&graph, "a", "missing").is_none());
+
+        let hood = k_hop_neighbourhood(&graph, "c", 1).unwrap();
+        let ids: Vec<&str> = hood.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_cache_is_keyed_by_topology() {
+        let cache = AnalyticsCache::new();
+        let mut graph = two_triangles();
+        let first = cache.get_or_compute(&graph);
+
+        graph.nodes[0].x = 42.0;
+        assert!(Arc::ptr_eq(&first, &cache.get_or_compute(&graph)));
+
+        graph.edges.pop();
+        assert!(!Arc::ptr_eq(&first, &cache.get_or_compute(&graph)));
+    }
+}
@@ -0,0 +1,589 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use crate::config::ClusterSettings;
+use crate::models::edge::Edge;
+use crate::models::graph::GraphData;
+use crate::models::metadata::PropertyValue;
+use crate::models::node::Node;
+use crate::services::analytics_service::{self, topology_version};
+use crate::services::query_service::induced_subgraph;
+
+/// Community splitting stops at this depth; deeper clusters are only chunked
+const MAX_DEPTH: usize = 4;
+const LAYOUT_ITERATIONS: usize = 60;
+/// Hierarchies kept at once; sessions with different views cluster different graphs
+const MAX_CACHED_HIERARCHIES: usize = 8;
+
+/// A member of a cluster's layout: a sub-cluster or a node placed directly in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Unit {
+    Cluster(usize),
+    Node(usize),
+}
+
+struct Cluster {
+    id: String,
+    label: String,
+    level: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Graph indices of nodes directly in this cluster
+    members: Vec<usize>,
+    /// Nodes in this cluster and all sub-clusters
+    size: usize,
+    position: [f32; 3],
+}
+
+/// Hierarchical aggregation of a graph into nested clusters, with a layout per level.
+///
+/// Cluster 0 is the root and always expanded. Every cluster's sub-clusters and member
+/// nodes are laid out around the cluster's own position within a radius that grows
+/// with its size, so expanding a meta-node puts its contents where it stood.
+pub struct ClusterHierarchy {
+    version: u64,
+    settings_key: String,
+    clusters: Vec<Cluster>,
+    /// Innermost cluster of each node, in graph order
+    node_cluster: Vec<usize>,
+    node_positions: Vec<[f32; 3]>,
+}
+
+impl ClusterHierarchy {
+    pub fn build(graph: &GraphData, settings: &ClusterSettings) -> Self {
+        let mut builder = Builder {
+            graph,
+            settings,
+            clusters: Vec::new(),
+            node_cluster: vec![0; graph.nodes.len()],
+            degree: node_degrees(graph),
+        };
+        let all: Vec<usize> = (0..graph.nodes.len()).collect();
+        match settings.strategy.as_str() {
+            "folder" => builder.folder_cluster(all, Vec::new(), None),
+            _ => builder.community_cluster(all, "root".to_string(), None, 0),
+        }
+
+        let mut hierarchy = Self {
+            version: topology_version(graph),
+            settings_key: format!("{:?}", settings),
+            clusters: builder.clusters,
+            node_cluster: builder.node_cluster,
+            node_positions: vec![[0.0; 3]; graph.nodes.len()],
+        };
+        hierarchy.layout(graph, settings.spacing);
+        hierarchy
+    }
+
+    /// The graph at the detail given by `expanded`: collapsed clusters become meta-nodes,
+    /// and edges between them are merged into aggregate edges carrying a `count`.
+    pub fn cut(&self, graph: &GraphData, expanded: &HashSet<String>) -> GraphData {
+        // Topmost collapsed cluster at or above each cluster; parents precede children
+        let mut collapsed_at: Vec<Option<usize>> = vec![None; self.clusters.len()];
+        for (c, cluster) in self.clusters.iter().enumerate() {
+            collapsed_at[c] = match cluster.parent {
+                Some(parent) if collapsed_at[parent].is_some() => collapsed_at[parent],
+                Some(_) if !expanded.contains(&cluster.id) => Some(c),
+                _ => None,
+            };
+        }
+        let unit_of = |node: usize| match collapsed_at[self.node_cluster[node]] {
+            Some(c) => Unit::Cluster(c),
+            None => Unit::Node(node),
+        };
+
+        let mut nodes = Vec::new();
+        for (c, cluster) in self.clusters.iter().enumerate() {
+            if collapsed_at[c] == Some(c) {
+                nodes.push(self.meta_node(cluster));
+            }
+        }
+        for (i, node) in graph.nodes.iter().enumerate() {
+            if unit_of(i) == Unit::Node(i) {
+                let mut node = node.clone();
+                [node.x, node.y, node.z] = self.node_positions[i];
+                node.metadata.insert("cluster".to_string(), PropertyValue::Text(self.clusters[self.node_cluster[i]].id.clone()));
+                nodes.push(node);
+            }
+        }
+
+        let index: HashMap<&str, usize> = graph.nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+        let mut edges = Vec::new();
+        let mut aggregates: BTreeMap<(Unit, Unit), (f32, usize)> = BTreeMap::new();
+        for edge in &graph.edges {
+            let (Some(&s), Some(&t)) = (index.get(edge.source.as_str()), index.get(edge.target_node.as_str())) else {
+                continue;
+            };
+            match (unit_of(s), unit_of(t)) {
+                (Unit::Node(_), Unit::Node(_)) => edges.push(edge.clone()),
+                (a, b) if a == b => {},
+                (a, b) => {
+                    let entry = aggregates.entry((a.min(b), a.max(b))).or_insert((0.0, 0));
+                    entry.0 += edge.weight;
+                    entry.1 += 1;
+                },
+            }
+        }
+        for ((a, b), (weight, count)) in aggregates {
+            edges.push(
+                Edge::new(self.unit_id(graph, a), self.unit_id(graph, b), weight)
+                    .with_directed(false)
+                    .with_attribute("aggregate", true)
+                    .with_attribute("count", count)
+            );
+        }
+
+        GraphData { nodes, edges, metadata: HashMap::new() }
+    }
+
+    fn meta_node(&self, cluster: &Cluster) -> Node {
+        let mut node = Node::new(cluster.id.clone());
+        node.label = cluster.label.clone();
+        [node.x, node.y, node.z] = cluster.position;
+        node.file_size = cluster.size as u64;
+        node.metadata.insert("type".to_string(), PropertyValue::Text("cluster".to_string()));
+        node.metadata.insert("size".to_string(), PropertyValue::Number(cluster.size as f64));
+        node.metadata.insert("level".to_string(), PropertyValue::Number(cluster.level as f64));
+        node
+    }
+
+    fn unit_id(&self, graph: &GraphData, unit: Unit) -> String {
+        match unit {
+            Unit::Cluster(c) => self.clusters[c].id.clone(),
+            Unit::Node(i) => graph.nodes[i].id.clone(),
+        }
+    }
+
+    /// Lay out every cluster's contents around its position, top down. Edges are
+    /// attributed to the innermost cluster containing both endpoints, between the
+    /// units of that cluster that hold them.
+    fn layout(&mut self, graph: &GraphData, spacing: f32) {
+        let index: HashMap<&str, usize> = graph.nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+        let mut cluster_edges: HashMap<usize, HashMap<(Unit, Unit), f32>> = HashMap::new();
+        for edge in &graph.edges {
+            let (Some(&s), Some(&t)) = (index.get(edge.source.as_str()), index.get(edge.target_node.as_str())) else {
+                continue;
+            };
+            if s == t {
+                continue;
+            }
+            let (a, b) = (self.ancestry(s), self.ancestry(t));
+            let common = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+            let owner = a[common - 1];
+            let unit = |chain: &[usize], node: usize| chain.get(common).map_or(Unit::Node(node), |&c| Unit::Cluster(c));
+            let (ua, ub) = (unit(&a, s), unit(&b, t));
+            *cluster_edges.entry(owner).or_default().entry((ua.min(ub), ua.max(ub))).or_insert(0.0) += edge.weight.max(0.0);
+        }
+
+        for c in 0..self.clusters.len() {
+            let cluster = &self.clusters[c];
+            let units: Vec<Unit> = cluster.children.iter().map(|&c| Unit::Cluster(c))
+                .chain(cluster.members.iter().map(|&i| Unit::Node(i)))
+                .collect();
+            let position_of: HashMap<Unit, usize> = units.iter().enumerate().map(|(i, u)| (*u, i)).collect();
+            let edges: Vec<(usize, usize, f32)> = cluster_edges.remove(&c).unwrap_or_default()
+                .into_iter()
+                .map(|((a, b), w)| (position_of[&a], position_of[&b], (1.0 + w).ln()))
+                .collect();
+            let seeds: Vec<u64> = units.iter().map(|&u| seed(&self.unit_id(graph, u))).collect();
+
+            let center = cluster.position;
+            let radius = spacing * (cluster.size.max(1) as f32).cbrt();
+            for (unit, offset) in units.iter().zip(force_layout(&seeds, &edges)) {
+                let position = [
+                    center[0] + offset[0] * radius,
+                    center[1] + offset[1] * radius,
+                    center[2] + offset[2] * radius,
+                ];
+                match *unit {
+                    Unit::Cluster(child) => self.clusters[child].position = position,
+                    Unit::Node(node) => self.node_positions[node] = position,
+                }
+            }
+        }
+    }
+
+    /// Clusters containing a node, root first
+    fn ancestry(&self, node: usize) -> Vec<usize> {
+        let mut chain = vec![self.node_cluster[node]];
+        while let Some(parent) = self.clusters[*chain.last().unwrap()].parent {
+            chain.push(parent);
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+struct Builder<'a> {
+    graph: &'a GraphData,
+    settings: &'a ClusterSettings,
+    clusters: Vec<Cluster>,
+    node_cluster: Vec<usize>,
+    degree: Vec<usize>,
+}
+
+impl Builder<'_> {
+    fn add_cluster(&mut self, id: String, label: String, parent: Option<usize>, size: usize) -> usize {
+        let level = parent.map_or(0, |p| self.clusters[p].level + 1);
+        self.clusters.push(Cluster {
+            id: format!("cluster:{}", id),
+            label,
+            level,
+            parent,
+            children: Vec::new(),
+            members: Vec::new(),
+            size,
+            position: [0.0; 3],
+        });
+        let index = self.clusters.len() - 1;
+        if let Some(parent) = parent {
+            self.clusters[parent].children.push(index);
+        }
+        index
+    }
+
+    /// Put nodes directly into a cluster, or into chunked sub-clusters when there are too many
+    fn add_members(&mut self, cluster: usize, mut nodes: Vec<usize>) {
+        if nodes.len() <= self.settings.max_cluster_size.max(1) {
+            for &node in &nodes {
+                self.node_cluster[node] = cluster;
+            }
+            self.clusters[cluster].members.extend(nodes);
+            return;
+        }
+
+        nodes.sort_by(|&a, &b| self.graph.nodes[a].label.cmp(&self.graph.nodes[b].label));
+        let chunk_size = self.settings.max_cluster_size.max(1);
+        let chunks: Vec<Vec<usize>> = nodes.chunks(chunk_size).map(<[usize]>::to_vec).collect();
+        let parent_id = self.clusters[cluster].id.trim_start_matches("cluster:").to_string();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let label = format!(
+                "{} – {}",
+                self.graph.nodes[chunk[0]].label,
+                self.graph.nodes[*chunk.last().unwrap()].label
+            );
+            let child = self.add_cluster(format!("{}~{}", parent_id, i), label, Some(cluster), chunk.len());
+            self.add_members(child, chunk);
+        }
+    }
+
+    /// Split nodes into Louvain communities, recursing into communities that are still too large.
+    /// Nodes left on their own are kept as direct members.
+    fn community_cluster(&mut self, nodes: Vec<usize>, key: String, parent: Option<usize>, depth: usize) {
+        let label = match parent {
+            None => "All".to_string(),
+            Some(_) => {
+                let hub = *nodes.iter().max_by_key(|&&i| (self.degree[i], std::cmp::Reverse(i))).unwrap();
+                format!("{} +{}", self.graph.nodes[hub].label, nodes.len() - 1)
+            },
+        };
+        let cluster = self.add_cluster(key.clone(), label, parent, nodes.len());
+        if nodes.len() <= self.settings.max_cluster_size.max(1) || depth >= MAX_DEPTH {
+            self.add_members(cluster, nodes);
+            return;
+        }
+
+        let (groups, singletons): (Vec<Vec<usize>>, Vec<Vec<usize>>) = self.split_communities(&nodes)
+            .into_iter()
+            .partition(|group| group.len() > 1);
+        if groups.len() == 1 && singletons.is_empty() {
+            // Louvain found no structure; fall back to chunks
+            self.add_members(cluster, nodes);
+            return;
+        }
+        for (i, group) in groups.into_iter().enumerate() {
+            self.community_cluster(group, format!("{}.{}", key, i), Some(cluster), depth + 1);
+        }
+        self.add_members(cluster, singletons.into_iter().flatten().collect());
+    }
+
+    fn split_communities(&self, nodes: &[usize]) -> Vec<Vec<usize>> {
+        let ids: Vec<String> = nodes.iter().map(|&i| self.graph.nodes[i].id.clone()).collect();
+        let subgraph = induced_subgraph(self.graph, &ids);
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        // The subgraph keeps graph order, and `nodes` is ascending
+        for (&node, community) in nodes.iter().zip(analytics_service::communities(&subgraph)) {
+            groups.entry(community).or_default().push(node);
+        }
+        groups.into_values().collect()
+    }
+
+    /// One cluster per folder of the page path, e.g. `projects/2024/plan`
+    fn folder_cluster(&mut self, nodes: Vec<usize>, path: Vec<String>, parent: Option<usize>) {
+        let label = path.last().cloned().unwrap_or_else(|| "All".to_string());
+        let key = if path.is_empty() { "root".to_string() } else { format!("/{}", path.join("/")) };
+        let cluster = self.add_cluster(key, label, parent, nodes.len());
+
+        let mut folders: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        let mut members = Vec::new();
+        for node in nodes {
+            let segments: Vec<&str> = self.graph.nodes[node].id.split('/').collect();
+            match segments.len() > path.len() + 1 {
+                true => folders.entry(segments[path.len()].to_string()).or_default().push(node),
+                false => members.push(node),
+            }
+        }
+        for (folder, nodes) in folders {
+            let mut child_path = path.clone();
+            child_path.push(folder);
+            self.folder_cluster(nodes, child_path, Some(cluster));
+        }
+        self.add_members(cluster, members);
+    }
+}
+
+/// The most recently used hierarchies, keyed by the topology of the clustered graph
+/// (which differs per session view) and the settings. The least recently used is
+/// dropped once `MAX_CACHED_HIERARCHIES` are kept.
+#[derive(Default)]
+pub struct ClusterCache {
+    recent: Mutex<VecDeque<Arc<ClusterHierarchy>>>,
+}
+
+impl ClusterCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The hierarchy of `graph`, built on the calling thread if it isn't cached.
+    /// The lock is only held to look up and store hierarchies, not while building.
+    pub fn get_or_build(&self, graph: &GraphData, settings: &ClusterSettings) -> Arc<ClusterHierarchy> {
+        let (version, settings_key) = (topology_version(graph), format!("{:?}", settings));
+        if let Some(hierarchy) = self.cached(version, &settings_key) {
+            return hierarchy;
+        }
+        let hierarchy = Arc::new(ClusterHierarchy::build(graph, settings));
+
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|h| h.version != version || h.settings_key != settings_key);
+        recent.push_front(hierarchy.clone());
+        recent.truncate(MAX_CACHED_HIERARCHIES);
+        hierarchy
+    }
+
+    /// The hierarchy of `graph` for async callers; a cache miss is built on the
+    /// blocking thread pool from a copy of the graph.
+    pub async fn build(self: &Arc<Self>, graph: &GraphData, settings: &ClusterSettings) -> Arc<ClusterHierarchy> {
+        if let Some(hierarchy) = self.cached(topology_version(graph), &format!("{:?}", settings)) {
+            return hierarchy;
+        }
+        let (cache, graph, settings) = (self.clone(), graph.clone(), settings.clone());
+        tokio::task::spawn_blocking(move || cache.get_or_build(&graph, &settings))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    /// A cached hierarchy, moved to the front as the most recently used
+    fn cached(&self, version: u64, settings_key: &str) -> Option<Arc<ClusterHierarchy>> {
+        let mut recent = self.recent.lock().unwrap();
+        let position = recent.iter().position(|h| h.version == version && h.settings_key == settings_key)?;
+        let hierarchy = recent.remove(position)?;
+        recent.push_front(hierarchy.clone());
+        Some(hierarchy)
+    }
+}
+
+fn node_degrees(graph: &GraphData) -> Vec<usize> {
+    let index: HashMap<&str, usize> = graph.nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+    let mut degree = vec![0; graph.nodes.len()];
+    for edge in &graph.edges {
+        for id in [&edge.source, &edge.target_node] {
+            if let Some(&i) = index.get(id.as_str()) {
+                degree[i] += 1;
+            }
+        }
+    }
+    degree
+}
+
+fn seed(id: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Deterministic Fruchterman-Reingold layout in the unit ball, starting from
+/// positions derived from each unit's seed so the result only depends on the input.
+fn force_layout(seeds: &[u64], edges: &[(usize, usize, f32)]) -> Vec<[f32; 3]> {
+    let n = seeds.len();
+    if n <= 1 {
+        return vec![[0.0; 3]; n];
+    }
+
+    let mut positions: Vec<[f32; 3]> = seeds.iter()
+        .map(|&s| {
+            let theta = (s & 0xffff) as f32 / 65535.0 * std::f32::consts::TAU;
+            let z = ((s >> 16) & 0xffff) as f32 / 65535.0 * 2.0 - 1.0;
+            let r = (1.0 - z * z).sqrt();
+            [r * theta.cos(), r * theta.sin(), z]
+        })
+        .collect();
+    let k = 1.0 / (n as f32).cbrt();
+
+    for iteration in 0..LAYOUT_ITERATIONS {
+        let temperature = 0.1 * (1.0 - iteration as f32 / LAYOUT_ITERATIONS as f32);
+        let mut displacement = vec![[0.0f32; 3]; n];
+
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let delta = sub(positions[i], positions[j]);
+                let distance = length(delta).max(1e-3);
+                let force = k * k / distance;
+                for axis in 0..3 {
+                    let push = delta[axis] / distance * force;
+                    displacement[i][axis] += push;
+                    displacement[j][axis] -= push;
+                }
+            }
+        }
+        for &(a, b, weight) in edges {
+            let delta = sub(positions[a], positions[b]);
+            let distance = length(delta).max(1e-3);
+            let force = distance * distance / k * weight;
+            for axis in 0..3 {
+                let pull = delta[axis] / distance * force;
+                displacement[a][axis] -= pull;
+                displacement[b][axis] += pull;
+            }
+        }
+
+        for (position, d) in positions.iter_mut().zip(&displacement) {
+            let magnitude = length(*d);
+            if magnitude > 0.0 {
+                let step = magnitude.min(temperature) / magnitude;
+                for axis in 0..3 {
+                    position[axis] += d[axis] * step;
+                }
+            }
+        }
+    }
+
+    // Center on the origin and fit into the unit ball
+    let mut centroid = [0.0f32; 3];
+    for position in &positions {
+        for axis in 0..3 {
+            centroid[axis] += position[axis] / n as f32;
+        }
+    }
+    let extent = positions.iter().map(|&p| length(sub(p, centroid))).fold(0.0, f32::max);
+    positions.iter()
+        .map(|&p| {
+            let offset = sub(p, centroid);
+            if extent > 0.0 { offset.map(|x| x / extent) } else { offset }
+        })
+        .collect()
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn length(v: [f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(nodes: &[&str], edges: &[(&str, &str)]) -> GraphData {
+        GraphData {
+            nodes: nodes.iter().map(|id| Node::new(id.to_string())).collect(),
+            edges: edges.iter().map(|(s, t)| Edge::new(s.to_string(), t.to_string(), 1.0)).collect(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn settings(strategy: &str) -> ClusterSettings {
+        ClusterSettings {
+            strategy: strategy.to_string(),
+            max_cluster_size: 3,
+            spacing: 8.0,
+        }
+    }
+
+    fn ids(graph: &GraphData) -> Vec<&str> {
+        graph.nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    /// Two triangles joined by the bridge c - d, plus an isolated node
+    fn two_triangles() -> GraphData {
+        graph(
+            &["a", "b", "c", "d", "e", "f", "g"],
+            &[("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e"), ("e", "f"), ("f", "d")],
+        )
+    }
+
+    #[test]
+    fn test_collapsed_communities_become_meta_nodes() {
+        let graph = two_triangles();
+        let hierarchy = ClusterHierarchy::build(&graph, &settings("community"));
+        let top = hierarchy.cut(&graph, &HashSet::new());
+
+        assert_eq!(ids(&top), vec!["cluster:root.0", "cluster:root.1", "g"]);
+        assert_eq!(top.nodes[0].metadata["size"], PropertyValue::Number(3.0));
+        assert_eq!(top.edges.len(), 1);
+        assert_eq!(top.edges[0].attributes["count"], "1");
+        assert!(!top.edges[0].directed);
+    }
+
+    #[test]
+    fn test_expanding_keeps_layout_consistent() {
+        let graph = two_triangles();
+        let hierarchy = ClusterHierarchy::build(&graph, &settings("community"));
+        let top = hierarchy.cut(&graph, &HashSet::new());
+        let meta = &top.nodes[0];
+
+        let expanded = hierarchy.cut(&graph, &HashSet::from([meta.id.clone()]));
+        let members: Vec<&Node> = expanded.nodes.iter()
+            .filter(|n| n.metadata.get("cluster") == Some(&PropertyValue::Text(meta.id.clone())))
+            .collect();
+        assert_eq!(members.len(), 3);
+
+        // Members sit around the meta-node they replace
+        let radius = 8.0 * 3f32.cbrt() + 1e-3;
+        for node in members {
+            let distance = length(sub([node.x, node.y, node.z], [meta.x, meta.y, meta.z]));
+            assert!(distance <= radius, "{} is {} from its cluster", node.id, distance);
+        }
+        // Three internal edges plus the bridge, now from a node to the other meta-node
+        assert_eq!(expanded.edges.len(), 4);
+        assert_eq!(expanded.edges.iter().filter(|e| e.attributes.contains_key("aggregate")).count(), 1);
+
+        // Same input, same layout
+        let again = ClusterHierarchy::build(&graph, &settings("community")).cut(&graph, &HashSet::new());
+        assert_eq!((again.nodes[0].x, again.nodes[0].y), (meta.x, meta.y));
+    }
+
+    #[test]
+    fn test_cache_keeps_a_hierarchy_per_view() {
+        let cache = ClusterCache::new();
+        let whole = two_triangles();
+        let view = graph(&["a", "b", "c"], &[("a", "b"), ("b", "c"), ("c", "a")]);
+
+        let first = cache.get_or_build(&whole, &settings("community"));
+        let viewed = cache.get_or_build(&view, &settings("community"));
+        assert!(!Arc::ptr_eq(&first, &viewed));
+        // Alternating between views doesn't rebuild either
+        assert!(Arc::ptr_eq(&first, &cache.get_or_build(&whole, &settings("community"))));
+        assert!(Arc::ptr_eq(&viewed, &cache.get_or_build(&view, &settings("community"))));
+        assert!(!Arc::ptr_eq(&first, &cache.get_or_build(&whole, &settings("folder"))));
+    }
+
+    #[test]
+    fn test_folder_hierarchy_and_chunking() {
+        let graph = graph(
+            &["projects/a", "projects/b", "notes/x", "notes/y", "notes/z", "notes/w", "top"],
+            &[("projects/a", "notes/x")],
+        );
+        let hierarchy = ClusterHierarchy::build(&graph, &settings("folder"));
+        let top = hierarchy.cut(&graph, &HashSet::new());
+        assert_eq!(ids(&top), vec!["cluster:/notes", "cluster:/projects", "top"]);
+
+        // Four notes exceed the cluster size, so the folder is chunked
+        let notes = hierarchy.cut(&graph, &HashSet::from(["cluster:/notes".to_string()]));
+        assert_eq!(ids(&notes), vec!["cluster:/notes~0", "cluster:/notes~1", "cluster:/projects", "top"]);
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::models::metadata::Metadata;
+
+pub const CONTENT_STORE_PATH: &str = "data/content.db";
+
+/// The current version of a stored page.
+#[derive(Debug, Clone)]
+pub struct StoredFile {
+    pub file_name: String,
+    /// SHA1 of `content`, identifying this version
+    pub version: String,
+    pub content: String,
+    pub metadata: Metadata,
+}
+
+/// Durable store of page content and metadata, backed by SQLite.
+///
+/// Every distinct content of a page is kept as a version; `files` points at the
+/// current one. The graph can be rebuilt from the store alone, without GitHub.
+pub struct ContentStore {
+    conn: Mutex<Connection>,
+}
+
+impl ContentStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        if let Some(parent) = path.as_ref().parent() {
+            // Let SQLite report the error if the directory really can't be created
+            let _ = std::fs::create_dir_all(parent);
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, rusqlite::Error> {
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS versions (
+                 file_name  TEXT NOT NULL,
+                 version    TEXT NOT NULL,
+                 content    TEXT NOT NULL,
+                 metadata   TEXT NOT NULL,
+                 refs       TEXT NOT NULL,
+                 stored_at  TEXT NOT NULL,
+                 PRIMARY KEY (file_name, version)
+             );
+             CREATE TABLE IF NOT EXISTS files (
+                 file_name  TEXT PRIMARY KEY,
+                 version    TEXT NOT NULL,
+                 updated_at TEXT NOT NULL
+             );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Store a page version and make it current. Returns the version id.
+    pub fn put(&self, file_name: &str, content: &str, metadata: &Metadata) -> Result<String, rusqlite::Error> {
+        let version = content_version(content);
+        let metadata_json = to_json(metadata)?;
+        let refs_json = to_json(&metadata.reference_counts)?;
+        let now = Utc::now().to_rfc3339();
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO versions (file_name, version, content, metadata, refs, stored_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (file_name, version) DO UPDATE SET metadata = ?4, refs = ?5",
+            params![file_name, version, content, metadata_json, refs_json, now],
+        )?;
+        tx.execute(
+            "INSERT INTO files (file_name, version, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (file_name) DO UPDATE SET version = ?2, updated_at = ?3",
+            params![file_name, version, now],
+        )?;
+        tx.commit()?;
+        Ok(version)
+    }
+
+    /// Current version of a page
+    pub fn get(&self, file_name: &str) -> Result<Option<StoredFile>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT f.file_name, f.version, v.content, v.metadata
+             FROM files f JOIN versions v ON v.file_name = f.file_name AND v.version = f.version
+             WHERE f.file_name = ?1",
+            params![file_name],
+            row_to_stored_file,
+        )
+        .optional()
+    }
+
+    /// Current version of every page
+    pub fn all(&self) -> Result<Vec<StoredFile>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT f.file_name, f.version, v.content, v.metadata
+             FROM files f JOIN versions v ON v.file_name = f.file_name AND v.version = f.version
+             ORDER BY f.file_name",
+        )?;
+        let files = stmt.query_map([], row_to_stored_file)?;
+        files.collect()
+    }
+
+    /// Current metadata of every page, keyed by file name
+    pub fn metadata(&self) -> Result<HashMap<String, Metadata>, rusqlite::Error> {
+        Ok(self.all()?
+            .into_iter()
+            .map(|file| (file.file_name, file.metadata))
+            .collect())
+    }
+
+    /// Drop pages that are no longer published. Their versions stay in history.
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) -> Result<usize, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let names: Vec<String> = conn.prepare("SELECT file_name FROM files")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        let mut removed = 0;
+        for name in names.iter().filter(|name| !keep(name)) {
+            removed += conn.execute("DELETE FROM files WHERE file_name = ?1", params![name])?;
+        }
+        if removed > 0 {
+            info!("Removed {} unpublished files from the content store", removed);
+        }
+        Ok(removed)
+    }
+
+    /// Run store operations on the blocking thread pool, off the async executor
+    pub async fn blocking<T, E, F>(self: &Arc<Self>, f: F) -> Result<T, Box<dyn StdError + Send + Sync>>
+    where
+        F: FnOnce(&ContentStore) -> Result<T, E> + Send + 'static,
+        E: Into<Box<dyn StdError + Send + Sync>>,
+        T: Send + 'static,
+    {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || f(&store).map_err(Into::into)).await?
+    }
+
+    pub fn is_empty(&self) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?;
+        Ok(count == 0)
+    }
+}
+
+fn row_to_stored_file(row: &rusqlite::Row) -> Result<StoredFile, rusqlite::Error> {
+    let metadata: String = row.get(3)?;
+    Ok(StoredFile {
+        file_name: row.get(0)?,
+        version: row.get(1)?,
+        content: row.get(2)?,
+        metadata: serde_json::from_str(&metadata)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?,
+    })
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, rusqlite::Error> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn content_version(content: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_store() -> ContentStore {
+        ContentStore::init(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn metadata(file_name: &str) -> Metadata {
+        Metadata {
+            file_name: file_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_put_keeps_versions_and_current_pointer() {
+        let store = memory_store();
+        let v1 = store.put("a.md", "first", &metadata("a.md")).unwrap();
+        let v2 = store.put("a.md", "second", &metadata("a.md")).unwrap();
+        assert_ne!(v1, v2);
+
+        let current = store.get("a.md").unwrap().unwrap();
+        assert_eq!(current.version, v2);
+        assert_eq!(current.content, "second");
+
+        let versions: i64 = store.conn.lock().unwrap()
+            .query_row("SELECT COUNT(*) FROM versions WHERE file_name = 'a.md'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(versions, 2);
+    }
+
+    #[test]
+    fn test_retain_drops_current_files() {
+        let store = memory_store();
+        store.put("a.md", "a", &metadata("a.md")).unwrap();
+        store.put("b.md", "b", &metadata("b.md")).unwrap();
+
+        assert_eq!(store.retain(|name| name == "a.md").unwrap(), 1);
+        assert_eq!(store.metadata().unwrap().keys().collect::<Vec<_>>(), vec!["a.md"]);
+        assert!(store.get("b.md").unwrap().is_none());
+    }
+}
@@ -0,0 +1,394 @@
+use async_trait::async_trait;
+use log::info;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::{Arc, Mutex};
+
+use crate::config::{SemanticSettings, Settings};
+use crate::models::metadata::{Metadata, PageEmbedding};
+use crate::services::content_store::ContentStore;
+use crate::utils::page_properties::PageProperties;
+
+/// Pages sent to the embedder per request
+const BATCH_SIZE: usize = 32;
+
+/// Words too common to say anything about what a page is about
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have",
+    "in", "is", "it", "its", "of", "on", "or", "that", "the", "this", "to", "was", "were",
+    "will", "with",
+];
+
+/// Turns page text into fixed-length vectors whose cosine similarity reflects topical similarity.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifies the model and its configuration, so vectors from different models are never compared.
+    fn model_id(&self) -> String;
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn StdError + Send + Sync>>;
+}
+
+/// Local, deterministic embedder: word unigrams, bigrams and character trigrams are
+/// hashed into a fixed number of signed buckets with sublinear term frequency.
+/// Needs no corpus statistics, so a page's vector only changes when the page does.
+pub struct HashedNgramEmbedder {
+    dimensions: usize,
+}
+
+impl HashedNgramEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .filter(|word| word.chars().count() > 1 && !STOP_WORDS.contains(&word.as_str()))
+            .collect();
+
+        let mut features: HashMap<String, u32> = HashMap::new();
+        for word in &words {
+            *features.entry(format!("w:{}", word)).or_insert(0) += 1;
+            let chars: Vec<char> = format!("<{}>", word).chars().collect();
+            for trigram in chars.windows(3) {
+                *features.entry(format!("c:{}", trigram.iter().collect::<String>())).or_insert(0) += 1;
+            }
+        }
+        for pair in words.windows(2) {
+            *features.entry(format!("b:{} {}", pair[0], pair[1])).or_insert(0) += 1;
+        }
+
+        let mut vector = vec![0.0f32; self.dimensions];
+        for (feature, count) in features {
+            let hash = fnv1a(feature.as_bytes());
+            let bucket = (hash % self.dimensions as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            // Word features carry more meaning than the trigrams derived from them
+            let scale = if feature.starts_with("c:") { 0.5 } else { 1.0 };
+            vector[bucket] += sign * scale * (1.0 + (count as f32).ln());
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+#[async_trait]
+impl Embedder for HashedNgramEmbedder {
+    fn model_id(&self) -> String {
+        format!("hashed-ngram-{}", self.dimensions)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn StdError + Send + Sync>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// Embeddings from an OpenAI compatible `/embeddings` endpoint.
+pub struct RemoteEmbedder {
+    client: Client,
+    api_url: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl RemoteEmbedder {
+    pub fn new(api_url: String, api_key: String, model: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_url,
+            api_key,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for RemoteEmbedder {
+    fn model_id(&self) -> String {
+        format!("remote-{}", self.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn StdError + Send + Sync>> {
+        let response = self.client.post(&self.api_url)
+            .bearer_auth(&self.api_key)
+            .json(&EmbeddingRequest { model: &self.model, input: texts })
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<EmbeddingResponse>()
+            .await?;
+
+        if response.data.len() != texts.len() {
+            return Err(format!(
+                "embedding endpoint returned {} vectors for {} inputs",
+                response.data.len(), texts.len()
+            ).into());
+        }
+        let mut data = response.data;
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter()
+            .map(|d| {
+                let mut vector = d.embedding;
+                normalize(&mut vector);
+                vector
+            })
+            .collect())
+    }
+}
+
+/// Build the embedder selected by `[semantic] provider`.
+pub fn embedder_from_settings(settings: &Settings) -> Arc<dyn Embedder> {
+    let semantic = &settings.semantic;
+    match semantic.provider.as_str() {
+        "remote" => Arc::new(RemoteEmbedder::new(
+            semantic.api_url.clone(),
+            settings.openai.openai_api_key.clone(),
+            semantic.model.clone(),
+        )),
+        _ => Arc::new(HashedNgramEmbedder::new(semantic.dimensions)),
+    }
+}
+
+/// Embed every stored page whose vector is missing, stale or from another model,
+/// saving the vector into the page's metadata. Returns the number of pages embedded.
+pub async fn refresh_embeddings(
+    store: &Arc<ContentStore>,
+    embedder: &dyn Embedder,
+) -> Result<usize, Box<dyn StdError + Send + Sync>> {
+    let model = embedder.model_id();
+    let stale: Vec<_> = store.blocking(|store| store.all()).await?
+        .into_iter()
+        .filter(|file| !file.metadata.embedding.as_ref()
+            .is_some_and(|e| e.model == model && e.version == file.version))
+        .collect();
+
+    for batch in stale.chunks(BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter()
+            .map(|file| embedding_text(&file.content, &file.metadata))
+            .collect();
+        let vectors = embedder.embed(&texts).await?;
+
+        let embedded: Vec<_> = batch.iter().zip(vectors)
+            .map(|(file, vector)| {
+                let mut metadata = file.metadata.clone();
+                metadata.embedding = Some(PageEmbedding {
+                    model: model.clone(),
+                    version: file.version.clone(),
+                    vector,
+                });
+                (file.file_name.clone(), file.content.clone(), metadata)
+            })
+            .collect();
+        store.blocking(move |store| {
+            embedded.iter().try_for_each(|(file_name, content, metadata)| {
+                store.put(file_name, content, metadata).map(|_| ())
+            })
+        }).await?;
+    }
+
+    if !stale.is_empty() {
+        info!("Computed {} page embeddings with {}", stale.len(), model);
+    }
+    Ok(stale.len())
+}
+
+/// Title, tags and body of a page; property lines would make every page look alike.
+fn embedding_text(content: &str, metadata: &Metadata) -> String {
+    let body = &content[PageProperties::parse(content).body_offset..];
+    let mut text = String::new();
+    if let Some(title) = &metadata.title {
+        text.push_str(title);
+        text.push('\n');
+    }
+    text.push_str(&metadata.tags.join(" "));
+    text.push('\n');
+    text.push_str(body);
+    text
+}
+
+/// Pairs of similar nodes with their similarity, as found by [`nearest_neighbours`]
+pub type Neighbours = Vec<(String, String, f32)>;
+
+/// Semantic neighbours of the most recent set of embeddings. Graphs are rebuilt far
+/// more often than pages change, so the pairwise comparison is keyed by the content
+/// versions the embeddings were computed from and only redone when one changed.
+#[derive(Default)]
+pub struct NeighbourCache {
+    latest: Mutex<Option<(u64, Arc<Neighbours>)>>,
+}
+
+impl NeighbourCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// [`nearest_neighbours`] of `embeddings`, keyed by node id. The comparison runs on
+    /// the blocking pool, as it is quadratic in the number of pages.
+    pub async fn get_or_compute(&self, embeddings: &HashMap<String, &PageEmbedding>, settings: &SemanticSettings) -> Arc<Neighbours> {
+        let key = neighbours_key(embeddings, settings);
+        if let Some((_, neighbours)) = self.latest.lock().unwrap().as_ref().filter(|(k, _)| *k == key) {
+            return neighbours.clone();
+        }
+
+        let vectors: HashMap<String, Vec<f32>> = embeddings.iter()
+            .map(|(id, embedding)| (id.clone(), embedding.vector.clone()))
+            .collect();
+        let settings = settings.clone();
+        let neighbours = tokio::task::spawn_blocking(move || Arc::new(nearest_neighbours(&vectors, &settings)))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        *self.latest.lock().unwrap() = Some((key, neighbours.clone()));
+        neighbours
+    }
+}
+
+fn neighbours_key(embeddings: &HashMap<String, &PageEmbedding>, settings: &SemanticSettings) -> u64 {
+    let mut ids: Vec<&String> = embeddings.keys().collect();
+    ids.sort();
+
+    let mut key = format!("{}:{}", settings.top_k, settings.threshold.to_bits());
+    for id in ids {
+        let embedding = embeddings[id];
+        key.push_str(&format!("\n{}\t{}\t{}", id, embedding.model, embedding.version));
+    }
+    fnv1a(key.as_bytes())
+}
+
+/// Pairs of nodes where either is among the other's `top_k` most similar nodes with
+/// a cosine similarity of at least `threshold`. Each pair is reported once, ordered by id.
+pub fn nearest_neighbours(vectors: &HashMap<String, Vec<f32>>, settings: &SemanticSettings) -> Neighbours {
+    let mut ids: Vec<&String> = vectors.keys().collect();
+    ids.sort();
+
+    let mut pairs: HashMap<(usize, usize), f32> = HashMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        let mut candidates: Vec<(usize, f32)> = ids.iter().enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(j, other)| (j, cosine(&vectors[*id], &vectors[*other])))
+            .filter(|&(_, similarity)| similarity >= settings.threshold)
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        candidates.truncate(settings.top_k);
+
+        for (j, similarity) in candidates {
+            pairs.insert((i.min(j), i.max(j)), similarity);
+        }
+    }
+
+    let mut neighbours: Vec<(String, String, f32)> = pairs.into_iter()
+        .map(|((i, j), similarity)| (ids[i].clone(), ids[j].clone(), similarity))
+        .collect();
+    neighbours.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    neighbours
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    // Vectors are stored normalized
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// 64-bit FNV-1a, stable across runs and platforms unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hashed_embeddings_are_deterministic_and_topical() {
+        let embedder = HashedNgramEmbedder::new(256);
+        let texts = vec![
+            "Rust ownership, borrowing and lifetimes in the Rust compiler".to_string(),
+            "Borrowing rules and lifetimes: how Rust checks ownership".to_string(),
+            "Sourdough bread needs flour, water, salt and a starter".to_string(),
+        ];
+        let first = embedder.embed(&texts).await.unwrap();
+        let second = embedder.embed(&texts).await.unwrap();
+        assert_eq!(first, second);
+
+        assert!(cosine(&first[0], &first[1]) > cosine(&first[0], &first[2]));
+        assert!((cosine(&first[0], &first[0]) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_nearest_neighbours_respects_top_k_and_threshold() {
+        let vectors = HashMap::from([
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("b".to_string(), vec![0.8, 0.6]),
+            ("c".to_string(), vec![0.6, 0.8]),
+            ("d".to_string(), vec![0.0, -1.0]),
+        ]);
+        let settings = SemanticSettings {
+            top_k: 1,
+            threshold: 0.5,
+            ..Default::default()
+        };
+
+        let pairs: Vec<(String, String)> = nearest_neighbours(&vectors, &settings)
+            .into_iter()
+            .map(|(a, b, _)| (a, b))
+            .collect();
+        // a and b pick each other, c picks b; d is similar to nothing
+        assert_eq!(pairs, vec![
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "c".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_neighbour_cache_recomputes_when_a_page_changes() {
+        let embedding = |version: &str, vector: Vec<f32>| PageEmbedding {
+            model: "test".to_string(),
+            version: version.to_string(),
+            vector,
+        };
+        let (a, b, b2) = (embedding("1", vec![1.0, 0.0]), embedding("1", vec![0.8, 0.6]), embedding("2", vec![0.0, -1.0]));
+        let cache = NeighbourCache::new();
+        let settings = SemanticSettings { threshold: 0.5, ..Default::default() };
+
+        let first = cache.get_or_compute(&HashMap::from([("a".to_string(), &a), ("b".to_string(), &b)]), &settings).await;
+        let again = cache.get_or_compute(&HashMap::from([("a".to_string(), &a), ("b".to_string(), &b)]), &settings).await;
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(first.len(), 1);
+
+        let changed = cache.get_or_compute(&HashMap::from([("a".to_string(), &a), ("b".to_string(), &b2)]), &settings).await;
+        assert!(changed.is_empty());
+    }
+}
@@ -0,0 +1,457 @@
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::models::edge::{Edge, EdgeKind};
+use crate::models::graph::GraphData;
+use crate::models::metadata::PropertyValue;
+
+/// Graph file formats understood by common analysis tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    GraphMl,
+    Gexf,
+    Dot,
+    JsonGraph,
+    CsvNodes,
+    CsvEdges,
+}
+
+impl ExportFormat {
+    pub const NAMES: &'static [&'static str] = &["graphml", "gexf", "dot", "jgf", "csv-nodes", "csv-edges"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "graphml" => Some(Self::GraphMl),
+            "gexf" => Some(Self::Gexf),
+            "dot" | "graphviz" => Some(Self::Dot),
+            "jgf" | "json-graph" | "json" => Some(Self::JsonGraph),
+            "csv-nodes" | "csv" => Some(Self::CsvNodes),
+            "csv-edges" => Some(Self::CsvEdges),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::GraphMl | Self::Gexf => "application/xml",
+            Self::Dot => "text/vnd.graphviz",
+            Self::JsonGraph => "application/json",
+            Self::CsvNodes | Self::CsvEdges => "text/csv",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::GraphMl => "graph.graphml",
+            Self::Gexf => "graph.gexf",
+            Self::Dot => "graph.dot",
+            Self::JsonGraph => "graph.json",
+            Self::CsvNodes => "nodes.csv",
+            Self::CsvEdges => "edges.csv",
+        }
+    }
+}
+
+/// Serialize the graph, including positions, node attributes and edge attributes.
+pub fn export(graph: &GraphData, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::GraphMl => to_graphml(graph),
+        ExportFormat::Gexf => to_gexf(graph),
+        ExportFormat::Dot => to_dot(graph),
+        ExportFormat::JsonGraph => to_json_graph(graph).to_string(),
+        ExportFormat::CsvNodes => nodes_to_csv(graph),
+        ExportFormat::CsvEdges => edges_to_csv(graph),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttributeType {
+    Double,
+    Boolean,
+    String,
+}
+
+impl AttributeType {
+    /// Type name in both GraphML and GEXF
+    fn name(self) -> &'static str {
+        match self {
+            Self::Double => "double",
+            Self::Boolean => "boolean",
+            Self::String => "string",
+        }
+    }
+}
+
+/// Node attribute names in sorted order, typed by the values they hold.
+/// Attributes whose values disagree on type are exported as strings.
+fn node_attributes(graph: &GraphData) -> BTreeMap<String, AttributeType> {
+    let mut attributes: BTreeMap<String, AttributeType> = BTreeMap::new();
+    for node in &graph.nodes {
+        for (key, value) in &node.metadata {
+            let value_type = match value {
+                PropertyValue::Number(_) => AttributeType::Double,
+                PropertyValue::Bool(_) => AttributeType::Boolean,
+                _ => AttributeType::String,
+            };
+            attributes.entry(key.clone())
+                .and_modify(|t| if *t != value_type { *t = AttributeType::String })
+                .or_insert(value_type);
+        }
+    }
+    attributes
+}
+
+fn edge_attributes(graph: &GraphData) -> Vec<String> {
+    let mut keys: Vec<String> = graph.edges.iter().flat_map(|e| e.attributes.keys().cloned()).collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn value_text(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Bool(b) => b.to_string(),
+        PropertyValue::Number(n) => n.to_string(),
+        PropertyValue::Date(d) => d.to_rfc3339(),
+        PropertyValue::Text(s) => s.clone(),
+        PropertyValue::List(items) => items.join(";"),
+    }
+}
+
+fn kind_name(kind: EdgeKind) -> String {
+    serde_json::to_value(kind).ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {},
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Default edge direction for formats with a graph-wide setting; mixed graphs mark undirected edges individually
+fn mostly_directed(graph: &GraphData) -> bool {
+    graph.edges.iter().filter(|e| e.directed).count() * 2 >= graph.edges.len()
+}
+
+fn to_graphml(graph: &GraphData) -> String {
+    let node_attributes = node_attributes(graph);
+    let edge_attributes = edge_attributes(graph);
+    let directed = mostly_directed(graph);
+    let mut out = String::new();
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (key, name, kind) in [("label", "label", "string"), ("x", "x", "float"), ("y", "y", "float"), ("z", "z", "float")] {
+        let _ = writeln!(out, "  <key id=\"{}\" for=\"node\" attr.name=\"{}\" attr.type=\"{}\"/>", key, name, kind);
+    }
+    for (i, (name, attribute_type)) in node_attributes.iter().enumerate() {
+        let _ = writeln!(out, "  <key id=\"n{}\" for=\"node\" attr.name=\"{}\" attr.type=\"{}\"/>", i, xml_escape(name), attribute_type.name());
+    }
+    out.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n");
+    out.push_str("  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n");
+    for (i, name) in edge_attributes.iter().enumerate() {
+        let _ = writeln!(out, "  <key id=\"e{}\" for=\"edge\" attr.name=\"{}\" attr.type=\"string\"/>", i, xml_escape(name));
+    }
+
+    let _ = writeln!(out, "  <graph id=\"G\" edgedefault=\"{}\">", if directed { "directed" } else { "undirected" });
+    for node in &graph.nodes {
+        let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&node.id));
+        let _ = writeln!(out, "      <data key=\"label\">{}</data>", xml_escape(&node.label));
+        let _ = writeln!(out, "      <data key=\"x\">{}</data>", node.x);
+        let _ = writeln!(out, "      <data key=\"y\">{}</data>", node.y);
+        let _ = writeln!(out, "      <data key=\"z\">{}</data>", node.z);
+        for (i, name) in node_attributes.keys().enumerate() {
+            if let Some(value) = node.metadata.get(name) {
+                let _ = writeln!(out, "      <data key=\"n{}\">{}</data>", i, xml_escape(&value_text(value)));
+            }
+        }
+        out.push_str("    </node>\n");
+    }
+    for (index, edge) in graph.edges.iter().enumerate() {
+        let direction = match edge.directed == directed {
+            true => String::new(),
+            false => format!(" directed=\"{}\"", edge.directed),
+        };
+        let _ = writeln!(
+            out,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"{}>",
+            index, xml_escape(&edge.source), xml_escape(&edge.target_node), direction
+        );
+        let _ = writeln!(out, "      <data key=\"weight\">{}</data>", edge.weight);
+        let _ = writeln!(out, "      <data key=\"kind\">{}</data>", kind_name(edge.kind));
+        for (i, name) in edge_attributes.iter().enumerate() {
+            if let Some(value) = edge.attributes.get(name) {
+                let _ = writeln!(out, "      <data key=\"e{}\">{}</data>", i, xml_escape(value));
+            }
+        }
+        out.push_str("    </edge>\n");
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn to_gexf(graph: &GraphData) -> String {
+    let node_attributes = node_attributes(graph);
+    let edge_attributes = edge_attributes(graph);
+    let directed = mostly_directed(graph);
+    let mut out = String::new();
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">\n");
+    let _ = writeln!(
+        out,
+        "  <graph mode=\"static\" defaultedgetype=\"{}\">",
+        if directed { "directed" } else { "undirected" }
+    );
+
+    out.push_str("    <attributes class=\"node\">\n");
+    for (i, (name, attribute_type)) in node_attributes.iter().enumerate() {
+        let _ = writeln!(out, "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>", i, xml_escape(name), attribute_type.name());
+    }
+    out.push_str("    </attributes>\n");
+    out.push_str("    <attributes class=\"edge\">\n");
+    for (i, name) in edge_attributes.iter().enumerate() {
+        let _ = writeln!(out, "      <attribute id=\"{}\" title=\"{}\" type=\"string\"/>", i, xml_escape(name));
+    }
+    out.push_str("    </attributes>\n");
+
+    out.push_str("    <nodes>\n");
+    for node in &graph.nodes {
+        let _ = writeln!(out, "      <node id=\"{}\" label=\"{}\">", xml_escape(&node.id), xml_escape(&node.label));
+        out.push_str("        <attvalues>\n");
+        for (i, name) in node_attributes.keys().enumerate() {
+            if let Some(value) = node.metadata.get(name) {
+                let _ = writeln!(out, "          <attvalue for=\"{}\" value=\"{}\"/>", i, xml_escape(&value_text(value)));
+            }
+        }
+        out.push_str("        </attvalues>\n");
+        let _ = writeln!(out, "        <viz:position x=\"{}\" y=\"{}\" z=\"{}\"/>", node.x, node.y, node.z);
+        out.push_str("      </node>\n");
+    }
+    out.push_str("    </nodes>\n");
+
+    out.push_str("    <edges>\n");
+    for (index, edge) in graph.edges.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" weight=\"{}\" type=\"{}\" label=\"{}\">",
+            index,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target_node),
+            edge.weight,
+            if edge.directed { "directed" } else { "undirected" },
+            kind_name(edge.kind)
+        );
+        out.push_str("        <attvalues>\n");
+        for (i, name) in edge_attributes.iter().enumerate() {
+            if let Some(value) = edge.attributes.get(name) {
+                let _ = writeln!(out, "          <attvalue for=\"{}\" value=\"{}\"/>", i, xml_escape(value));
+            }
+        }
+        out.push_str("        </attvalues>\n");
+        out.push_str("      </edge>\n");
+    }
+    out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    out
+}
+
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Graphviz DOT. Positions go into `pos` in points, pinned, so `neato -n` keeps the layout.
+fn to_dot(graph: &GraphData) -> String {
+    let directed = graph.edges.iter().any(|e| e.directed);
+    let arrow = if directed { "->" } else { "--" };
+    let mut out = String::new();
+
+    let _ = writeln!(out, "{} G {{", if directed { "digraph" } else { "graph" });
+    for node in &graph.nodes {
+        let mut attributes = vec![
+            format!("label={}", dot_quote(&node.label)),
+            format!("pos=\"{},{}!\"", node.x, node.y),
+            format!("z={}", node.z),
+        ];
+        let mut metadata: Vec<_> = node.metadata.iter().collect();
+        metadata.sort_by(|a, b| a.0.cmp(b.0));
+        attributes.extend(metadata.into_iter()
+            .map(|(key, value)| format!("{}={}", dot_quote(key), dot_quote(&value_text(value)))));
+        let _ = writeln!(out, "  {} [{}];", dot_quote(&node.id), attributes.join(", "));
+    }
+    for edge in &graph.edges {
+        let mut attributes = vec![
+            format!("weight={}", edge.weight),
+            format!("kind={}", dot_quote(&kind_name(edge.kind))),
+        ];
+        if directed && !edge.directed {
+            attributes.push("dir=none".to_string());
+        }
+        let mut extra: Vec<_> = edge.attributes.iter().collect();
+        extra.sort();
+        attributes.extend(extra.into_iter().map(|(key, value)| format!("{}={}", dot_quote(key), dot_quote(value))));
+        let _ = writeln!(
+            out,
+            "  {} {} {} [{}];",
+            dot_quote(&edge.source), arrow, dot_quote(&edge.target_node), attributes.join(", ")
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// JSON Graph Format v2: nodes keyed by id, typed metadata kept as JSON values.
+fn to_json_graph(graph: &GraphData) -> Value {
+    let mut nodes = Map::new();
+    for node in &graph.nodes {
+        let mut metadata: Map<String, Value> = node.metadata.iter()
+            .map(|(key, value)| (key.clone(), serde_json::to_value(value).unwrap_or(Value::Null)))
+            .collect();
+        metadata.insert("x".to_string(), json!(node.x));
+        metadata.insert("y".to_string(), json!(node.y));
+        metadata.insert("z".to_string(), json!(node.z));
+        nodes.insert(node.id.clone(), json!({ "label": node.label, "metadata": metadata }));
+    }
+
+    let edges: Vec<Value> = graph.edges.iter().map(|edge: &Edge| {
+        let mut metadata: Map<String, Value> = edge.attributes.iter()
+            .map(|(key, value)| (key.clone(), json!(value)))
+            .collect();
+        metadata.insert("weight".to_string(), json!(edge.weight));
+        json!({
+            "source": edge.source,
+            "target": edge.target_node,
+            "relation": kind_name(edge.kind),
+            "directed": edge.directed,
+            "metadata": metadata
+        })
+    }).collect();
+
+    json!({
+        "graph": {
+            "directed": mostly_directed(graph),
+            "nodes": nodes,
+            "edges": edges
+        }
+    })
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let mut row = fields.into_iter().map(|f| csv_field(&f)).collect::<Vec<_>>().join(",");
+    row.push('\n');
+    row
+}
+
+fn nodes_to_csv(graph: &GraphData) -> String {
+    let attributes: Vec<String> = node_attributes(graph).into_keys().collect();
+    let mut out = csv_row(["id", "label", "x", "y", "z"].into_iter().map(str::to_string).chain(attributes.iter().cloned()));
+    for node in &graph.nodes {
+        let fixed = [node.id.clone(), node.label.clone(), node.x.to_string(), node.y.to_string(), node.z.to_string()];
+        let values = attributes.iter().map(|key| node.metadata.get(key).map(value_text).unwrap_or_default());
+        out.push_str(&csv_row(fixed.into_iter().chain(values)));
+    }
+    out
+}
+
+fn edges_to_csv(graph: &GraphData) -> String {
+    let attributes = edge_attributes(graph);
+    let header = ["source", "target", "weight", "kind", "directed"].into_iter().map(str::to_string);
+    let mut out = csv_row(header.chain(attributes.iter().cloned()));
+    for edge in &graph.edges {
+        let fixed = [
+            edge.source.clone(),
+            edge.target_node.clone(),
+            edge.weight.to_string(),
+            kind_name(edge.kind),
+            edge.directed.to_string(),
+        ];
+        let values = attributes.iter().map(|key| edge.attributes.get(key).cloned().unwrap_or_default());
+        out.push_str(&csv_row(fixed.into_iter().chain(values)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::node::Node;
+    use std::collections::HashMap;
+
+    fn sample() -> GraphData {
+        let mut a = Node::new("A & <B>".to_string());
+        a.x = 1.5;
+        a.metadata.insert("pagerank".to_string(), PropertyValue::Number(0.25));
+        a.metadata.insert("tags".to_string(), PropertyValue::List(vec!["rust".to_string(), "xr".to_string()]));
+        let mut c = Node::new("C".to_string());
+        c.label = "Say \"hi\", C".to_string();
+        c.metadata.insert("pagerank".to_string(), PropertyValue::Number(0.75));
+
+        GraphData {
+            nodes: vec![a, c],
+            edges: vec![
+                Edge::new("A & <B>".to_string(), "C".to_string(), 2.0).with_attribute("count", 2),
+                Edge::new("C".to_string(), "A & <B>".to_string(), 0.5)
+                    .with_kind(EdgeKind::SemanticSimilarity)
+                    .with_directed(false),
+            ],
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_xml_formats_escape_and_type_attributes() {
+        let graphml = export(&sample(), ExportFormat::GraphMl);
+        assert!(graphml.contains("<node id=\"A &amp; &lt;B&gt;\">"));
+        assert!(graphml.contains("attr.name=\"pagerank\" attr.type=\"double\""));
+        assert!(graphml.contains("<data key=\"x\">1.5</data>"));
+        assert!(graphml.contains("directed=\"false\""));
+
+        let gexf = export(&sample(), ExportFormat::Gexf);
+        assert!(gexf.contains("<viz:position x=\"1.5\" y=\"0\" z=\"0\"/>"));
+        assert!(gexf.contains("type=\"undirected\" label=\"semantic_similarity\""));
+        assert!(gexf.contains("value=\"rust;xr\""));
+    }
+
+    #[test]
+    fn test_dot_and_json_graph() {
+        let dot = export(&sample(), ExportFormat::Dot);
+        assert!(dot.starts_with("digraph G {"));
+        assert!(dot.contains("\"C\" [label=\"Say \\\"hi\\\", C\""));
+        assert!(dot.contains("\"C\" -> \"A & <B>\" [weight=0.5, kind=\"semantic_similarity\", dir=none];"));
+
+        let jgf: Value = serde_json::from_str(&export(&sample(), ExportFormat::JsonGraph)).unwrap();
+        assert_eq!(jgf["graph"]["nodes"]["C"]["metadata"]["pagerank"], json!(0.75));
+        assert_eq!(jgf["graph"]["edges"][0]["metadata"]["count"], json!("2"));
+    }
+
+    #[test]
+    fn test_csv_lists() {
+        let nodes = export(&sample(), ExportFormat::CsvNodes);
+        let lines: Vec<&str> = nodes.lines().collect();
+        assert_eq!(lines[0], "id,label,x,y,z,pagerank,tags");
+        assert_eq!(lines[2], "C,\"Say \"\"hi\"\", C\",0,0,0,0.75,");
+
+        let edges = export(&sample(), ExportFormat::CsvEdges);
+        assert_eq!(edges.lines().nth(1), Some("A & <B>,C,2,wikilink,true,2"));
+    }
+}
@@ -1,5 +1,11 @@
 use crate::models::metadata::Metadata;
 use crate::config::Settings;
+use crate::models::reference::ReferenceKind;
+use crate::services::content_store::{ContentStore, StoredFile};
+use crate::services::metadata_file;
+use crate::services::publish_filter::{FilterDecision, PublishFilter};
+use crate::utils::page_properties::{split_property_list, PageProperties};
+use crate::utils::reference_parser::{self, ReferenceParser};
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use reqwest::header::{HeaderMap, HeaderValue};
@@ -53,11 +59,8 @@ pub struct ProcessedFile {
     pub metadata: Metadata,
 }
 
-// Structure to hold reference information
-#[derive(Default)]
-struct ReferenceInfo {
-    direct_mentions: usize,
-}
+/// Reference counts per target page, broken down by reference type
+type ReferenceCounts = HashMap<String, HashMap<ReferenceKind, usize>>;
 
 #[async_trait]
 pub trait GitHubService: Send + Sync {
@@ -207,23 +210,37 @@ impl GitHubService for RealGitHubService {
     }
 }
 
+/// Differences between the content store, the markdown files on disk and metadata.json.
+#[derive(Debug, Default, Serialize)]
+pub struct MetadataReport {
+    /// In the content store but not on disk
+    pub missing_files: Vec<String>,
+    /// On disk but not in the content store
+    pub untracked_files: Vec<String>,
+    /// Stored content differs from disk, or stored metadata from the stored content
+    pub stale_files: Vec<String>,
+    /// metadata.json doesn't match the content store's metadata
+    pub outdated_metadata_json: bool,
+    pub repaired: bool,
+}
+
+impl MetadataReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_files.is_empty() && self.untracked_files.is_empty() && self.stale_files.is_empty()
+            && !self.outdated_metadata_json
+    }
+}
+
 pub struct FileService;
 
 impl FileService {
     /// Load or create metadata from file
     pub fn load_or_create_metadata() -> Result<HashMap<String, Metadata>, Box<dyn StdError + Send + Sync>> {
-        if let Ok(content) = fs::read_to_string(METADATA_PATH) {
-            if !content.trim().is_empty() {
-                if let Ok(metadata) = serde_json::from_str(&content) {
-                    return Ok(metadata);
-                }
-            }
-        }
-        Ok(HashMap::new())
+        metadata_file::load(Path::new(METADATA_PATH))
     }
 
     /// Calculate node size using logarithmic scaling
-    fn calculate_node_size(file_size: usize) -> f64 {
+    pub fn calculate_node_size(file_size: usize) -> f64 {
         if file_size == 0 {
             return MIN_NODE_SIZE;
         }
@@ -236,37 +253,97 @@ impl FileService {
         MIN_NODE_SIZE + (log_size - min_log) * (MAX_NODE_SIZE - MIN_NODE_SIZE) / (max_log - min_log)
     }
 
-    /// Extract both direct mentions and hyperlink references to other files
-    fn extract_references(content: &str, valid_nodes: &[String]) -> HashMap<String, ReferenceInfo> {
-        let mut references = HashMap::new();
-        
-        for node_name in valid_nodes {
-            let mut ref_info = ReferenceInfo::default();
-            
-            // Count direct mentions (case insensitive)
-            let direct_pattern = format!(r"(?i)\[\[{}]]|\b{}\b", regex::escape(node_name), regex::escape(node_name));
-            if let Ok(re) = Regex::new(&direct_pattern) {
-                ref_info.direct_mentions = re.find_iter(content).count();
+    /// Build a reference parser aware of every page, its aliases and its block ids.
+    ///
+    /// `contents` holds freshly fetched pages keyed by node name; pages that are
+    /// only known through `metadata_map` contribute their stored aliases and block ids.
+    pub fn build_reference_parser(
+        valid_nodes: &[String],
+        contents: &HashMap<String, String>,
+        metadata_map: &HashMap<String, Metadata>,
+    ) -> ReferenceParser {
+        let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+        let mut block_index = HashMap::new();
+
+        for (file_name, metadata) in metadata_map {
+            let node_name = file_name.trim_end_matches(".md");
+            if contents.contains_key(node_name) {
+                continue;
             }
-            
-            if ref_info.direct_mentions > 0 {
-                references.insert(node_name.clone(), ref_info);
+            aliases.insert(node_name.to_string(), metadata.aliases.clone());
+            for block_id in &metadata.block_ids {
+                block_index.insert(block_id.clone(), node_name.to_string());
             }
         }
-        
+
+        for (node_name, content) in contents {
+            aliases.insert(node_name.clone(), Self::extract_aliases(content));
+            for block_id in reference_parser::block_ids(content) {
+                block_index.insert(block_id, node_name.clone());
+            }
+        }
+
+        ReferenceParser::new(valid_nodes)
+            .with_aliases(&aliases)
+            .with_block_index(block_index)
+    }
+
+    /// Aliases declared with an `alias::` page property or front matter `aliases`/`alias`
+    fn extract_aliases(content: &str) -> Vec<String> {
+        let props = PageProperties::parse(content);
+        let mut aliases: Vec<String> = props.property("alias")
+            .map(split_property_list)
+            .unwrap_or_default();
+        aliases.extend(props.front_matter_values("aliases"));
+        aliases.extend(props.front_matter_values("alias"));
+        aliases
+    }
+
+    /// Properties, tags, title and dates declared in the page itself
+    fn extract_page_metadata(content: &str) -> Metadata {
+        let props = PageProperties::parse(content);
+        Metadata {
+            properties: props.typed_properties(),
+            tags: props.tags(content),
+            title: props.title(content),
+            created: props.date(&["created", "created-at", "date"]),
+            updated: props.date(&["updated", "updated-at", "modified"]),
+            ..Default::default()
+        }
+    }
+
+    /// Extract typed references to other known pages
+    fn extract_references(
+        node_name: &str,
+        content: &str,
+        parser: &ReferenceParser,
+        valid_nodes: &HashSet<&str>,
+    ) -> ReferenceCounts {
+        let mut references: ReferenceCounts = HashMap::new();
+
+        for reference in parser.parse(content) {
+            if reference.target == node_name || !valid_nodes.contains(reference.target.as_str()) {
+                continue;
+            }
+            *references.entry(reference.target)
+                .or_default()
+                .entry(reference.kind)
+                .or_insert(0) += 1;
+        }
+
         references
     }
 
-    fn convert_references_to_topic_counts(references: HashMap<String, ReferenceInfo>) -> HashMap<String, usize> {
-        references.into_iter()
-            .map(|(name, info)| (name, info.direct_mentions))
+    fn convert_references_to_topic_counts(references: &ReferenceCounts) -> HashMap<String, usize> {
+        references.iter()
+            .map(|(name, counts)| (name.clone(), counts.values().sum()))
             .collect()
     }
 
     /// Initialize the local markdown directory and metadata structure.
     pub async fn initialize_local_storage(
         github_service: &dyn GitHubService,
-        _settings: Arc<RwLock<Settings>>,
+        settings: Arc<RwLock<Settings>>,
     ) -> Result<(), Box<dyn StdError + Send + Sync>> {
         info!("Checking local storage status");
         
@@ -280,6 +357,7 @@ impl FileService {
         }
 
         info!("Initializing local storage with files from GitHub");
+        let filter = PublishFilter::new(&settings.read().await.publish_filter);
 
         // Step 1: Get all markdown files from GitHub
         let github_files = github_service.fetch_file_metadata().await?;
@@ -293,10 +371,9 @@ impl FileService {
         for file_meta in github_files {
             match github_service.fetch_file_content(&file_meta.download_url).await {
                 Ok(content) => {
-                    // Check if file starts with "public:: true"
-                    let first_line = content.lines().next().unwrap_or("").trim();
-                    if first_line != "public:: true" {
-                        debug!("Skipping non-public file: {}", file_meta.name);
+                    let decision = filter.evaluate(&file_meta.name, &content);
+                    if !decision.publish {
+                        debug!("Skipping non-public file {}: {}", file_meta.name, decision.reason);
                         continue;
                     }
 
@@ -314,6 +391,8 @@ impl FileService {
 
         // Get list of valid node names (filenames without .md)
         let valid_nodes: Vec<String> = file_contents.keys().cloned().collect();
+        let valid_node_set: HashSet<&str> = valid_nodes.iter().map(String::as_str).collect();
+        let parser = Self::build_reference_parser(&valid_nodes, &file_contents, &HashMap::new());
 
         // Step 3: Second pass - extract references and create metadata
         let mut metadata_map = HashMap::new();
@@ -329,8 +408,8 @@ impl FileService {
             fs::write(&file_path, content)?;
 
             // Extract references
-            let references = Self::extract_references(content, &valid_nodes);
-            let topic_counts = Self::convert_references_to_topic_counts(references);
+            let reference_counts = Self::extract_references(node_name, content, &parser, &valid_node_set);
+            let topic_counts = Self::convert_references_to_topic_counts(&reference_counts);
 
             // Get GitHub metadata
             let github_meta = file_metadata.get(&file_name).unwrap();
@@ -351,6 +430,10 @@ impl FileService {
                 perplexity_link: String::new(),
                 last_perplexity_process: None,
                 topic_counts,
+                reference_counts,
+                aliases: Self::extract_aliases(content),
+                block_ids: reference_parser::block_ids(content),
+                ..Self::extract_page_metadata(content)
             };
 
             metadata_map.insert(file_name, metadata);
@@ -367,30 +450,16 @@ impl FileService {
 
     /// Check if we have a valid local setup
     fn has_valid_local_setup() -> bool {
-        // Check if metadata.json exists and is not empty
-        if let Ok(metadata_content) = fs::read_to_string(METADATA_PATH) {
-            if metadata_content.trim().is_empty() {
-                return false;
-            }
-            
-            // Try to parse metadata to ensure it's valid
-            if let Ok(metadata_map) = serde_json::from_str::<HashMap<String, Metadata>>(&metadata_content) {
-                if metadata_map.is_empty() {
-                    return false;
-                }
-                
+        // Check if metadata.json exists, parses, and is not empty
+        match Self::load_or_create_metadata() {
+            Ok(metadata_map) if !metadata_map.is_empty() => {
                 // Check if the markdown files referenced in metadata actually exist
-                for (filename, _) in metadata_map {
-                    let file_path = format!("{}/{}", MARKDOWN_DIR, filename);
-                    if !Path::new(&file_path).exists() {
-                        return false;
-                    }
-                }
-                
-                return true;
+                metadata_map.keys().all(|filename| {
+                    Path::new(&format!("{}/{}", MARKDOWN_DIR, filename)).exists()
+                })
             }
+            _ => false,
         }
-        false
     }
 
     /// Ensures all required directories exist
@@ -402,11 +471,12 @@ impl FileService {
     /// Handles incremental updates after initial setup
     pub async fn fetch_and_process_files(
         github_service: &dyn GitHubService,
-        _settings: Arc<RwLock<Settings>>,
+        settings: Arc<RwLock<Settings>>,
         metadata_map: &mut HashMap<String, Metadata>,
     ) -> Result<Vec<ProcessedFile>, Box<dyn StdError + Send + Sync>> {
         // Ensure directories exist before any operations
         Self::ensure_directories()?;
+        let filter = PublishFilter::new(&settings.read().await.publish_filter);
 
         // Get metadata for markdown files in target directory
         let github_files_metadata = github_service.fetch_file_metadata().await?;
@@ -414,9 +484,6 @@ impl FileService {
 
         let mut processed_files = Vec::new();
 
-        // Save current metadata
-        Self::save_metadata(metadata_map)?;
-
         // Clean up local files that no longer exist in GitHub
         let github_files: HashSet<_> = github_files_metadata.iter()
             .map(|meta| meta.name.clone())
@@ -426,11 +493,7 @@ impl FileService {
         let removed_files: Vec<_> = local_files.difference(&github_files).collect();
 
         for file_name in removed_files {
-            let file_path = format!("{}/{}", MARKDOWN_DIR, file_name);
-            if let Err(e) = fs::remove_file(&file_path) {
-                error!("Failed to remove file {}: {}", file_path, e);
-            }
-            metadata_map.remove(file_name);
+            Self::forget(metadata_map, file_name);
         }
 
         // Get list of valid node names (filenames without .md)
@@ -446,46 +509,14 @@ impl FileService {
             })
             .collect();
 
-        // Process each file
+        // Fetch content of each changed public file
+        let mut fetched = Vec::new();
         for file_meta in files_to_process {
             match github_service.fetch_file_content(&file_meta.download_url).await {
                 Ok(content) => {
-                    let first_line = content.lines().next().unwrap_or("").trim();
-                    if first_line != "public:: true" {
-                        debug!("Skipping non-public file: {}", file_meta.name);
-                        continue;
+                    if Self::admit(&filter, metadata_map, &file_meta.name, &content) {
+                        fetched.push((file_meta, content));
                     }
-
-                    let file_path = format!("{}/{}", MARKDOWN_DIR, file_meta.name);
-                    fs::write(&file_path, &content)?;
-
-                    // Extract references
-                    let references = Self::extract_references(&content, &valid_nodes);
-                    let topic_counts = Self::convert_references_to_topic_counts(references);
-
-                    // Calculate node size
-                    let file_size = content.len();
-                    let node_size = Self::calculate_node_size(file_size);
-
-                    let new_metadata = Metadata {
-                        file_name: file_meta.name.clone(),
-                        file_size,
-                        node_size,
-                        hyperlink_count: Self::count_hyperlinks(&content),
-                        sha1: Self::calculate_sha1(&content),
-                        last_modified: file_meta.last_modified.unwrap_or_else(|| Utc::now()),
-                        perplexity_link: String::new(),
-                        last_perplexity_process: None,
-                        topic_counts,
-                    };
-
-                    metadata_map.insert(file_meta.name.clone(), new_metadata.clone());
-                    processed_files.push(ProcessedFile {
-                        file_name: file_meta.name,
-                        content,
-                        is_public: true,
-                        metadata: new_metadata,
-                    });
                 }
                 Err(e) => {
                     error!("Failed to fetch content: {}", e);
@@ -494,21 +525,251 @@ impl FileService {
             sleep(GITHUB_API_DELAY).await;
         }
 
-        // Save updated metadata
-        Self::save_metadata(metadata_map)?;
+        let fetched_contents: HashMap<String, String> = fetched.iter()
+            .map(|(file_meta, content)| (file_meta.name.trim_end_matches(".md").to_string(), content.clone()))
+            .collect();
+        let valid_node_set: HashSet<&str> = valid_nodes.iter().map(String::as_str).collect();
+        let parser = Self::build_reference_parser(&valid_nodes, &fetched_contents, metadata_map);
+
+        // Process each file
+        for (file_meta, content) in fetched {
+            let file_path = format!("{}/{}", MARKDOWN_DIR, file_meta.name);
+            fs::write(&file_path, &content)?;
+
+            // Extract references
+            let node_name = file_meta.name.trim_end_matches(".md");
+            let reference_counts = Self::extract_references(node_name, &content, &parser, &valid_node_set);
+            let topic_counts = Self::convert_references_to_topic_counts(&reference_counts);
+
+            // Calculate node size
+            let file_size = content.len();
+            let node_size = Self::calculate_node_size(file_size);
+
+            let new_metadata = Metadata {
+                file_name: file_meta.name.clone(),
+                file_size,
+                node_size,
+                hyperlink_count: Self::count_hyperlinks(&content),
+                sha1: Self::calculate_sha1(&content),
+                last_modified: file_meta.last_modified.unwrap_or_else(|| Utc::now()),
+                perplexity_link: String::new(),
+                last_perplexity_process: None,
+                topic_counts,
+                aliases: Self::extract_aliases(&content),
+                block_ids: reference_parser::block_ids(&content),
+                reference_counts,
+                ..Self::extract_page_metadata(&content)
+            };
+
+            metadata_map.insert(file_meta.name.clone(), new_metadata.clone());
+            processed_files.push(ProcessedFile {
+                file_name: file_meta.name,
+                content,
+                is_public: true,
+                metadata: new_metadata,
+            });
+        }
 
         Ok(processed_files)
     }
 
+    /// Whether a fetched file is published. One that isn't is forgotten, so a page made
+    /// private or excluded by a filter change leaves the store and the graph.
+    fn admit(filter: &PublishFilter, metadata_map: &mut HashMap<String, Metadata>, file_name: &str, content: &str) -> bool {
+        let decision = filter.evaluate(file_name, content);
+        if !decision.publish {
+            debug!("Skipping non-public file {}: {}", file_name, decision.reason);
+            Self::forget(metadata_map, file_name);
+        }
+        decision.publish
+    }
+
+    /// Drop a page's metadata, and with it its store entry on the next
+    /// `store_processed_files`, and delete its markdown copy
+    fn forget(metadata_map: &mut HashMap<String, Metadata>, file_name: &str) {
+        if metadata_map.remove(file_name).is_none() {
+            return;
+        }
+        let file_path = format!("{}/{}", MARKDOWN_DIR, file_name);
+        match fs::remove_file(&file_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => error!("Failed to remove file {}: {}", file_path, e),
+            _ => {}
+        }
+    }
+
+    /// Evaluate the publish filter against every file in GitHub without
+    /// writing anything locally, reporting what would be published and why.
+    pub async fn preview_publish_filter(
+        github_service: &dyn GitHubService,
+        settings: Arc<RwLock<Settings>>,
+    ) -> Result<Vec<FilterDecision>, Box<dyn StdError + Send + Sync>> {
+        let filter = PublishFilter::new(&settings.read().await.publish_filter);
+        let github_files = github_service.fetch_file_metadata().await?;
+        let mut decisions = Vec::with_capacity(github_files.len());
+
+        for file_meta in github_files {
+            match github_service.fetch_file_content(&file_meta.download_url).await {
+                Ok(content) => decisions.push(filter.evaluate(&file_meta.name, &content)),
+                Err(e) => {
+                    error!("Failed to fetch content for {}: {}", file_meta.name, e);
+                    decisions.push(FilterDecision {
+                        file_name: file_meta.name,
+                        publish: false,
+                        reason: format!("failed to fetch content: {}", e),
+                    });
+                }
+            }
+            sleep(GITHUB_API_DELAY).await;
+        }
+
+        Ok(decisions)
+    }
+
+    /// One-time import of markdown files and metadata.json written before the
+    /// content store existed, so existing deployments boot without refetching.
+    pub fn seed_content_store(store: &ContentStore) -> Result<usize, Box<dyn StdError + Send + Sync>> {
+        if !store.is_empty()? {
+            return Ok(0);
+        }
+
+        let mut imported = 0;
+        for (file_name, metadata) in Self::load_or_create_metadata()? {
+            let file_path = format!("{}/{}", MARKDOWN_DIR, file_name);
+            match fs::read_to_string(&file_path) {
+                Ok(content) => {
+                    store.put(&file_name, &content, &metadata)?;
+                    imported += 1;
+                }
+                Err(e) => error!("Skipping {} during content store import: {}", file_path, e),
+            }
+        }
+
+        info!("Imported {} local files into the content store", imported);
+        Ok(imported)
+    }
+
+    /// Persist freshly processed files and drop pages no longer in `metadata_map`.
+    /// metadata.json is then rewritten from the store for tools that still read it.
+    pub fn store_processed_files(
+        store: &ContentStore,
+        processed_files: &[ProcessedFile],
+        metadata_map: &HashMap<String, Metadata>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        Self::update_store(store, processed_files, metadata_map)?;
+        Self::save_metadata(&store.metadata()?)
+    }
+
+    fn update_store(
+        store: &ContentStore,
+        processed_files: &[ProcessedFile],
+        metadata_map: &HashMap<String, Metadata>,
+    ) -> Result<(), rusqlite::Error> {
+        for processed_file in processed_files {
+            store.put(&processed_file.file_name, &processed_file.content, &processed_file.metadata)?;
+            debug!("Stored file: {}", processed_file.file_name);
+        }
+        store.retain(|file_name| metadata_map.contains_key(file_name))?;
+        Ok(())
+    }
+
     /// Save metadata to file
     pub fn save_metadata(metadata: &HashMap<String, Metadata>) -> Result<(), Box<dyn StdError + Send + Sync>> {
-        let json = serde_json::to_string_pretty(metadata)?;
-        fs::write(METADATA_PATH, json)?;
-        Ok(())
+        metadata_file::save(Path::new(METADATA_PATH), metadata)
+    }
+
+    /// Reconcile the content store with the markdown files on disk, and metadata.json
+    /// with the store. With `repair`, markdown copies missing on disk are restored from
+    /// the store, untracked files are stored, stale entries are recomputed from disk,
+    /// and metadata.json is regenerated from the store.
+    pub fn validate_metadata(store: &ContentStore, repair: bool) -> Result<MetadataReport, Box<dyn StdError + Send + Sync>> {
+        let stored: HashMap<String, StoredFile> = store.all()?.into_iter()
+            .map(|file| (file.file_name.clone(), file))
+            .collect();
+        let mut report = MetadataReport::default();
+
+        let mut disk_files = HashMap::new();
+        for entry in fs::read_dir(MARKDOWN_DIR)?.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                continue;
+            }
+            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+                let modified = entry.metadata()?.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+                disk_files.insert(file_name.to_string(), (fs::read_to_string(&path)?, modified));
+            }
+        }
+
+        for file_name in stored.keys() {
+            if !disk_files.contains_key(file_name) {
+                report.missing_files.push(file_name.clone());
+            }
+        }
+        for (file_name, (content, _)) in &disk_files {
+            match stored.get(file_name) {
+                None => report.untracked_files.push(file_name.clone()),
+                Some(file) if file.content != *content || file.metadata.sha1 != Self::calculate_sha1(&file.content) => {
+                    report.stale_files.push(file_name.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        report.missing_files.sort();
+        report.untracked_files.sort();
+        report.stale_files.sort();
+
+        let store_metadata = serde_json::to_value(store.metadata()?)?;
+        report.outdated_metadata_json = Self::load_or_create_metadata().ok()
+            .and_then(|metadata_map| serde_json::to_value(metadata_map).ok())
+            .is_none_or(|metadata_json| metadata_json != store_metadata);
+
+        if repair && !report.is_consistent() {
+            for file_name in &report.missing_files {
+                fs::write(format!("{}/{}", MARKDOWN_DIR, file_name), &stored[file_name].content)?;
+            }
+
+            let contents: HashMap<String, String> = disk_files.iter()
+                .map(|(file_name, (content, _))| (file_name, content))
+                .chain(report.missing_files.iter().map(|file_name| (file_name, &stored[file_name].content)))
+                .map(|(file_name, content)| (file_name.trim_end_matches(".md").to_string(), content.clone()))
+                .collect();
+            let valid_nodes: Vec<String> = contents.keys().cloned().collect();
+            let valid_node_set: HashSet<&str> = valid_nodes.iter().map(String::as_str).collect();
+            let parser = Self::build_reference_parser(&valid_nodes, &contents, &HashMap::new());
+
+            for file_name in report.untracked_files.iter().chain(&report.stale_files) {
+                let (content, modified) = &disk_files[file_name];
+                let node_name = file_name.trim_end_matches(".md");
+                let reference_counts = Self::extract_references(node_name, content, &parser, &valid_node_set);
+                let previous = stored.get(file_name).map(|file| file.metadata.clone()).unwrap_or_default();
+
+                store.put(file_name, content, &Metadata {
+                    file_name: file_name.clone(),
+                    file_size: content.len(),
+                    node_size: Self::calculate_node_size(content.len()),
+                    hyperlink_count: Self::count_hyperlinks(content),
+                    sha1: Self::calculate_sha1(content),
+                    last_modified: *modified,
+                    perplexity_link: previous.perplexity_link,
+                    last_perplexity_process: previous.last_perplexity_process,
+                    topic_counts: Self::convert_references_to_topic_counts(&reference_counts),
+                    reference_counts,
+                    aliases: Self::extract_aliases(content),
+                    block_ids: reference_parser::block_ids(content),
+                    ..Self::extract_page_metadata(content)
+                })?;
+            }
+
+            Self::save_metadata(&store.metadata()?)?;
+            report.repaired = true;
+            info!("Repaired content store: {} restored, {} added, {} refreshed",
+                report.missing_files.len(), report.untracked_files.len(), report.stale_files.len());
+        }
+
+        Ok(report)
     }
 
     /// Calculate SHA1 hash of content
-    fn calculate_sha1(content: &str) -> String {
+    pub fn calculate_sha1(content: &str) -> String {
         use sha1::{Sha1, Digest};
         let mut hasher = Sha1::new();
         hasher.update(content.as_bytes());
@@ -521,3 +782,26 @@ impl FileService {
         re.find_iter(content).count()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PublishFilterSettings;
+
+    #[test]
+    fn test_page_made_private_leaves_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::open(dir.path().join("content.db")).unwrap();
+        let filter = PublishFilter::new(&PublishFilterSettings::default());
+        let public = "public:: true\n- Launch plans";
+        let metadata = Metadata { file_name: "Secret.md".to_string(), ..Default::default() };
+        store.put("Secret.md", public, &metadata).unwrap();
+        let mut metadata_map = HashMap::from([("Secret.md".to_string(), metadata)]);
+
+        assert!(!FileService::admit(&filter, &mut metadata_map, "Secret.md", "public:: false\n- Launch plans"));
+        assert!(metadata_map.is_empty());
+        FileService::update_store(&store, &[], &metadata_map).unwrap();
+        assert!(store.get("Secret.md").unwrap().is_none());
+        assert!(store.metadata().unwrap().is_empty());
+    }
+}
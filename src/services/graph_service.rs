@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::io::Error;
@@ -9,9 +9,16 @@ use log::{info, warn};
 use rand::Rng;
 use crate::models::graph::GraphData;
 use crate::models::node::Node;
-use crate::models::edge::Edge;
+use crate::models::edge::{Edge, EdgeKind};
+use crate::models::metadata::{Metadata, PageEmbedding, PropertyValue};
+use crate::models::reference::ReferenceKind;
+use crate::config::{BlockGraphSettings, ReferenceSettings, SemanticSettings};
 use crate::models::simulation_params::SimulationParams;
+use crate::services::embedding_service::NeighbourCache;
+use crate::services::file_service::FileService;
+use crate::services::import_service::GraphSource;
 use crate::utils::gpu_compute::GPUCompute;
+use crate::utils::outline;
 use crate::AppState;
 
 pub struct FileMetadata {
@@ -30,30 +37,273 @@ impl GraphService {
     }
 
     pub async fn build_graph(state: &web::Data<AppState>) -> Result<GraphData, Box<dyn std::error::Error + Send + Sync>> {
-        let file_cache = state.file_cache.read().await;
+        if let Some(source) = state.graph_source.read().await.clone() {
+            return Self::build_imported_graph(source.as_ref()).await;
+        }
+
+        let (reference_weights, block_settings, semantic_settings) = {
+            let settings = state.settings.read().await;
+            (settings.references.clone(), settings.blocks.clone(), settings.semantic.clone())
+        };
+        let stored_files = state.content_store.blocking(|store| store.all()).await?;
+        let contents: HashMap<String, String> = stored_files.iter()
+            .map(|file| (file.file_name.clone(), file.content.clone()))
+            .collect();
+        let versions: HashMap<String, String> = stored_files.iter()
+            .map(|file| (file.file_name.clone(), file.version.clone()))
+            .collect();
+        let metadata_map: HashMap<String, Metadata> = stored_files.into_iter()
+            .map(|file| (file.file_name, file.metadata))
+            .collect();
         let mut graph = GraphData::new();
-        let edge_map = HashMap::new();
+        let mut edges = Vec::new();
 
-        // Build nodes from file cache
-        for (file_name, _content) in file_cache.iter() {
+        // Build nodes from the content store
+        for file_name in contents.keys() {
             let source_id = file_name.trim_end_matches(".md").to_string();
-            if !graph.nodes.iter().any(|n| n.id == source_id) {
-                graph.nodes.push(Node::new(source_id));
+            if graph.nodes.iter().any(|n| n.id == source_id) {
+                continue;
+            }
+            let mut node = Node::new(source_id);
+            if let Some(metadata) = metadata_map.get(file_name) {
+                Self::apply_node_metadata(&mut node, metadata);
+                // Embeddings only feed semantic edges; clients don't need the vectors
+                graph.metadata.insert(file_name.clone(), Metadata { embedding: None, ..metadata.clone() });
+            }
+            if let Some(version) = versions.get(file_name) {
+                node.metadata.insert("version".to_string(), PropertyValue::Text(version.clone()));
+            }
+            graph.nodes.push(node);
+        }
+
+        // Build one directed edge per (source, target, kind), weighted per reference type
+        let node_ids: HashSet<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        for (file_name, metadata) in &graph.metadata {
+            let source_id = file_name.trim_end_matches(".md");
+            for (target_id, counts) in &metadata.reference_counts {
+                if source_id == target_id || !node_ids.contains(target_id.as_str()) {
+                    continue;
+                }
+                for (kind, count) in counts {
+                    let edge = Edge::new(
+                        source_id.to_string(),
+                        target_id.clone(),
+                        reference_weights.weight(*kind) * *count as f32,
+                    )
+                    .with_kind(EdgeKind::from(*kind))
+                    .with_attribute("count", count);
+                    edges.push(edge);
+                }
             }
         }
 
         // Initialize random positions for all nodes
         Self::initialize_random_positions(&mut graph);
 
-        // Convert edge_map to edges
-        graph.edges = edge_map.into_iter().map(|((source, target), weight)| {
-            Edge::new(source, target, weight)
-        }).collect();
+        graph.edges = edges;
+
+        if block_settings.enabled {
+            let expanded_pages = state.expanded_pages.read().await;
+            if !expanded_pages.is_empty() {
+                Self::expand_pages(
+                    &mut graph,
+                    &expanded_pages,
+                    &contents,
+                    &metadata_map,
+                    &reference_weights,
+                    &block_settings,
+                );
+            }
+        }
+
+        if semantic_settings.enabled {
+            Self::add_semantic_edges(&mut graph, &metadata_map, &state.embedder.model_id(), &semantic_settings, &state.neighbours).await;
+        }
 
         info!("Built graph with {} nodes and {} edges", graph.nodes.len(), graph.edges.len());
         Ok(graph)
     }
 
+    /// Link pages to their most similar pages by embedding, unless they are already linked.
+    /// Similarity is symmetric, so these edges are undirected.
+    async fn add_semantic_edges(
+        graph: &mut GraphData,
+        metadata_map: &HashMap<String, Metadata>,
+        model: &str,
+        settings: &SemanticSettings,
+        cache: &NeighbourCache,
+    ) {
+        let embeddings: HashMap<String, &PageEmbedding> = metadata_map.iter()
+            .filter_map(|(file_name, metadata)| {
+                let embedding = metadata.embedding.as_ref().filter(|e| e.model == model)?;
+                Some((file_name.trim_end_matches(".md").to_string(), embedding))
+            })
+            .collect();
+        let neighbours = cache.get_or_compute(&embeddings, settings).await;
+
+        let linked: HashSet<(&str, &str)> = graph.edges.iter()
+            .flat_map(|e| [(e.source.as_str(), e.target_node.as_str()), (e.target_node.as_str(), e.source.as_str())])
+            .collect();
+        let semantic_edges: Vec<Edge> = neighbours.iter()
+            .filter(|(a, b, _)| !linked.contains(&(a.as_str(), b.as_str())))
+            .map(|(a, b, similarity)| {
+                Edge::new(a.clone(), b.clone(), settings.weight * similarity)
+                    .with_kind(EdgeKind::SemanticSimilarity)
+                    .with_directed(false)
+                    .with_attribute("similarity", format!("{:.3}", similarity))
+            })
+            .collect();
+
+        info!("Added {} semantic similarity edges", semantic_edges.len());
+        graph.edges.extend(semantic_edges);
+    }
+
+    /// Copy the typed page metadata onto a page node. The page's own properties win over
+    /// the derived attributes of the same name, except `tags` and `aliases`, which
+    /// already include the page's own values.
+    fn apply_node_metadata(node: &mut Node, metadata: &Metadata) {
+        node.file_size = metadata.file_size as u64;
+        node.metadata = metadata.properties.clone();
+        node.metadata.insert("tags".to_string(), PropertyValue::List(metadata.tags.clone()));
+        node.metadata.insert("aliases".to_string(), PropertyValue::List(metadata.aliases.clone()));
+
+        let derived = [
+            ("type", PropertyValue::Text("page".to_string())),
+            ("file_size", PropertyValue::Number(metadata.file_size as f64)),
+            ("node_size", PropertyValue::Number(metadata.node_size)),
+            ("hyperlink_count", PropertyValue::Number(metadata.hyperlink_count as f64)),
+            ("last_modified", PropertyValue::Date(metadata.last_modified)),
+            ("created", PropertyValue::Date(metadata.created.unwrap_or(metadata.last_modified))),
+            ("updated", PropertyValue::Date(metadata.updated.unwrap_or(metadata.last_modified))),
+        ];
+        for (key, value) in derived {
+            node.metadata.entry(key.to_string()).or_insert(value);
+        }
+        if let Some(title) = &metadata.title {
+            node.label = title.clone();
+            node.metadata.entry("title".to_string()).or_insert_with(|| PropertyValue::Text(title.clone()));
+        }
+    }
+
+    /// Replace each expanded page's outgoing edges with its outline blocks.
+    ///
+    /// Every block becomes a node `page#uuid` (or `page#index` without an `id::`)
+    /// contained by its parent block or page. References are attached to the block
+    /// they occur in; block references point at the referenced block when its page
+    /// is expanded too, and at the page otherwise. Collapsed pages keep the
+    /// aggregated per-page edges from metadata, so page-level views never parse content.
+    fn expand_pages(
+        graph: &mut GraphData,
+        expanded_pages: &HashSet<String>,
+        file_contents: &HashMap<String, String>,
+        metadata_map: &HashMap<String, Metadata>,
+        reference_weights: &ReferenceSettings,
+        block_settings: &BlockGraphSettings,
+    ) {
+        let page_ids: Vec<String> = graph.nodes.iter().map(|n| n.id.clone()).collect();
+        let contents: HashMap<String, String> = file_contents.iter()
+            .map(|(file_name, content)| (file_name.trim_end_matches(".md").to_string(), content.clone()))
+            .collect();
+        let parser = FileService::build_reference_parser(&page_ids, &contents, metadata_map);
+
+        let mut pages: Vec<&String> = expanded_pages.iter()
+            .filter(|page| contents.contains_key(page.as_str()) && page_ids.contains(page))
+            .collect();
+        pages.sort();
+
+        // Add block nodes and containment edges, remembering where each block uuid lives
+        let mut block_nodes: HashMap<String, String> = HashMap::new();
+        let mut page_blocks = Vec::new();
+        for page in pages {
+            let mut blocks = outline::parse_outline(&contents[page.as_str()]);
+            blocks.truncate(block_settings.max_blocks_per_page);
+
+            let node_ids: Vec<String> = blocks.iter().enumerate()
+                .map(|(index, block)| match &block.id {
+                    Some(uuid) => format!("{}#{}", page, uuid),
+                    None => format!("{}#{}", page, index),
+                })
+                .collect();
+
+            for (block, node_id) in blocks.iter().zip(&node_ids) {
+                if let Some(uuid) = &block.id {
+                    block_nodes.insert(uuid.clone(), node_id.clone());
+                }
+
+                let mut node = Node::new(node_id.clone());
+                node.label = block.content.lines().next().unwrap_or("").chars().take(40).collect();
+                node.file_size = block.content.len() as u64;
+                node.metadata.insert("type".to_string(), PropertyValue::Text("block".to_string()));
+                node.metadata.insert("page".to_string(), PropertyValue::Text(page.to_string()));
+                node.metadata.insert("depth".to_string(), PropertyValue::Number(block.depth as f64));
+                graph.nodes.push(node);
+
+                let parent = match block.parent {
+                    Some(parent) => node_ids[parent].clone(),
+                    None => page.clone(),
+                };
+                graph.edges.push(
+                    Edge::new(parent, node_id.clone(), block_settings.containment_weight)
+                        .with_kind(EdgeKind::Contains)
+                );
+            }
+
+            page_blocks.push((page, blocks, node_ids));
+        }
+
+        graph.edges.retain(|edge| {
+            edge.kind == EdgeKind::Contains || !page_blocks.iter().any(|(page, _, _)| **page == edge.source)
+        });
+
+        // Attach references to the blocks they occur in
+        let valid_pages: HashSet<&str> = page_ids.iter().map(String::as_str).collect();
+        for (page, blocks, node_ids) in &page_blocks {
+            for (block, source_id) in blocks.iter().zip(node_ids) {
+                let mut counts: HashMap<(String, ReferenceKind), usize> = HashMap::new();
+                for reference in parser.parse(&block.content) {
+                    let target_block = reference.block.as_ref().and_then(|uuid| block_nodes.get(uuid));
+                    let target = match target_block {
+                        Some(block_node) => block_node.clone(),
+                        None if valid_pages.contains(reference.target.as_str()) => reference.target,
+                        None => continue,
+                    };
+                    if target == *source_id || target == **page {
+                        continue;
+                    }
+                    *counts.entry((target, reference.kind)).or_insert(0) += 1;
+                }
+
+                for ((target, kind), count) in counts {
+                    graph.edges.push(
+                        Edge::new(source_id.clone(), target, reference_weights.weight(kind) * count as f32)
+                            .with_kind(EdgeKind::from(kind))
+                            .with_attribute("count", count)
+                    );
+                }
+            }
+        }
+    }
+
+    /// Keep positions of nodes that survive a rebuild; new block nodes start next to their page.
+    pub fn carry_over_positions(graph: &mut GraphData, previous: &GraphData) {
+        let positions: HashMap<&str, (f32, f32, f32)> = previous.nodes.iter()
+            .map(|n| (n.id.as_str(), (n.x, n.y, n.z)))
+            .collect();
+        let mut rng = rand::thread_rng();
+
+        for node in &mut graph.nodes {
+            if let Some(&(x, y, z)) = positions.get(node.id.as_str()) {
+                node.x = x;
+                node.y = y;
+                node.z = z;
+            } else if let Some(&(x, y, z)) = node.metadata.get("page").and_then(PropertyValue::as_str).and_then(|page| positions.get(page)) {
+                node.x = x + rng.gen_range(-2.0..2.0);
+                node.y = y + rng.gen_range(-2.0..2.0);
+                node.z = z + rng.gen_range(-2.0..2.0);
+            }
+        }
+    }
+
     pub async fn load_graph(&self, path: &Path) -> Result<(), Error> {
         info!("Loading graph from {}", path.display());
         let mut nodes = Vec::new();
@@ -98,6 +348,16 @@ impl GraphService {
         Ok(())
     }
 
+    /// Load a graph from an import source, keeping its layout if it has one
+    pub async fn build_imported_graph(source: &dyn GraphSource) -> Result<GraphData, Box<dyn std::error::Error + Send + Sync>> {
+        let mut graph = source.load().await?;
+        if graph.nodes.iter().all(|n| n.x == 0.0 && n.y == 0.0 && n.z == 0.0) {
+            Self::initialize_random_positions(&mut graph);
+        }
+        info!("Imported {} nodes and {} edges from {}", graph.nodes.len(), graph.edges.len(), source.name());
+        Ok(graph)
+    }
+
     fn initialize_random_positions(graph: &mut GraphData) {
         let mut rng = rand::thread_rng();
         let initial_radius = 30.0;
@@ -207,7 +467,6 @@ impl GraphService {
         metadata: &HashMap<String, FileMetadata>
     ) -> Result<GraphData, Box<dyn std::error::Error + Send + Sync>> {
         let mut graph = GraphData::new();
-        let mut edge_map = HashMap::new();
 
         // Build nodes and edges
         for (file_name, file_metadata) in metadata {
@@ -218,27 +477,45 @@ impl GraphService {
                 graph.nodes.push(Node::new(source_id.clone()));
             }
 
-            // Process references
+            // Process references, keeping their direction
             for (target_id, reference_count) in &file_metadata.topic_counts {
                 if source_id != *target_id {
-                    let edge_key = if source_id < *target_id {
-                        (source_id.clone(), target_id.clone())
-                    } else {
-                        (target_id.clone(), source_id.clone())
-                    };
-
-                    edge_map.entry(edge_key)
-                        .and_modify(|weight| { *weight += *reference_count as f32 })
-                        .or_insert(*reference_count as f32);
+                    graph.edges.push(
+                        Edge::new(source_id.clone(), target_id.clone(), *reference_count as f32)
+                            .with_attribute("count", reference_count)
+                    );
                 }
             }
         }
 
-        // Convert edge map to edges
-        graph.edges = edge_map.into_iter().map(|((source, target), weight)| {
-            Edge::new(source, target, weight)
-        }).collect();
-
         Ok(graph)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_properties_win_over_derived_attributes() {
+        let metadata = Metadata {
+            properties: HashMap::from([
+                ("type".to_string(), PropertyValue::Text("project".to_string())),
+                ("created".to_string(), PropertyValue::Text("last spring".to_string())),
+                ("tags".to_string(), PropertyValue::List(vec!["rust".to_string()])),
+            ]),
+            tags: vec!["rust".to_string(), "web".to_string()],
+            file_size: 120,
+            ..Default::default()
+        };
+        let mut node = Node::new("Project X".to_string());
+        GraphService::apply_node_metadata(&mut node, &metadata);
+
+        assert_eq!(node.metadata["type"], PropertyValue::Text("project".to_string()));
+        assert_eq!(node.metadata["created"], PropertyValue::Text("last spring".to_string()));
+        assert_eq!(node.metadata["file_size"], PropertyValue::Number(120.0));
+        // Tags merge the property with inline tags
+        assert_eq!(node.metadata["tags"], PropertyValue::List(vec!["rust".to_string(), "web".to_string()]));
+        assert!(node.matches_attributes([(&"type".to_string(), &"project".to_string())]));
+    }
+}
@@ -0,0 +1,668 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use schemars::JsonSchema;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error::Error as StdError;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::config::Settings;
+use crate::models::edge::Edge;
+use crate::models::graph::GraphData;
+use crate::models::metadata::PropertyValue;
+use crate::models::node::Node;
+use crate::models::reference::ReferenceKind;
+use crate::utils::reference_parser::ReferenceParser;
+
+const GITHUB_API_DELAY: Duration = Duration::from_millis(100);
+const COMMITS_PER_PAGE: usize = 100;
+
+/// A markdown page as of a commit; `content` is `None` when the commit deleted it.
+#[derive(Debug, Clone)]
+pub struct PageChange {
+    pub path: String,
+    pub content: Option<String>,
+}
+
+/// A commit touching the vault.
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+    pub changes: Vec<PageChange>,
+}
+
+/// Where the vault's commit history comes from.
+#[async_trait]
+pub trait HistorySource: Send + Sync {
+    /// Revisions touching markdown pages in the vault, oldest first
+    async fn revisions(&self) -> Result<Vec<Revision>, Box<dyn StdError + Send + Sync>>;
+}
+
+/// History from the GitHub commits API, one request per commit plus one per changed page.
+pub struct GitHubHistory {
+    client: Client,
+    token: String,
+    owner: String,
+    repo: String,
+    base_path: String,
+    max_commits: usize,
+}
+
+impl GitHubHistory {
+    pub fn new(
+        token: String,
+        owner: String,
+        repo: String,
+        base_path: String,
+        max_commits: usize,
+    ) -> Result<Self, Box<dyn StdError + Send + Sync>> {
+        let client = Client::builder()
+            .user_agent("rust-github-api")
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self { client, token, owner, repo, base_path, max_commits })
+    }
+
+    async fn get_json(&self, url: &str, query: &[(&str, String)]) -> Result<Value, Box<dyn StdError + Send + Sync>> {
+        sleep(GITHUB_API_DELAY).await;
+        let response = self.client.get(url)
+            .header("Authorization", format!("token {}", self.token))
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    async fn fetch_raw(&self, url: &str) -> Result<String, Box<dyn StdError + Send + Sync>> {
+        sleep(GITHUB_API_DELAY).await;
+        let response = self.client.get(url)
+            .header("Authorization", format!("token {}", self.token))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.text().await?)
+    }
+}
+
+#[async_trait]
+impl HistorySource for GitHubHistory {
+    async fn revisions(&self) -> Result<Vec<Revision>, Box<dyn StdError + Send + Sync>> {
+        let commits_url = format!("https://api.github.com/repos/{}/{}/commits", self.owner, self.repo);
+
+        // Newest first, paged
+        let mut shas = Vec::new();
+        let mut page = 1;
+        while shas.len() < self.max_commits {
+            let listing = self.get_json(&commits_url, &[
+                ("path", self.base_path.clone()),
+                ("per_page", COMMITS_PER_PAGE.to_string()),
+                ("page", page.to_string()),
+            ]).await?;
+            let Some(commits) = listing.as_array().filter(|c| !c.is_empty()) else {
+                break;
+            };
+            shas.extend(commits.iter().filter_map(|c| c["sha"].as_str().map(str::to_string)));
+            if commits.len() < COMMITS_PER_PAGE {
+                break;
+            }
+            page += 1;
+        }
+        shas.truncate(self.max_commits);
+        shas.reverse();
+
+        let mut revisions = Vec::new();
+        for sha in shas {
+            let commit = self.get_json(&format!("{}/{}", commits_url, sha), &[]).await?;
+            let timestamp = commit["commit"]["committer"]["date"].as_str()
+                .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+                .map(|date| date.with_timezone(&Utc))
+                .ok_or_else(|| format!("Commit {} has no committer date", sha))?;
+
+            let mut changes = Vec::new();
+            for file in commit["files"].as_array().into_iter().flatten() {
+                let path = file["filename"].as_str().unwrap_or_default();
+                if !in_vault(path, &self.base_path) {
+                    continue;
+                }
+                let status = file["status"].as_str().unwrap_or_default();
+                if status == "renamed" {
+                    if let Some(previous) = file["previous_filename"].as_str().filter(|p| in_vault(p, &self.base_path)) {
+                        changes.push(PageChange { path: previous.to_string(), content: None });
+                    }
+                }
+                let content = match (status, file["raw_url"].as_str()) {
+                    ("removed", _) | (_, None) => None,
+                    (_, Some(raw_url)) => Some(self.fetch_raw(raw_url).await?),
+                };
+                changes.push(PageChange { path: path.to_string(), content });
+            }
+
+            debug!("Commit {} changed {} pages", sha, changes.len());
+            revisions.push(Revision {
+                commit: sha,
+                timestamp,
+                message: first_line(commit["commit"]["message"].as_str().unwrap_or_default()),
+                changes,
+            });
+        }
+        Ok(revisions)
+    }
+}
+
+/// History from a local git checkout through the `git` command line.
+#[derive(Clone)]
+pub struct LocalGitHistory {
+    repo_path: PathBuf,
+    vault_path: String,
+    max_commits: usize,
+}
+
+impl LocalGitHistory {
+    pub fn new(repo_path: impl Into<PathBuf>, vault_path: String, max_commits: usize) -> Self {
+        Self { repo_path: repo_path.into(), vault_path, max_commits }
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, Box<dyn StdError + Send + Sync>> {
+        let output = Command::new("git").arg("-C").arg(&self.repo_path).args(args).output()?;
+        if !output.status.success() {
+            return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn read_revisions(&self) -> Result<Vec<Revision>, Box<dyn StdError + Send + Sync>> {
+        let max_count = format!("--max-count={}", self.max_commits);
+        let log = self.git(&[
+            "log", &max_count, "--no-renames", "--name-status",
+            "--format=%x1e%H%x1f%cI%x1f%s", "--", &self.vault_path,
+        ])?;
+
+        let mut revisions = Vec::new();
+        for record in log.split('\x1e').filter(|r| !r.trim().is_empty()) {
+            let mut lines = record.lines();
+            let header: Vec<&str> = lines.next().unwrap_or_default().split('\x1f').collect();
+            let [sha, date, subject] = header[..] else {
+                return Err(format!("Unexpected git log header: {:?}", header).into());
+            };
+            let timestamp = DateTime::parse_from_rfc3339(date)?.with_timezone(&Utc);
+
+            let mut changes = Vec::new();
+            for line in lines.filter(|l| !l.is_empty()) {
+                let Some((status, path)) = line.split_once('\t') else {
+                    continue;
+                };
+                if !in_vault(path, &self.vault_path) {
+                    continue;
+                }
+                let content = match status {
+                    "D" => None,
+                    _ => Some(self.git(&["show", &format!("{}:{}", sha, path)])?),
+                };
+                changes.push(PageChange { path: path.to_string(), content });
+            }
+            revisions.push(Revision {
+                commit: sha.to_string(),
+                timestamp,
+                message: subject.to_string(),
+                changes,
+            });
+        }
+        revisions.reverse();
+        Ok(revisions)
+    }
+}
+
+#[async_trait]
+impl HistorySource for LocalGitHistory {
+    async fn revisions(&self) -> Result<Vec<Revision>, Box<dyn StdError + Send + Sync>> {
+        let source = self.clone();
+        tokio::task::spawn_blocking(move || source.read_revisions()).await?
+    }
+}
+
+/// Build the history source selected by `[history] source`.
+pub fn history_source_from_settings(settings: &Settings) -> Result<Box<dyn HistorySource>, Box<dyn StdError + Send + Sync>> {
+    let history = &settings.history;
+    Ok(match history.source.as_str() {
+        "local" => Box::new(LocalGitHistory::new(&history.repo_path, history.vault_path.clone(), history.max_commits)),
+        _ => Box::new(GitHubHistory::new(
+            settings.github.github_access_token.clone(),
+            settings.github.github_owner.clone(),
+            settings.github.github_repo.clone(),
+            settings.github.github_directory.clone(),
+            history.max_commits,
+        )?),
+    })
+}
+
+fn in_vault(path: &str, vault: &str) -> bool {
+    let vault = vault.trim_matches('/');
+    path.ends_with(".md") && (vault.is_empty() || path.starts_with(&format!("{}/", vault)))
+}
+
+fn first_line(message: &str) -> String {
+    message.lines().next().unwrap_or_default().to_string()
+}
+
+/// Pages are keyed by file name like the live graph, wherever they sit in the vault
+fn node_id(path: &str) -> String {
+    Path::new(path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// A change to the graph's topology.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum GraphChange {
+    NodeAdded { id: String },
+    NodeRemoved { id: String },
+    EdgeAdded { source: String, target: String },
+    EdgeRemoved { source: String, target: String },
+}
+
+/// The topology changes one commit made.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommitFrame {
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+    pub changes: Vec<GraphChange>,
+}
+
+/// When something existed within a time range; `None` means before the range or still there.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Lifespan {
+    pub appeared: Option<DateTime<Utc>>,
+    pub disappeared: Option<DateTime<Utc>>,
+}
+
+/// Nodes and edges that existed at some point within a time range.
+#[derive(Debug, Default)]
+pub struct TimeRange {
+    pub nodes: BTreeMap<String, Lifespan>,
+    pub edges: BTreeMap<(String, String), Lifespan>,
+}
+
+/// The page graph's topology over time, as a sequence of per-commit changes.
+///
+/// Links are resolved by page name as of each commit. Aliases and unlinked mentions
+/// depend on the whole vault at the time and are not tracked.
+#[derive(Debug, Default)]
+pub struct GraphHistory {
+    /// Commits that changed the topology, oldest first
+    pub frames: Vec<CommitFrame>,
+}
+
+impl GraphHistory {
+    /// Replay `revisions` in order. Pages in `current` that no revision touches
+    /// predate the recorded history and are added before its first commit.
+    pub fn from_revisions(mut revisions: Vec<Revision>, current: &[String]) -> Self {
+        revisions.sort_by_key(|r| r.timestamp);
+        let mut replay = Replay::default();
+        let mut frames = Vec::new();
+
+        let touched: HashSet<String> = revisions.iter()
+            .flat_map(|r| r.changes.iter().map(|c| node_id(&c.path)))
+            .collect();
+        let baseline: Vec<GraphChange> = current.iter()
+            .filter(|id| !touched.contains(*id))
+            .map(|id| {
+                replay.pages.insert(id.to_lowercase(), id.clone());
+                GraphChange::NodeAdded { id: id.clone() }
+            })
+            .collect();
+        if let (false, Some(first)) = (baseline.is_empty(), revisions.first()) {
+            frames.push(CommitFrame {
+                commit: String::new(),
+                timestamp: first.timestamp,
+                message: "Pages unchanged since before the recorded history".to_string(),
+                changes: baseline,
+            });
+        }
+
+        for revision in revisions {
+            let changes = replay.apply(&revision.changes);
+            if !changes.is_empty() {
+                frames.push(CommitFrame {
+                    commit: revision.commit,
+                    timestamp: revision.timestamp,
+                    message: revision.message,
+                    changes,
+                });
+            }
+        }
+        info!("Replayed {} commits changing the graph", frames.len());
+        Self { frames }
+    }
+
+    /// Frames with `from <= timestamp <= to`
+    pub fn frames_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> &[CommitFrame] {
+        let start = self.frames.partition_point(|f| f.timestamp < from);
+        let end = self.frames.partition_point(|f| f.timestamp <= to).max(start);
+        &self.frames[start..end]
+    }
+
+    pub fn first_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.frames.first().map(|f| f.timestamp)
+    }
+
+    pub fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.frames.last().map(|f| f.timestamp)
+    }
+
+    /// Nodes and edges that exist just before `t`, as a playback starting point
+    pub fn state_before(&self, t: DateTime<Utc>) -> (Vec<String>, Vec<(String, String)>) {
+        let (nodes, edges) = self.alive_before(t);
+        (nodes.into_keys().collect(), edges.into_keys().collect())
+    }
+
+    /// Everything that existed at any moment between `from` and `to`
+    pub fn time_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> TimeRange {
+        let mut range = TimeRange::default();
+        let (nodes, edges) = self.alive_before(from);
+        let alive = |appeared: DateTime<Utc>| Lifespan { appeared: Some(appeared), disappeared: None };
+        range.nodes.extend(nodes.into_iter().map(|(id, t)| (id, alive(t))));
+        range.edges.extend(edges.into_iter().map(|(key, t)| (key, alive(t))));
+
+        for frame in self.frames_between(from, to) {
+            let t = frame.timestamp;
+            for change in &frame.changes {
+                match change {
+                    GraphChange::NodeAdded { id } => { range.nodes.insert(id.clone(), alive(t)); },
+                    GraphChange::NodeRemoved { id } => {
+                        if let Some(span) = range.nodes.get_mut(id) {
+                            span.disappeared = Some(t);
+                        }
+                    },
+                    GraphChange::EdgeAdded { source, target } => { range.edges.insert((source.clone(), target.clone()), alive(t)); },
+                    GraphChange::EdgeRemoved { source, target } => {
+                        if let Some(span) = range.edges.get_mut(&(source.clone(), target.clone())) {
+                            span.disappeared = Some(t);
+                        }
+                    },
+                }
+            }
+        }
+        range
+    }
+
+    /// Nodes and edges alive just before `t`, with when they last appeared
+    #[allow(clippy::type_complexity)]
+    fn alive_before(&self, t: DateTime<Utc>) -> (BTreeMap<String, DateTime<Utc>>, BTreeMap<(String, String), DateTime<Utc>>) {
+        let mut nodes = BTreeMap::new();
+        let mut edges = BTreeMap::new();
+        let end = self.frames.partition_point(|f| f.timestamp < t);
+        for frame in &self.frames[..end] {
+            for change in &frame.changes {
+                match change {
+                    GraphChange::NodeAdded { id } => { nodes.insert(id.clone(), frame.timestamp); },
+                    GraphChange::NodeRemoved { id } => { nodes.remove(id); },
+                    GraphChange::EdgeAdded { source, target } => { edges.insert((source.clone(), target.clone()), frame.timestamp); },
+                    GraphChange::EdgeRemoved { source, target } => { edges.remove(&(source.clone(), target.clone())); },
+                }
+            }
+        }
+        (nodes, edges)
+    }
+}
+
+impl TimeRange {
+    /// The range as a graph. Nodes and edges still in `current` keep their data;
+    /// every node and edge is annotated with when it appeared and disappeared.
+    pub fn to_graph(&self, current: &GraphData) -> GraphData {
+        let current_nodes: HashMap<&str, &Node> = current.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let mut current_edges: HashMap<(&str, &str), Vec<&Edge>> = HashMap::new();
+        for edge in &current.edges {
+            current_edges.entry((edge.source.as_str(), edge.target_node.as_str())).or_default().push(edge);
+        }
+
+        let nodes = self.nodes.iter()
+            .map(|(id, span)| {
+                let mut node = current_nodes.get(id.as_str()).map_or_else(|| Node::new(id.clone()), |n| (*n).clone());
+                if let Some(appeared) = span.appeared {
+                    node.metadata.insert("appeared".to_string(), PropertyValue::Date(appeared));
+                }
+                if let Some(disappeared) = span.disappeared {
+                    node.metadata.insert("disappeared".to_string(), PropertyValue::Date(disappeared));
+                }
+                node
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        for ((source, target), span) in &self.edges {
+            let existing = current_edges.get(&(source.as_str(), target.as_str()));
+            let base: Vec<Edge> = match existing {
+                Some(list) => list.iter().map(|e| (*e).clone()).collect(),
+                None => vec![Edge::new(source.clone(), target.clone(), 1.0)],
+            };
+            for mut edge in base {
+                if let Some(appeared) = span.appeared {
+                    edge.attributes.insert("appeared".to_string(), appeared.to_rfc3339());
+                }
+                if let Some(disappeared) = span.disappeared {
+                    edge.attributes.insert("disappeared".to_string(), disappeared.to_rfc3339());
+                }
+                edges.push(edge);
+            }
+        }
+
+        GraphData { nodes, edges, metadata: HashMap::new() }
+    }
+}
+
+/// Topology state while replaying revisions.
+struct Replay {
+    /// Lowercased page name -> page id
+    pages: HashMap<String, String>,
+    /// Page id -> lowercased names it references
+    links: HashMap<String, HashSet<String>>,
+    /// Lowercased name -> pages referencing it
+    referrers: HashMap<String, HashSet<String>>,
+    edges: HashSet<(String, String)>,
+    parser: ReferenceParser,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            pages: HashMap::new(),
+            links: HashMap::new(),
+            referrers: HashMap::new(),
+            edges: HashSet::new(),
+            parser: ReferenceParser::new(&[]),
+        }
+    }
+}
+
+impl Replay {
+    /// Apply one commit's page changes. Nodes are added before and removed after
+    /// the edge changes, so every change refers to nodes that exist at that point.
+    fn apply(&mut self, changes: &[PageChange]) -> Vec<GraphChange> {
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        let mut candidates: BTreeSet<(String, String)> = BTreeSet::new();
+
+        for change in changes {
+            let id = node_id(&change.path);
+            let key = id.to_lowercase();
+
+            for name in self.links.remove(&id).unwrap_or_default() {
+                if let Some(referrers) = self.referrers.get_mut(&name) {
+                    referrers.remove(&id);
+                }
+                if let Some(target) = self.pages.get(&name) {
+                    candidates.insert((id.clone(), target.clone()));
+                }
+            }
+
+            match &change.content {
+                Some(content) => {
+                    if self.pages.insert(key.clone(), id.clone()).is_none() {
+                        // A page moved between folders keeps its node
+                        let node = GraphChange::NodeRemoved { id: id.clone() };
+                        match removed.contains(&node) {
+                            true => removed.retain(|r| r != &node),
+                            false => added.push(GraphChange::NodeAdded { id: id.clone() }),
+                        }
+                    }
+                    let names = self.link_names(content);
+                    for name in &names {
+                        self.referrers.entry(name.clone()).or_default().insert(id.clone());
+                    }
+                    self.links.insert(id.clone(), names);
+                },
+                None => {
+                    if self.pages.remove(&key).is_some() {
+                        let node = GraphChange::NodeAdded { id: id.clone() };
+                        match added.contains(&node) {
+                            true => added.retain(|a| a != &node),
+                            false => removed.push(GraphChange::NodeRemoved { id: id.clone() }),
+                        }
+                    }
+                },
+            }
+
+            for source in self.referrers.get(&key).into_iter().flatten() {
+                candidates.insert((source.clone(), id.clone()));
+            }
+            for name in self.links.get(&id).into_iter().flatten() {
+                if let Some(target) = self.pages.get(name) {
+                    candidates.insert((id.clone(), target.clone()));
+                }
+            }
+        }
+
+        let mut result = added;
+        for (source, target) in candidates {
+            let exists = self.edge_exists(&source, &target);
+            let key = (source, target);
+            match (self.edges.contains(&key), exists) {
+                (false, true) => {
+                    result.push(GraphChange::EdgeAdded { source: key.0.clone(), target: key.1.clone() });
+                    self.edges.insert(key);
+                },
+                (true, false) => {
+                    self.edges.remove(&key);
+                    result.push(GraphChange::EdgeRemoved { source: key.0, target: key.1 });
+                },
+                _ => {},
+            }
+        }
+        result.extend(removed);
+        result
+    }
+
+    fn edge_exists(&self, source: &str, target: &str) -> bool {
+        let target_key = target.to_lowercase();
+        source != target
+            && self.pages.get(&source.to_lowercase()).is_some_and(|id| id == source)
+            && self.pages.get(&target_key).is_some_and(|id| id == target)
+            && self.links.get(source).is_some_and(|names| names.contains(&target_key))
+    }
+
+    /// Names of pages a page references, lowercased; namespaced names by their last segment
+    fn link_names(&self, content: &str) -> HashSet<String> {
+        self.parser.parse(content).into_iter()
+            .filter(|r| matches!(r.kind, ReferenceKind::Link | ReferenceKind::Tag | ReferenceKind::Embed))
+            .map(|r| r.target.rsplit('/').next().unwrap_or_default().trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap()
+    }
+
+    fn revision(d: u32, changes: &[(&str, Option<&str>)]) -> Revision {
+        Revision {
+            commit: format!("c{}", d),
+            timestamp: day(d),
+            message: String::new(),
+            changes: changes.iter()
+                .map(|(path, content)| PageChange {
+                    path: format!("pages/{}.md", path),
+                    content: content.map(str::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    fn history() -> GraphHistory {
+        GraphHistory::from_revisions(vec![
+            revision(1, &[("A", Some("See [[B]] and [[C]]")), ("B", Some("Plain"))]),
+            revision(2, &[("C", Some("Back to [[a]]"))]),
+            revision(3, &[("A", Some("Only [[C]] now"))]),
+            revision(4, &[("C", None)]),
+        ], &[])
+    }
+
+    fn edge(kind: &str, source: &str, target: &str) -> GraphChange {
+        let (source, target) = (source.to_string(), target.to_string());
+        match kind {
+            "+" => GraphChange::EdgeAdded { source, target },
+            _ => GraphChange::EdgeRemoved { source, target },
+        }
+    }
+
+    #[test]
+    fn test_replay_tracks_nodes_and_edges() {
+        let history = history();
+        let changes: Vec<&[GraphChange]> = history.frames.iter().map(|f| f.changes.as_slice()).collect();
+
+        assert_eq!(changes[0], &[
+            GraphChange::NodeAdded { id: "A".to_string() },
+            GraphChange::NodeAdded { id: "B".to_string() },
+            edge("+", "A", "B"),
+        ]);
+        // A's dangling link to C resolves once C exists; links match case-insensitively
+        assert_eq!(changes[1], &[
+            GraphChange::NodeAdded { id: "C".to_string() },
+            edge("+", "A", "C"),
+            edge("+", "C", "A"),
+        ]);
+        assert_eq!(changes[2], &[edge("-", "A", "B")]);
+        assert_eq!(changes[3], &[
+            edge("-", "A", "C"),
+            edge("-", "C", "A"),
+            GraphChange::NodeRemoved { id: "C".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_time_range_reports_lifespans() {
+        let history = history();
+        let range = history.time_range(day(2), day(3));
+
+        assert_eq!(range.nodes.keys().collect::<Vec<_>>(), vec!["A", "B", "C"]);
+        assert_eq!(range.nodes["C"], Lifespan { appeared: Some(day(2)), disappeared: None });
+        assert_eq!(range.edges[&("A".to_string(), "B".to_string())], Lifespan { appeared: Some(day(1)), disappeared: Some(day(3)) });
+
+        let graph = range.to_graph(&GraphData::new());
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!(graph.nodes[2].metadata["appeared"], PropertyValue::Date(day(2)));
+        assert_eq!(history.frames_between(day(2), day(3)).len(), 2);
+    }
+
+    #[test]
+    fn test_untouched_pages_predate_history() {
+        let history = GraphHistory::from_revisions(vec![revision(5, &[("A", Some("[[Old]]"))])], &["Old".to_string(), "A".to_string()]);
+        assert_eq!(history.frames[0].changes, vec![GraphChange::NodeAdded { id: "Old".to_string() }]);
+        assert!(history.frames[1].changes.contains(&edge("+", "A", "Old")));
+    }
+}
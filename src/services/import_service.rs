@@ -0,0 +1,567 @@
+use async_trait::async_trait;
+use roxmltree::{Document, Node as XmlNode};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::error::Error as StdError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::ImportSettings;
+use crate::models::edge::{Edge, EdgeKind};
+use crate::models::graph::GraphData;
+use crate::models::metadata::PropertyValue;
+use crate::models::node::Node;
+use crate::utils::page_properties::typed_value;
+
+/// Somewhere a graph can be loaded from, as an alternative to the markdown vault.
+#[async_trait]
+pub trait GraphSource: Send + Sync {
+    /// Shown in logs and the import endpoint
+    fn name(&self) -> String;
+
+    async fn load(&self) -> Result<GraphData, Box<dyn StdError + Send + Sync>>;
+}
+
+/// A graph file on disk, re-read on every rebuild.
+pub struct FileGraphSource {
+    path: PathBuf,
+    format: Option<ImportFormat>,
+    mapping: AttributeMapping,
+}
+
+impl FileGraphSource {
+    pub fn new(path: impl Into<PathBuf>, format: Option<ImportFormat>, mapping: AttributeMapping) -> Self {
+        Self { path: path.into(), format, mapping }
+    }
+}
+
+#[async_trait]
+impl GraphSource for FileGraphSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    async fn load(&self) -> Result<GraphData, Box<dyn StdError + Send + Sync>> {
+        let text = tokio::fs::read_to_string(&self.path).await?;
+        let format = self.format
+            .or_else(|| ImportFormat::from_path(&self.path))
+            .unwrap_or_else(|| ImportFormat::detect(&text));
+        import(&text, format, &self.mapping)
+    }
+}
+
+/// A graph that was uploaded and parsed once.
+pub struct UploadedGraphSource {
+    name: String,
+    graph: GraphData,
+}
+
+impl UploadedGraphSource {
+    pub fn new(name: String, graph: GraphData) -> Self {
+        Self { name, graph }
+    }
+}
+
+#[async_trait]
+impl GraphSource for UploadedGraphSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn load(&self) -> Result<GraphData, Box<dyn StdError + Send + Sync>> {
+        Ok(self.graph.clone())
+    }
+}
+
+/// The graph source configured in `[import]`, if any.
+pub fn graph_source_from_settings(settings: &ImportSettings) -> Result<Option<Arc<dyn GraphSource>>, Box<dyn StdError + Send + Sync>> {
+    if settings.path.is_empty() {
+        return Ok(None);
+    }
+    let format = match settings.format.as_str() {
+        "" => None,
+        name => Some(ImportFormat::parse(name).ok_or_else(|| format!("Unknown import format: {}", name))?),
+    };
+    Ok(Some(Arc::new(FileGraphSource::new(&settings.path, format, AttributeMapping::from(settings)))))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    GraphMl,
+    Gexf,
+    CsvEdges,
+    JsonGraph,
+}
+
+impl ImportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "graphml" => Some(Self::GraphMl),
+            "gexf" => Some(Self::Gexf),
+            "csv" | "csv-edges" => Some(Self::CsvEdges),
+            "jgf" | "json-graph" | "json" => Some(Self::JsonGraph),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::parse(path.extension()?.to_str()?)
+    }
+
+    /// Guess the format from the content itself
+    pub fn detect(text: &str) -> Self {
+        let start = text.trim_start();
+        if start.starts_with('<') {
+            match start.contains("<gexf") {
+                true => Self::Gexf,
+                false => Self::GraphMl,
+            }
+        } else if start.starts_with('{') {
+            Self::JsonGraph
+        } else {
+            Self::CsvEdges
+        }
+    }
+}
+
+/// Which imported attributes become the node label and the edge weight. Attributes
+/// named `x`, `y` and `z` become positions and `kind` the edge kind when it names one;
+/// everything else is kept as node metadata or edge attributes.
+#[derive(Debug, Clone)]
+pub struct AttributeMapping {
+    pub label: String,
+    pub weight: String,
+}
+
+impl From<&ImportSettings> for AttributeMapping {
+    fn from(settings: &ImportSettings) -> Self {
+        Self {
+            label: settings.label_attribute.clone(),
+            weight: settings.weight_attribute.clone(),
+        }
+    }
+}
+
+impl Default for AttributeMapping {
+    fn default() -> Self {
+        Self::from(&ImportSettings::default())
+    }
+}
+
+/// Parse a graph file into `GraphData`.
+pub fn import(text: &str, format: ImportFormat, mapping: &AttributeMapping) -> Result<GraphData, Box<dyn StdError + Send + Sync>> {
+    let mut builder = GraphBuilder::new(mapping);
+    match format {
+        ImportFormat::GraphMl => builder.read_graphml(text)?,
+        ImportFormat::Gexf => builder.read_gexf(text)?,
+        ImportFormat::CsvEdges => builder.read_csv(text)?,
+        ImportFormat::JsonGraph => builder.read_json_graph(text)?,
+    }
+    Ok(builder.finish())
+}
+
+/// Collects nodes and edges, creating nodes for edge endpoints that were never declared.
+struct GraphBuilder<'a> {
+    mapping: &'a AttributeMapping,
+    nodes: Vec<Node>,
+    node_ids: HashSet<String>,
+    edges: Vec<Edge>,
+}
+
+impl<'a> GraphBuilder<'a> {
+    fn new(mapping: &'a AttributeMapping) -> Self {
+        Self {
+            mapping,
+            nodes: Vec::new(),
+            node_ids: HashSet::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    fn add_node(&mut self, id: &str, attributes: Vec<(String, PropertyValue)>) {
+        if !self.node_ids.insert(id.to_string()) {
+            return;
+        }
+        let mut node = Node::new(id.to_string());
+        for (name, value) in attributes {
+            match (name.as_str(), &value) {
+                (name, _) if name == self.mapping.label => node.label = value_string(&value),
+                ("x", PropertyValue::Number(n)) => node.x = *n as f32,
+                ("y", PropertyValue::Number(n)) => node.y = *n as f32,
+                ("z", PropertyValue::Number(n)) => node.z = *n as f32,
+                _ => { node.metadata.insert(name, value); },
+            }
+        }
+        self.nodes.push(node);
+    }
+
+    fn add_edge(&mut self, source: &str, target: &str, directed: bool, attributes: Vec<(String, String)>) {
+        for id in [source, target] {
+            if !self.node_ids.contains(id) {
+                self.add_node(id, Vec::new());
+            }
+        }
+        let mut edge = Edge::new(source.to_string(), target.to_string(), 1.0).with_directed(directed);
+        for (name, value) in attributes {
+            if name == self.mapping.weight {
+                if let Ok(weight) = value.trim().parse::<f32>() {
+                    edge.weight = weight;
+                    continue;
+                }
+            }
+            if name == "kind" {
+                if let Ok(kind) = serde_json::from_value::<EdgeKind>(Value::String(value.clone())) {
+                    edge.kind = kind;
+                    continue;
+                }
+            }
+            edge.attributes.insert(name, value);
+        }
+        self.edges.push(edge);
+    }
+
+    fn finish(self) -> GraphData {
+        GraphData { nodes: self.nodes, edges: self.edges, metadata: HashMap::new() }
+    }
+
+    fn read_graphml(&mut self, text: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let document = Document::parse(text)?;
+        let root = document.root_element();
+
+        // Key id -> (attribute name, declared type)
+        let keys: HashMap<&str, (&str, Option<&str>)> = children(root, "key")
+            .filter_map(|key| Some((key.attribute("id")?, (key.attribute("attr.name").unwrap_or(key.attribute("id")?), key.attribute("attr.type")))))
+            .collect();
+        let data = |element: XmlNode| -> Vec<(String, String, Option<&str>)> {
+            children(element, "data")
+                .filter_map(|d| {
+                    let key = d.attribute("key")?;
+                    let (name, declared) = keys.get(key).copied().unwrap_or((key, None));
+                    Some((name.to_string(), d.text().unwrap_or_default().trim().to_string(), declared))
+                })
+                .collect()
+        };
+
+        let graph = children(root, "graph").next().ok_or("GraphML file has no <graph>")?;
+        let directed_default = graph.attribute("edgedefault") != Some("undirected");
+        for node in children(graph, "node") {
+            let id = node.attribute("id").ok_or("GraphML node without an id")?;
+            let attributes = data(node).into_iter().map(|(name, value, declared)| (name, typed(&value, declared))).collect();
+            self.add_node(id, attributes);
+        }
+        for edge in children(graph, "edge") {
+            let (Some(source), Some(target)) = (edge.attribute("source"), edge.attribute("target")) else {
+                return Err("GraphML edge without source or target".into());
+            };
+            let directed = edge.attribute("directed").map_or(directed_default, |d| d == "true");
+            let attributes = data(edge).into_iter().map(|(name, value, _)| (name, value)).collect();
+            self.add_edge(source, target, directed, attributes);
+        }
+        Ok(())
+    }
+
+    fn read_gexf(&mut self, text: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let document = Document::parse(text)?;
+        let graph = children(document.root_element(), "graph").next().ok_or("GEXF file has no <graph>")?;
+        let directed_default = graph.attribute("defaultedgetype") != Some("undirected");
+
+        // (class, attribute id) -> (title, declared type)
+        let mut declared: HashMap<(&str, &str), (&str, Option<&str>)> = HashMap::new();
+        for attributes in children(graph, "attributes") {
+            let class = attributes.attribute("class").unwrap_or("node");
+            for attribute in children(attributes, "attribute") {
+                if let Some(id) = attribute.attribute("id") {
+                    declared.insert((class, id), (attribute.attribute("title").unwrap_or(id), attribute.attribute("type")));
+                }
+            }
+        }
+        let attvalues = |element: XmlNode, class: &str| -> Vec<(String, String, Option<&str>)> {
+            children(element, "attvalues")
+                .flat_map(|values| children(values, "attvalue"))
+                .filter_map(|value| {
+                    let key = value.attribute("for").or_else(|| value.attribute("id"))?;
+                    let (name, kind) = declared.get(&(class, key)).copied().unwrap_or((key, None));
+                    Some((name.to_string(), value.attribute("value")?.to_string(), kind))
+                })
+                .collect()
+        };
+
+        for node in children(graph, "nodes").flat_map(|nodes| children(nodes, "node")) {
+            let id = node.attribute("id").ok_or("GEXF node without an id")?;
+            let mut attributes: Vec<(String, PropertyValue)> = attvalues(node, "node").into_iter()
+                .map(|(name, value, kind)| (name, typed(&value, kind)))
+                .collect();
+            if let Some(label) = node.attribute("label") {
+                attributes.insert(0, (self.mapping.label.clone(), PropertyValue::Text(label.to_string())));
+            }
+            if let Some(position) = children(node, "position").next() {
+                for axis in ["x", "y", "z"] {
+                    if let Some(n) = position.attribute(axis).and_then(|v| v.parse::<f64>().ok()) {
+                        attributes.push((axis.to_string(), PropertyValue::Number(n)));
+                    }
+                }
+            }
+            self.add_node(id, attributes);
+        }
+
+        for edge in children(graph, "edges").flat_map(|edges| children(edges, "edge")) {
+            let (Some(source), Some(target)) = (edge.attribute("source"), edge.attribute("target")) else {
+                return Err("GEXF edge without source or target".into());
+            };
+            let directed = edge.attribute("type").map_or(directed_default, |t| t == "directed");
+            let mut attributes: Vec<(String, String)> = attvalues(edge, "edge").into_iter()
+                .map(|(name, value, _)| (name, value))
+                .collect();
+            if let Some(weight) = edge.attribute("weight") {
+                attributes.push((self.mapping.weight.clone(), weight.to_string()));
+            }
+            if let Some(label) = edge.attribute("label") {
+                let name = match serde_json::from_value::<EdgeKind>(Value::String(label.to_string())) {
+                    Ok(_) => "kind",
+                    Err(_) => "label",
+                };
+                attributes.push((name.to_string(), label.to_string()));
+            }
+            self.add_edge(source, target, directed, attributes);
+        }
+        Ok(())
+    }
+
+    /// An edge list with `source` and `target` columns; without a header row the first
+    /// two columns are the endpoints and a third the weight.
+    fn read_csv(&mut self, text: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let mut rows = parse_csv(text).into_iter().filter(|row| row.iter().any(|f| !f.trim().is_empty()));
+        let Some(first) = rows.next() else {
+            return Ok(());
+        };
+
+        let lowered: Vec<String> = first.iter().map(|h| h.trim().to_lowercase()).collect();
+        let position = |name: &str| lowered.iter().position(|h| h == name);
+        let (header, pending) = match (position("source"), position("target")) {
+            (Some(_), Some(_)) => (first.iter().map(|h| h.trim().to_string()).collect::<Vec<_>>(), None),
+            _ => {
+                let mut header = vec!["source".to_string(), "target".to_string(), self.mapping.weight.clone()];
+                header.extend((3..first.len()).map(|i| format!("column{}", i + 1)));
+                (header, Some(first))
+            },
+        };
+        let lowered_header: Vec<String> = header.iter().map(|h| h.to_lowercase()).collect();
+        let source_column = lowered_header.iter().position(|h| h == "source").unwrap_or(0);
+        let target_column = lowered_header.iter().position(|h| h == "target").unwrap_or(1);
+        let directed_column = lowered_header.iter().position(|h| h == "directed");
+
+        for (line, row) in pending.into_iter().chain(rows).enumerate() {
+            let (Some(source), Some(target)) = (row.get(source_column), row.get(target_column)) else {
+                return Err(format!("CSV row {} has no source and target", line + 1).into());
+            };
+            let directed = directed_column
+                .and_then(|c| row.get(c))
+                .is_none_or(|d| d.trim() != "false");
+            let attributes = header.iter()
+                .zip(&row)
+                .enumerate()
+                .filter(|(i, _)| *i != source_column && *i != target_column && Some(*i) != directed_column)
+                .map(|(_, (name, value))| (name.clone(), value.clone()))
+                .collect();
+            self.add_edge(source.trim(), target.trim(), directed, attributes);
+        }
+        Ok(())
+    }
+
+    /// JSON Graph Format, version 2 (nodes keyed by id) or 1 (nodes as a list),
+    /// as a single `graph` or the first of `graphs`.
+    fn read_json_graph(&mut self, text: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let document: Value = serde_json::from_str(text)?;
+        let graph = document.get("graph")
+            .or_else(|| document.get("graphs").and_then(|g| g.get(0)))
+            .ok_or("JSON Graph file has no graph")?;
+        let directed_default = graph.get("directed").and_then(Value::as_bool).unwrap_or(true);
+
+        let nodes: Vec<(String, &Value)> = match graph.get("nodes") {
+            Some(Value::Object(nodes)) => nodes.iter().map(|(id, node)| (id.clone(), node)).collect(),
+            Some(Value::Array(nodes)) => nodes.iter()
+                .filter_map(|node| Some((json_text(node.get("id")?), node)))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for (id, node) in nodes {
+            let mut attributes: Vec<(String, PropertyValue)> = Vec::new();
+            if let Some(label) = node.get("label").and_then(Value::as_str) {
+                attributes.push((self.mapping.label.clone(), PropertyValue::Text(label.to_string())));
+            }
+            for (name, value) in node.get("metadata").and_then(Value::as_object).into_iter().flatten() {
+                if let Some(value) = json_property(value) {
+                    attributes.push((name.clone(), value));
+                }
+            }
+            self.add_node(&id, attributes);
+        }
+
+        for edge in graph.get("edges").and_then(Value::as_array).into_iter().flatten() {
+            let (Some(source), Some(target)) = (edge.get("source"), edge.get("target")) else {
+                return Err("JSON Graph edge without source or target".into());
+            };
+            let directed = edge.get("directed").and_then(Value::as_bool).unwrap_or(directed_default);
+            let mut attributes: Vec<(String, String)> = edge.get("metadata").and_then(Value::as_object).into_iter()
+                .flatten()
+                .map(|(name, value)| (name.clone(), json_text(value)))
+                .collect();
+            if let Some(relation) = edge.get("relation").and_then(Value::as_str) {
+                attributes.push(("kind".to_string(), relation.to_string()));
+            }
+            self.add_edge(&json_text(source), &json_text(target), directed, attributes);
+        }
+        Ok(())
+    }
+}
+
+fn children<'a, 'input>(element: XmlNode<'a, 'input>, name: &'static str) -> impl Iterator<Item = XmlNode<'a, 'input>> {
+    element.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+/// Convert a value using its declared GraphML/GEXF type, inferring the type when undeclared
+fn typed(value: &str, declared: Option<&str>) -> PropertyValue {
+    match declared {
+        Some("int" | "integer" | "long" | "float" | "double") => value.trim().parse::<f64>()
+            .map_or_else(|_| PropertyValue::Text(value.to_string()), PropertyValue::Number),
+        Some("boolean") => value.trim().parse::<bool>()
+            .map_or_else(|_| PropertyValue::Text(value.to_string()), PropertyValue::Bool),
+        Some("string") => PropertyValue::Text(value.to_string()),
+        _ => typed_value(value),
+    }
+}
+
+fn value_string(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Text(s) => s.clone(),
+        PropertyValue::Number(n) => n.to_string(),
+        PropertyValue::Bool(b) => b.to_string(),
+        PropertyValue::Date(d) => d.to_rfc3339(),
+        PropertyValue::List(items) => items.join(", "),
+    }
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn json_property(value: &Value) -> Option<PropertyValue> {
+    match value {
+        Value::Bool(b) => Some(PropertyValue::Bool(*b)),
+        Value::Number(n) => n.as_f64().map(PropertyValue::Number),
+        Value::String(s) => Some(typed_value(s)),
+        Value::Array(items) => Some(PropertyValue::List(items.iter().map(json_text).collect())),
+        Value::Null | Value::Object(_) => None,
+    }
+}
+
+/// RFC 4180 records: quoted fields may contain commas, doubled quotes and newlines
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            ('\r', false) => {},
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::export_service::{self, ExportFormat};
+
+    fn sample() -> GraphData {
+        let mut a = Node::new("a".to_string());
+        a.label = "Alpha, \"first\"".to_string();
+        [a.x, a.y, a.z] = [1.0, 2.0, 3.0];
+        a.metadata.insert("pagerank".to_string(), PropertyValue::Number(0.5));
+        a.metadata.insert("team".to_string(), PropertyValue::Text("core".to_string()));
+        let b = Node::new("b".to_string());
+
+        GraphData {
+            nodes: vec![a, b],
+            edges: vec![
+                Edge::new("a".to_string(), "b".to_string(), 2.5).with_attribute("count", 3),
+                Edge::new("b".to_string(), "a".to_string(), 1.0)
+                    .with_kind(EdgeKind::Tag)
+                    .with_directed(false),
+            ],
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_round_trips_exported_formats() {
+        let mapping = AttributeMapping::default();
+        for (export_format, import_format) in [
+            (ExportFormat::GraphMl, ImportFormat::GraphMl),
+            (ExportFormat::Gexf, ImportFormat::Gexf),
+            (ExportFormat::JsonGraph, ImportFormat::JsonGraph),
+        ] {
+            let text = export_service::export(&sample(), export_format);
+            assert_eq!(ImportFormat::detect(&text), import_format);
+            let graph = import(&text, import_format, &mapping).unwrap();
+
+            let a = &graph.nodes[0];
+            assert_eq!((a.id.as_str(), a.label.as_str()), ("a", "Alpha, \"first\""), "{:?}", import_format);
+            assert_eq!([a.x, a.y, a.z], [1.0, 2.0, 3.0], "{:?}", import_format);
+            assert_eq!(a.metadata["pagerank"], PropertyValue::Number(0.5), "{:?}", import_format);
+            assert_eq!(a.metadata["team"], PropertyValue::Text("core".to_string()), "{:?}", import_format);
+
+            assert_eq!(graph.edges.len(), 2);
+            assert_eq!((graph.edges[0].weight, graph.edges[0].attributes["count"].as_str()), (2.5, "3"), "{:?}", import_format);
+            assert_eq!((graph.edges[1].kind, graph.edges[1].directed), (EdgeKind::Tag, false), "{:?}", import_format);
+        }
+    }
+
+    #[test]
+    fn test_csv_edge_lists() {
+        let mapping = AttributeMapping::default();
+        let exported = export_service::export(&sample(), ExportFormat::CsvEdges);
+        let graph = import(&exported, ImportFormat::CsvEdges, &mapping).unwrap();
+        assert_eq!(graph.nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!((graph.edges[1].kind, graph.edges[1].directed), (EdgeKind::Tag, false));
+
+        // No header: source, target, weight
+        let graph = import("\"x, inc\",y,4\ny,z\n", ImportFormat::CsvEdges, &mapping).unwrap();
+        assert_eq!(graph.nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec!["x, inc", "y", "z"]);
+        assert_eq!((graph.edges[0].weight, graph.edges[1].weight), (4.0, 1.0));
+    }
+
+    #[test]
+    fn test_attribute_mapping() {
+        let mapping = AttributeMapping { label: "name".to_string(), weight: "strength".to_string() };
+        let text = r#"{"graph": {"nodes": [{"id": 1, "metadata": {"name": "Ada", "reports": ["b"]}}],
+            "edges": [{"source": 1, "target": 2, "metadata": {"strength": 0.3}}]}}"#;
+        let graph = import(text, ImportFormat::JsonGraph, &mapping).unwrap();
+        assert_eq!(graph.nodes[0].label, "Ada");
+        assert_eq!(graph.nodes[0].metadata["reports"], PropertyValue::List(vec!["b".to_string()]));
+        assert_eq!(graph.nodes[1].id, "2");
+        assert_eq!(graph.edges[0].weight, 0.3);
+    }
+}
@@ -0,0 +1,174 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use crate::models::metadata::Metadata;
+
+/// Version written by this build. Bump it and append to `MIGRATIONS` when the format changes.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// `MIGRATIONS[n]` upgrades a document from schema version `n + 1` to `n + 2`.
+const MIGRATIONS: &[Migration] = &[
+    migrate_v1_to_v2,
+];
+
+#[derive(Serialize, Deserialize)]
+struct MetadataDocument {
+    schema_version: u32,
+    files: HashMap<String, Metadata>,
+}
+
+/// Read metadata, upgrading older formats. A missing or empty file is an empty map;
+/// anything unreadable is an error so a later save can't silently wipe it.
+pub fn load(path: &Path) -> Result<HashMap<String, Metadata>, Box<dyn StdError + Send + Sync>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    if content.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let value: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("{} is not valid JSON: {}", path.display(), e))?;
+    let document: MetadataDocument = serde_json::from_value(migrate(value)?)?;
+    Ok(document.files)
+}
+
+/// Write metadata to a temporary file next to `path` and rename it into place,
+/// so readers only ever see the old or the new document.
+pub fn save(path: &Path, files: &HashMap<String, Metadata>) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let document = json!({
+        "schema_version": CURRENT_SCHEMA_VERSION,
+        "files": files,
+    });
+
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(serde_json::to_string_pretty(&document)?.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Run every migration between the document's version and the current one.
+pub fn migrate(mut value: Value) -> Result<Value, Box<dyn StdError + Send + Sync>> {
+    let mut version = schema_version(&value);
+    if version < 1 {
+        return Err(format!("metadata schema version {} is not a valid version", version).into());
+    }
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(format!(
+            "metadata schema version {} is newer than supported version {}",
+            version, CURRENT_SCHEMA_VERSION
+        ).into());
+    }
+
+    while version < CURRENT_SCHEMA_VERSION {
+        info!("Migrating metadata from schema version {} to {}", version, version + 1);
+        value = MIGRATIONS[version as usize - 1](value)?;
+        version += 1;
+    }
+    Ok(value)
+}
+
+/// Documents without a `schema_version` are the original bare `file name -> metadata` map.
+fn schema_version(value: &Value) -> u32 {
+    value.get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |v| u32::try_from(v).unwrap_or(u32::MAX))
+}
+
+/// v1 was a bare map and predates several required fields.
+fn migrate_v1_to_v2(value: Value) -> Result<Value, String> {
+    let Value::Object(entries) = value else {
+        return Err("expected a JSON object of file metadata".to_string());
+    };
+
+    let mut files = Map::new();
+    for (file_name, mut entry) in entries {
+        let Some(fields) = entry.as_object_mut() else {
+            return Err(format!("metadata for {} is not an object", file_name));
+        };
+        fields.entry("file_name").or_insert_with(|| json!(file_name));
+        fields.entry("file_size").or_insert(json!(0));
+        fields.entry("node_size").or_insert(json!(0.0));
+        fields.entry("hyperlink_count").or_insert(json!(0));
+        fields.entry("sha1").or_insert(json!(""));
+        fields.entry("perplexity_link").or_insert(json!(""));
+        fields.entry("last_perplexity_process").or_insert(Value::Null);
+        fields.entry("last_modified").or_insert(json!("1970-01-01T00:00:00Z"));
+        fields.entry("topic_counts").or_insert(json!({}));
+        files.insert(file_name, entry);
+    }
+
+    Ok(json!({
+        "schema_version": 2,
+        "files": files,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrates_bare_v1_map() {
+        let v1 = json!({
+            "Rust.md": {
+                "file_size": 120,
+                "sha1": "abc",
+                "last_modified": "2024-01-31T00:00:00Z",
+                "topic_counts": { "WebXR": 2 }
+            }
+        });
+
+        let migrated = migrate(v1).unwrap();
+        assert_eq!(migrated["schema_version"], CURRENT_SCHEMA_VERSION);
+
+        let document: MetadataDocument = serde_json::from_value(migrated).unwrap();
+        let rust = &document.files["Rust.md"];
+        assert_eq!(rust.file_name, "Rust.md");
+        assert_eq!(rust.file_size, 120);
+        assert_eq!(rust.topic_counts["WebXR"], 2);
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let doc = json!({ "schema_version": CURRENT_SCHEMA_VERSION + 1, "files": {} });
+        assert!(migrate(doc).is_err());
+    }
+
+    #[test]
+    fn test_rejects_schema_version_zero() {
+        let doc = json!({ "schema_version": 0, "files": {} });
+        assert!(migrate(doc).is_err());
+    }
+
+    #[test]
+    fn test_save_then_load_round_trips() {
+        let dir = std::env::temp_dir().join(format!("metadata-file-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("metadata.json");
+
+        let files = HashMap::from([("a.md".to_string(), Metadata {
+            file_name: "a.md".to_string(),
+            ..Default::default()
+        })]);
+        save(&path, &files).unwrap();
+
+        assert!(!path.with_extension("json.tmp").exists());
+        assert_eq!(load(&path).unwrap()["a.md"].file_name, "a.md");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
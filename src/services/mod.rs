@@ -1,9 +1,25 @@
+pub mod analytics_service;
+pub mod cluster_service;
+pub mod content_store;
+pub mod embedding_service;
+pub mod export_service;
 pub mod file_service;
 pub mod graph_service;
+pub mod history_service;
+pub mod import_service;
+pub mod metadata_file;
+pub mod node_lock_service;
 pub mod perplexity_service;
+pub mod presence_service;
 pub mod ragflow_service;
 pub mod speech_service;
 pub mod github_service;
+pub mod publish_filter;
+pub mod query_service;
+pub mod recording_service;
+pub mod room_service;
+pub mod search_service;
+pub mod tour_service;
 
 pub use file_service::FileService;
 pub use graph_service::GraphService;
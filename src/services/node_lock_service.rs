@@ -0,0 +1,202 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::models::node::GPUNodePositionUpdate;
+use crate::utils::websocket_manager::SessionId;
+
+/// Positions are clamped to the same cube the force simulation keeps nodes in
+pub const MAX_COORDINATE: f32 = 100.0;
+
+/// A node held by a session while it drags it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NodeLock {
+    pub node: String,
+    pub session: SessionId,
+}
+
+/// Where a session dragged a node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NodeMove {
+    pub node: String,
+    pub position: [f32; 3],
+    pub session: SessionId,
+}
+
+#[derive(Default)]
+struct LockState {
+    owners: HashMap<String, SessionId>,
+    /// Latest position of each node dragged since the last relay
+    moved: BTreeMap<String, NodeMove>,
+}
+
+/// Which session holds which node. Only the holder may move a node, and position
+/// frames from other sessions leave it alone until it is released.
+#[derive(Default)]
+pub struct NodeLocks {
+    state: Mutex<LockState>,
+}
+
+impl NodeLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes `node` for `session`. Fails with the holder if another session has it.
+    pub fn grab(&self, node: &str, session: SessionId) -> Result<(), SessionId> {
+        let mut state = self.state.lock().unwrap();
+        match state.owners.get(node) {
+            Some(&owner) if owner != session => Err(owner),
+            _ => {
+                state.owners.insert(node.to_string(), session);
+                Ok(())
+            }
+        }
+    }
+
+    /// Releases `node` if `session` holds it
+    pub fn release(&self, node: &str, session: SessionId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.owners.get(node) != Some(&session) {
+            return false;
+        }
+        state.owners.remove(node);
+        true
+    }
+
+    /// Releases everything `session` holds, returning the nodes
+    pub fn release_all(&self, session: SessionId) -> Vec<String> {
+        let mut state = self.state.lock().unwrap();
+        let released: Vec<String> = state.owners.iter()
+            .filter(|(_, &owner)| owner == session)
+            .map(|(node, _)| node.clone())
+            .collect();
+        for node in &released {
+            state.owners.remove(node);
+        }
+        released
+    }
+
+    pub fn owner(&self, node: &str) -> Option<SessionId> {
+        self.state.lock().unwrap().owners.get(node).copied()
+    }
+
+    /// Every held node, by node id
+    pub fn locks(&self) -> Vec<NodeLock> {
+        let state = self.state.lock().unwrap();
+        let mut locks: Vec<NodeLock> = state.owners.iter()
+            .map(|(node, &session)| NodeLock { node: node.clone(), session })
+            .collect();
+        locks.sort_by(|a, b| a.node.cmp(&b.node));
+        locks
+    }
+
+    /// Nodes held by sessions other than `session`
+    pub fn held_by_others(&self, session: SessionId) -> HashMap<String, SessionId> {
+        let state = self.state.lock().unwrap();
+        state.owners.iter()
+            .filter(|(_, &owner)| owner != session)
+            .map(|(node, &owner)| (node.clone(), owner))
+            .collect()
+    }
+
+    /// Queues a move for relay, replacing any earlier one of the same node
+    pub fn record_move(&self, node_move: NodeMove) {
+        self.state.lock().unwrap().moved.insert(node_move.node.clone(), node_move);
+    }
+
+    /// Moves recorded since the last call
+    pub fn take_moves(&self) -> Vec<NodeMove> {
+        std::mem::take(&mut self.state.lock().unwrap().moved).into_values().collect()
+    }
+}
+
+/// Checks a client position and clamps it into the simulated space.
+pub fn validate_position(position: [f32; 3]) -> Result<[f32; 3], String> {
+    if position.iter().any(|v| !v.is_finite()) {
+        return Err(format!("Position {:?} is not finite", position));
+    }
+    Ok(position.map(|v| v.clamp(-MAX_COORDINATE, MAX_COORDINATE)))
+}
+
+/// Splits a `position-velocity-f32` upload into one update per record, dropping
+/// records with non-finite values. Records are in the order of the uploading
+/// session's visible nodes, so `indices` maps each to its graph index.
+pub fn decode_position_frame(data: &[u8], indices: &[usize]) -> Result<Vec<(usize, GPUNodePositionUpdate)>, String> {
+    let record_size = std::mem::size_of::<GPUNodePositionUpdate>();
+    if data.len() != indices.len() * record_size {
+        return Err(format!(
+            "Invalid position data length: expected {} bytes for {} visible nodes, got {}",
+            indices.len() * record_size, indices.len(), data.len()
+        ));
+    }
+    Ok(data.chunks_exact(record_size)
+        .zip(indices)
+        .filter_map(|(record, &index)| {
+            let update: GPUNodePositionUpdate = bytemuck::pod_read_unaligned(record);
+            let position = validate_position([update.x, update.y, update.z]).ok()?;
+            let velocity = [update.vx, update.vy, update.vz];
+            if velocity.iter().any(|v| !v.is_finite()) {
+                return None;
+            }
+            let [x, y, z] = position;
+            let [vx, vy, vz] = velocity;
+            Some((index, GPUNodePositionUpdate { x, y, z, vx, vy, vz }))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locks_are_exclusive_until_released() {
+        let locks = NodeLocks::new();
+        assert!(locks.grab("Rust", 1).is_ok());
+        assert!(locks.grab("Rust", 1).is_ok());
+        assert_eq!(locks.grab("Rust", 2), Err(1));
+        assert!(!locks.release("Rust", 2));
+        assert!(locks.grab("Go", 1).is_ok());
+        assert_eq!(locks.held_by_others(2).len(), 2);
+
+        let mut released = locks.release_all(1);
+        released.sort();
+        assert_eq!(released, vec!["Go".to_string(), "Rust".to_string()]);
+        assert!(locks.grab("Rust", 2).is_ok());
+        assert_eq!(locks.locks(), vec![NodeLock { node: "Rust".to_string(), session: 2 }]);
+    }
+
+    #[test]
+    fn test_moves_are_coalesced_per_node() {
+        let locks = NodeLocks::new();
+        for x in 0..3 {
+            locks.record_move(NodeMove { node: "Rust".to_string(), position: [x as f32, 0.0, 0.0], session: 1 });
+        }
+        let moves = locks.take_moves();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].position, [2.0, 0.0, 0.0]);
+        assert!(locks.take_moves().is_empty());
+    }
+
+    #[test]
+    fn test_position_frames_map_to_visible_nodes() {
+        let records = [
+            [1.0f32, 2.0, 3.0, 0.0, 0.0, 0.0],
+            [f32::NAN, 0.0, 0.0, 0.0, 0.0, 0.0],
+            [500.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        ];
+        let data: Vec<u8> = bytemuck::cast_slice(&records).to_vec();
+
+        // A session viewing three of the graph's nodes uploads just those
+        let updates = decode_position_frame(&data, &[2, 5, 9]).unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].0, 2);
+        assert_eq!([updates[0].1.x, updates[0].1.y, updates[0].1.z], [1.0, 2.0, 3.0]);
+        assert_eq!(updates[1].0, 9);
+        assert_eq!(updates[1].1.x, MAX_COORDINATE);
+
+        assert!(decode_position_frame(&data, &[0, 1]).is_err());
+    }
+}
@@ -8,57 +8,12 @@ use log::{error, info};
 use thiserror::Error;
 use lazy_static::lazy_static;
 use std::env;
-use pulldown_cmark::{Parser, Event, Tag, TagEnd};
 use async_trait::async_trait;
 use config::ConfigError;
 
 use crate::config::Settings;
 use crate::services::file_service::ProcessedFile;
-
-fn split_markdown_blocks(content: &str) -> Vec<String> {
-    let parser = Parser::new(content);
-    let mut blocks = Vec::new();
-    let mut current_block = String::new();
-
-    for event in parser {
-        match event {
-            Event::Start(tag) => match tag {
-                Tag::Heading { .. } | Tag::Item => {
-                    if !current_block.is_empty() {
-                        blocks.push(current_block.clone());
-                        current_block.clear();
-                    }
-                },
-                _ => {},
-            },
-            Event::Text(text) => {
-                current_block.push_str(&text);
-            },
-            Event::End(tag) => match tag {
-                TagEnd::Paragraph | TagEnd::Item => {
-                    if !current_block.is_empty() {
-                        blocks.push(current_block.clone());
-                        current_block.clear();
-                    }
-                },
-                TagEnd::Heading(_) => {
-                    if !current_block.is_empty() {
-                        blocks.push(current_block.clone());
-                        current_block.clear();
-                    }
-                },
-                _ => {},
-            },
-            _ => {},
-        }
-    }
-
-    if !current_block.is_empty() {
-        blocks.push(current_block);
-    }
-
-    blocks
-}
+use crate::utils::outline::split_markdown_blocks;
 
 pub fn select_context_blocks(content: &str, active_block: &str) -> Vec<String> {
     let blocks = split_markdown_blocks(content);
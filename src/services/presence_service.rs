@@ -0,0 +1,186 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+use crate::utils::websocket_manager::SessionId;
+
+/// Longest display name accepted, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Where a tracked head or hand is, in world space.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Pose {
+    pub position: [f32; 3],
+    /// Unit quaternion as x, y, z, w
+    pub orientation: [f32; 4],
+}
+
+impl Pose {
+    pub fn is_finite(&self) -> bool {
+        self.position.iter().chain(self.orientation.iter()).all(|v| v.is_finite())
+    }
+}
+
+/// A session as the other participants see it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Presence {
+    pub session: SessionId,
+    pub name: String,
+    pub head: Option<Pose>,
+    pub left_hand: Option<Pose>,
+    pub right_hand: Option<Pose>,
+    pub selected_node: Option<String>,
+    pub hovered_node: Option<String>,
+}
+
+/// The client's latest state. Everything but `name` replaces what was there before,
+/// so a missing pose is an untracked hand and a missing node means nothing is selected.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PresenceUpdate {
+    /// New display name; the current one is kept when omitted
+    pub name: Option<String>,
+    pub head: Option<Pose>,
+    pub left_hand: Option<Pose>,
+    pub right_hand: Option<Pose>,
+    pub selected_node: Option<String>,
+    pub hovered_node: Option<String>,
+}
+
+#[derive(Default)]
+struct PresenceState {
+    peers: BTreeMap<SessionId, Presence>,
+    /// Sessions whose presence changed since the last relay
+    changed: BTreeSet<SessionId>,
+}
+
+/// Presence of every connected session. Updates are collected here and relayed
+/// to the other sessions in batches, so a client sending poses every frame costs
+/// its peers at most one message per relay interval.
+#[derive(Default)]
+pub struct PresenceRegistry {
+    state: Mutex<PresenceState>,
+}
+
+impl PresenceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a session with nothing tracked yet and returns how it appears to others.
+    pub fn join(&self, session: SessionId, name: &str) -> Presence {
+        let presence = Presence {
+            session,
+            name: clean_name(name).unwrap_or_else(|| format!("Guest {}", session)),
+            head: None,
+            left_hand: None,
+            right_hand: None,
+            selected_node: None,
+            hovered_node: None,
+        };
+        self.state.lock().unwrap().peers.insert(session, presence.clone());
+        presence
+    }
+
+    /// Removes a session. Returns false if it never joined.
+    pub fn leave(&self, session: SessionId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.changed.remove(&session);
+        state.peers.remove(&session).is_some()
+    }
+
+    pub fn update(&self, session: SessionId, update: PresenceUpdate) -> Result<(), String> {
+        let poses = [&update.head, &update.left_hand, &update.right_hand];
+        if poses.iter().any(|pose| pose.is_some_and(|pose| !pose.is_finite())) {
+            return Err("Pose contains a non-finite value".to_string());
+        }
+        let name = match update.name.as_deref() {
+            Some(name) => Some(clean_name(name).ok_or_else(|| format!(
+                "Display name must be 1 to {} characters", MAX_NAME_LENGTH
+            ))?),
+            None => None,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let presence = state.peers.get_mut(&session)
+            .ok_or_else(|| format!("Session {} has no presence", session))?;
+        let updated = Presence {
+            session,
+            name: name.unwrap_or_else(|| presence.name.clone()),
+            head: update.head,
+            left_hand: update.left_hand,
+            right_hand: update.right_hand,
+            selected_node: update.selected_node,
+            hovered_node: update.hovered_node,
+        };
+        if *presence != updated {
+            *presence = updated;
+            state.changed.insert(session);
+        }
+        Ok(())
+    }
+
+    /// Everyone currently present, by session id
+    pub fn peers(&self) -> Vec<Presence> {
+        self.state.lock().unwrap().peers.values().cloned().collect()
+    }
+
+    /// The presence of every session that changed since the last call
+    pub fn take_changed(&self) -> Vec<Presence> {
+        let mut state = self.state.lock().unwrap();
+        let changed = std::mem::take(&mut state.changed);
+        changed.iter().filter_map(|session| state.peers.get(session).cloned()).collect()
+    }
+}
+
+fn clean_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    let length = name.chars().count();
+    (length > 0 && length <= MAX_NAME_LENGTH).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pose(x: f32) -> Pose {
+        Pose { position: [x, 1.6, 0.0], orientation: [0.0, 0.0, 0.0, 1.0] }
+    }
+
+    #[test]
+    fn test_changes_are_coalesced_until_taken() {
+        let registry = PresenceRegistry::new();
+        assert_eq!(registry.join(1, "  Ada ").name, "Ada");
+        assert_eq!(registry.join(2, "").name, "Guest 2");
+
+        for x in 0..3 {
+            let update = PresenceUpdate { head: Some(pose(x as f32)), ..Default::default() };
+            registry.update(1, update).unwrap();
+        }
+        let changed = registry.take_changed();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].head, Some(pose(2.0)));
+        assert_eq!(changed[0].name, "Ada");
+        assert!(registry.take_changed().is_empty());
+
+        // An identical update is not relayed again
+        registry.update(1, PresenceUpdate { head: Some(pose(2.0)), ..Default::default() }).unwrap();
+        assert!(registry.take_changed().is_empty());
+
+        registry.update(2, PresenceUpdate { selected_node: Some("Rust".to_string()), ..Default::default() }).unwrap();
+        assert!(registry.leave(2));
+        assert!(registry.take_changed().is_empty());
+        assert_eq!(registry.peers().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_updates_are_rejected() {
+        let registry = PresenceRegistry::new();
+        registry.join(1, "Ada");
+        let bad_pose = Pose { position: [f32::NAN, 0.0, 0.0], orientation: [0.0, 0.0, 0.0, 1.0] };
+        assert!(registry.update(1, PresenceUpdate { left_hand: Some(bad_pose), ..Default::default() }).is_err());
+        assert!(registry.update(1, PresenceUpdate { name: Some("x".repeat(65)), ..Default::default() }).is_err());
+        assert!(registry.update(7, PresenceUpdate::default()).is_err());
+        assert!(registry.take_changed().is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use regex::Regex;
+use serde::Serialize;
+use log::warn;
+
+use crate::config::PublishFilterSettings;
+use crate::utils::page_properties::PageProperties;
+
+/// Outcome of evaluating a page against the publish filter.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterDecision {
+    pub file_name: String,
+    pub publish: bool,
+    pub reason: String,
+}
+
+/// Compiled form of [`PublishFilterSettings`].
+pub struct PublishFilter {
+    settings: PublishFilterSettings,
+    include_paths: Vec<(String, Regex)>,
+    exclude_paths: Vec<(String, Regex)>,
+}
+
+impl PublishFilter {
+    pub fn new(settings: &PublishFilterSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            include_paths: compile_globs(&settings.include_paths),
+            exclude_paths: compile_globs(&settings.exclude_paths),
+        }
+    }
+
+    /// Decide whether a page should be published, with a human readable reason.
+    pub fn evaluate(&self, path: &str, content: &str) -> FilterDecision {
+        let (publish, reason) = self.decide(path, content);
+        FilterDecision {
+            file_name: path.to_string(),
+            publish,
+            reason,
+        }
+    }
+
+    fn decide(&self, path: &str, content: &str) -> (bool, String) {
+        if let Some((glob, _)) = self.exclude_paths.iter().find(|(_, re)| re.is_match(path)) {
+            return (false, format!("path matches exclude glob `{}`", glob));
+        }
+
+        let props = PageProperties::parse(content);
+        let tags = props.tags(content);
+
+        if let Some(tag) = self.settings.exclude_tags.iter().find(|t| contains_tag(&tags, t)) {
+            return (false, format!("tagged with excluded tag `#{}`", tag));
+        }
+
+        for (key, values) in &self.settings.page_properties {
+            if let Some(value) = props.property(key) {
+                if values.is_empty() || values.iter().any(|v| v.eq_ignore_ascii_case(value.trim())) {
+                    return (true, format!("page property `{}:: {}`", key, value));
+                }
+            }
+        }
+
+        for (key, values) in &self.settings.front_matter {
+            let Some(found_key) = props.front_matter.keys().find(|k| k.eq_ignore_ascii_case(key)) else {
+                continue;
+            };
+            let found = props.front_matter_values(found_key);
+            if values.is_empty() {
+                return (true, format!("front matter key `{}`", found_key));
+            }
+            if let Some(value) = found.iter().find(|f| values.iter().any(|v| v.eq_ignore_ascii_case(f))) {
+                return (true, format!("front matter `{}: {}`", found_key, value));
+            }
+        }
+
+        if let Some(tag) = self.settings.include_tags.iter().find(|t| contains_tag(&tags, t)) {
+            return (true, format!("tagged with included tag `#{}`", tag));
+        }
+
+        if let Some((glob, _)) = self.include_paths.iter().find(|(_, re)| re.is_match(path)) {
+            return (true, format!("path matches include glob `{}`", glob));
+        }
+
+        if self.has_include_rules() {
+            (false, "no include rule matched".to_string())
+        } else {
+            (true, "no include rules configured".to_string())
+        }
+    }
+
+    fn has_include_rules(&self) -> bool {
+        !self.settings.page_properties.is_empty()
+            || !self.settings.front_matter.is_empty()
+            || !self.settings.include_tags.is_empty()
+            || !self.include_paths.is_empty()
+    }
+}
+
+fn contains_tag(tags: &[String], tag: &str) -> bool {
+    let tag = tag.trim_start_matches('#').to_lowercase();
+    tags.contains(&tag)
+}
+
+fn compile_globs(globs: &[String]) -> Vec<(String, Regex)> {
+    globs.iter()
+        .filter_map(|glob| match glob_to_regex(glob) {
+            Ok(re) => Some((glob.clone(), re)),
+            Err(e) => {
+                warn!("Ignoring invalid path glob `{}`: {}", glob, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Translate a path glob into an anchored regex.
+/// `**` matches across directories, `*` within one path segment, `?` a single character.
+fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches zero directories
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            other => pattern.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+
+    pattern.push('$');
+    Regex::new(&pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn filter(settings: PublishFilterSettings) -> PublishFilter {
+        PublishFilter::new(&settings)
+    }
+
+    impl PublishFilter {
+        fn is_public(&self, path: &str, content: &str) -> bool {
+            self.evaluate(path, content).publish
+        }
+    }
+
+    #[test]
+    fn test_default_matches_public_property() {
+        let f = filter(PublishFilterSettings::default());
+        assert!(f.is_public("a.md", "public:: true\n\n- content"));
+        assert!(f.is_public("a.md", "title:: A\npublic:: true\n- content"));
+        assert!(!f.is_public("a.md", "- content\n- public:: true"));
+    }
+
+    #[test]
+    fn test_property_front_matter_and_tags() {
+        let f = filter(PublishFilterSettings {
+            page_properties: HashMap::from([("visibility".to_string(), vec!["team".to_string()])]),
+            front_matter: HashMap::from([("publish".to_string(), vec![])]),
+            include_tags: vec!["shared".to_string()],
+            exclude_tags: vec!["#private".to_string()],
+            ..PublishFilterSettings::default()
+        });
+
+        assert!(f.is_public("a.md", "visibility:: team\n"));
+        assert!(f.is_public("a.md", "---\npublish: false\n---\nbody"));
+        assert!(f.is_public("a.md", "- notes #shared"));
+        assert!(!f.is_public("a.md", "visibility:: team\n- #private"));
+        assert!(!f.is_public("a.md", "- nothing here"));
+    }
+
+    #[test]
+    fn test_path_globs() {
+        let f = filter(PublishFilterSettings {
+            page_properties: HashMap::new(),
+            exclude_paths: vec!["journals/*".to_string(), "**/draft-*.md".to_string()],
+            ..PublishFilterSettings::default()
+        });
+
+        assert!(f.is_public("Rust.md", ""));
+        assert!(!f.is_public("journals/2024_01_01.md", ""));
+        assert!(!f.is_public("draft-ideas.md", ""));
+        assert!(!f.is_public("notes/deep/draft-ideas.md", ""));
+
+        let decision = f.evaluate("journals/x.md", "");
+        assert_eq!(decision.reason, "path matches exclude glob `journals/*`");
+    }
+
+    #[test]
+    fn test_include_paths_are_one_include_rule_among_others() {
+        let f = filter(PublishFilterSettings {
+            page_properties: HashMap::new(),
+            include_tags: vec!["shared".to_string()],
+            include_paths: vec!["journals/**".to_string()],
+            ..PublishFilterSettings::default()
+        });
+
+        assert!(f.is_public("journals/2024/2024_01_01.md", "- nothing here"));
+        // A matching tag publishes a page outside the included folders
+        assert!(f.is_public("pages/Rust.md", "- notes #shared"));
+        assert!(!f.is_public("pages/Rust.md", "- nothing here"));
+        assert_eq!(f.evaluate("journals/x.md", "").reason, "path matches include glob `journals/**`");
+    }
+}
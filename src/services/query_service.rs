@@ -0,0 +1,644 @@
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use thiserror::Error;
+
+use crate::models::graph::GraphData;
+use crate::models::metadata::PropertyValue;
+use crate::models::node::Node;
+use crate::services::analytics_service::{self, AnalyticsCache, Degrees, GraphAnalytics};
+use crate::utils::page_properties::parse_date;
+
+/// Fields counted from the edges rather than stored on nodes
+const DEGREE_FIELDS: &[&str] = &["degree", "in_degree", "out_degree"];
+/// Fields that need the whole-graph analytics
+const METRIC_FIELDS: &[&str] = &["pagerank", "betweenness", "community", "component"];
+/// Longest query accepted, in bytes
+pub const MAX_QUERY_LENGTH: usize = 4096;
+/// Deepest nesting of parentheses, `NOT` and `NEAR[k]` accepted
+pub const MAX_QUERY_DEPTH: usize = 64;
+
+#[derive(Debug, Error, PartialEq)]
+#[error("{message} at position {position}")]
+pub struct QueryError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self { message: message.into(), position }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Bare word or quoted string: node id or label
+    Name(String),
+    /// `field:value`, matched like node attribute filters; a trailing `*` matches a prefix
+    Match { field: String, value: String },
+    /// `field>value` etc. on numbers and dates; `=` and `!=` also work on text
+    Compare { field: String, op: Comparison, value: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Nodes within `hops` of any node matching the inner expression
+    Near { hops: usize, target: Box<Expr> },
+    Term(Term),
+}
+
+/// A parsed graph query.
+///
+/// ```text
+/// query  := or
+/// or     := and ("OR" and)*
+/// and    := unary (["AND"] unary)*
+/// unary  := "NOT" unary | "NEAR[k]" unary | atom ("NEAR[k]" atom)*
+/// atom   := "(" query ")" | term
+/// term   := word | "quoted" | field ":" value | field (">" | ">=" | "<" | "<=" | "=" | "!=") value
+/// ```
+///
+/// `a NEAR[k] b` selects nodes matching `a` within `k` hops of a node matching `b`,
+/// e.g. `tag:rust AND degree>3 NEAR[2] "Project X"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQuery {
+    expr: Expr,
+}
+
+impl GraphQuery {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        if input.len() > MAX_QUERY_LENGTH {
+            return Err(QueryError::new(format!("Query is longer than {} bytes", MAX_QUERY_LENGTH), MAX_QUERY_LENGTH));
+        }
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, position: 0, end: input.len(), depth: 0 };
+        let expr = parser.parse_or()?;
+        if let Some((_, position)) = parser.tokens.get(parser.position) {
+            return Err(QueryError::new("Unexpected token", *position));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Whether the query compares whole-graph metrics, which must be passed to `evaluate`
+    pub fn needs_analytics(&self) -> bool {
+        uses_fields(&self.expr, METRIC_FIELDS)
+    }
+
+    /// Ids of the matching nodes, in graph order. Degrees are counted here when
+    /// `analytics` isn't given.
+    pub fn evaluate(&self, graph: &GraphData, analytics: Option<&GraphAnalytics>) -> Vec<String> {
+        let degrees = (analytics.is_none() && uses_fields(&self.expr, DEGREE_FIELDS))
+            .then(|| analytics_service::degrees(graph));
+        let matches = Evaluator { graph, analytics, degrees }.eval(&self.expr);
+        graph.nodes.iter()
+            .filter(|node| matches.contains(node.id.as_str()))
+            .map(|node| node.id.clone())
+            .collect()
+    }
+}
+
+/// One criterion of a session's view. A view shows the nodes matching all of its filters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ViewFilter {
+    /// Exactly these nodes
+    Nodes { ids: Vec<String> },
+    /// Nodes whose attribute matches, as in `/api/graph/nodes?key=value`
+    Metadata { key: String, value: String },
+    /// Nodes whose id starts with the prefix, e.g. a folder or namespace
+    Prefix { prefix: String },
+    /// A node and everything within `hops` of it
+    Neighbourhood { node: String, hops: usize },
+    Query { query: String },
+}
+
+/// The analytics `filters` need, if any, computed off the executor on a cache miss.
+/// Invalid queries need none; `select_view` reports them.
+pub async fn view_analytics(graph: &RwLock<GraphData>, filters: &[ViewFilter], cache: &Arc<AnalyticsCache>) -> Option<Arc<GraphAnalytics>> {
+    let needed = filters.iter().any(|filter| match filter {
+        ViewFilter::Query { query } => GraphQuery::parse(query).is_ok_and(|query| query.needs_analytics()),
+        _ => false,
+    });
+    match needed {
+        true => Some(cache.analyze(graph).await),
+        false => None,
+    }
+}
+
+/// Ids of the nodes matching every filter, in graph order. No filters selects every node.
+pub fn select_view(graph: &GraphData, filters: &[ViewFilter], analytics: Option<&GraphAnalytics>) -> Result<Vec<String>, QueryError> {
+    let mut selected: HashSet<String> = graph.nodes.iter().map(|n| n.id.clone()).collect();
+    for filter in filters {
+        let matches: HashSet<String> = match filter {
+            ViewFilter::Nodes { ids } => ids.iter().cloned().collect(),
+            ViewFilter::Metadata { key, value } => graph.nodes.iter()
+                .filter(|n| n.matches_attributes([(key, value)]))
+                .map(|n| n.id.clone())
+                .collect(),
+            ViewFilter::Prefix { prefix } => graph.nodes.iter()
+                .filter(|n| n.id.starts_with(prefix.as_str()))
+                .map(|n| n.id.clone())
+                .collect(),
+            ViewFilter::Neighbourhood { node, hops } => {
+                analytics_service::nodes_within_hops(graph, &HashSet::from([node.as_str()]), *hops)
+            },
+            ViewFilter::Query { query } => GraphQuery::parse(query)?.evaluate(graph, analytics).into_iter().collect(),
+        };
+        selected.retain(|id| matches.contains(id));
+    }
+
+    Ok(graph.nodes.iter()
+        .filter(|n| selected.contains(&n.id))
+        .map(|n| n.id.clone())
+        .collect())
+}
+
+/// The nodes in `ids` with the edges between them, keeping graph order.
+pub fn induced_subgraph(graph: &GraphData, ids: &[String]) -> GraphData {
+    let selected: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    GraphData {
+        nodes: graph.nodes.iter()
+            .filter(|n| selected.contains(n.id.as_str()))
+            .cloned()
+            .collect(),
+        edges: graph.edges.iter()
+            .filter(|e| selected.contains(e.source.as_str()) && selected.contains(e.target_node.as_str()))
+            .cloned()
+            .collect(),
+        metadata: graph.metadata.iter()
+            .filter(|(file_name, _)| selected.contains(file_name.trim_end_matches(".md")))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Near(usize),
+    Quoted(String),
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            chars.next();
+            tokens.push((if c == '(' { Token::LParen } else { Token::RParen }, start));
+        } else if c == '"' {
+            chars.next();
+            tokens.push((Token::Quoted(read_quoted(&mut chars, start)?), start));
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                chars.next();
+                if c == '"' {
+                    // `field:"quoted value"`
+                    word.push_str(&read_quoted(&mut chars, start)?);
+                    break;
+                }
+                word.push(c);
+            }
+            tokens.push((keyword(&word, start)?.unwrap_or(Token::Word(word)), start));
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::CharIndices>, start: usize) -> Result<String, QueryError> {
+    let mut value = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '"' => return Ok(value),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+    Err(QueryError::new("Unterminated string", start))
+}
+
+fn keyword(word: &str, position: usize) -> Result<Option<Token>, QueryError> {
+    let upper = word.to_uppercase();
+    Ok(match upper.as_str() {
+        "AND" => Some(Token::And),
+        "OR" => Some(Token::Or),
+        "NOT" => Some(Token::Not),
+        "NEAR" => Some(Token::Near(1)),
+        _ if upper.starts_with("NEAR[") && upper.ends_with(']') => {
+            let hops = upper[5..upper.len() - 1].parse()
+                .map_err(|_| QueryError::new("NEAR expects a hop count like NEAR[2]", position))?;
+            Some(Token::Near(hops))
+        },
+        _ => None,
+    })
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    end: usize,
+    /// Nesting of the expression being parsed, bounded so a query can't exhaust the stack
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.position).map_or(self.end, |(_, offset)| *offset)
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<Expr, QueryError>) -> Result<Expr, QueryError> {
+        if self.depth == MAX_QUERY_DEPTH {
+            return Err(QueryError::new(format!("Query nests deeper than {} levels", MAX_QUERY_DEPTH), self.offset()));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.position += 1,
+                // Adjacent terms are an implicit AND
+                Some(Token::Word(_) | Token::Quoted(_) | Token::LParen | Token::Not | Token::Near(_)) => {},
+                _ => return Ok(left),
+            }
+            left = Expr::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.position += 1;
+                Ok(Expr::Not(Box::new(self.nested(Self::parse_unary)?)))
+            },
+            Some(&Token::Near(hops)) => {
+                self.position += 1;
+                Ok(Expr::Near { hops, target: Box::new(self.nested(Self::parse_unary)?) })
+            },
+            _ => {
+                let mut left = self.parse_atom()?;
+                while let Some(&Token::Near(hops)) = self.peek() {
+                    self.position += 1;
+                    let near = Expr::Near { hops, target: Box::new(self.parse_atom()?) };
+                    left = Expr::And(Box::new(left), Box::new(near));
+                }
+                Ok(left)
+            },
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, QueryError> {
+        let offset = self.offset();
+        let Some((token, _)) = self.tokens.get(self.position).cloned() else {
+            return Err(QueryError::new("Unexpected end of query", offset));
+        };
+        self.position += 1;
+        match token {
+            Token::LParen => {
+                let expr = self.nested(Self::parse_or)?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(QueryError::new("Expected ')'", self.offset()));
+                }
+                self.position += 1;
+                Ok(expr)
+            },
+            Token::Quoted(name) => Ok(Expr::Term(Term::Name(name))),
+            Token::Word(word) => parse_term(&word, offset).map(Expr::Term),
+            _ => Err(QueryError::new("Expected a term", offset)),
+        }
+    }
+}
+
+fn parse_term(word: &str, position: usize) -> Result<Term, QueryError> {
+    let Some(split) = word.find([':', '=', '!', '<', '>']) else {
+        return Ok(Term::Name(word.to_string()));
+    };
+    let field = word[..split].to_lowercase();
+    let rest = &word[split..];
+    let (op, value) = if let Some(value) = rest.strip_prefix(':') {
+        return match field.is_empty() || value.is_empty() {
+            true => Err(QueryError::new("Expected field:value", position)),
+            false => Ok(Term::Match { field, value: value.to_string() }),
+        };
+    } else if let Some(value) = rest.strip_prefix(">=") {
+        (Comparison::Ge, value)
+    } else if let Some(value) = rest.strip_prefix("<=") {
+        (Comparison::Le, value)
+    } else if let Some(value) = rest.strip_prefix("!=") {
+        (Comparison::Ne, value)
+    } else if let Some(value) = rest.strip_prefix('>') {
+        (Comparison::Gt, value)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        (Comparison::Lt, value)
+    } else if let Some(value) = rest.strip_prefix('=') {
+        (Comparison::Eq, value)
+    } else {
+        return Err(QueryError::new("Unknown operator", position + split));
+    };
+
+    if field.is_empty() || value.is_empty() {
+        return Err(QueryError::new("Comparison needs a field and a value", position));
+    }
+    Ok(Term::Compare { field, op, value: value.to_string() })
+}
+
+fn uses_fields(expr: &Expr, fields: &[&str]) -> bool {
+    match expr {
+        Expr::And(a, b) | Expr::Or(a, b) => uses_fields(a, fields) || uses_fields(b, fields),
+        Expr::Not(inner) | Expr::Near { target: inner, .. } => uses_fields(inner, fields),
+        Expr::Term(Term::Match { field, .. } | Term::Compare { field, .. }) => fields.contains(&field.as_str()),
+        Expr::Term(Term::Name(_)) => false,
+    }
+}
+
+struct Evaluator<'a> {
+    graph: &'a GraphData,
+    analytics: Option<&'a GraphAnalytics>,
+    /// Counted when the query compares degrees and no analytics were given
+    degrees: Option<HashMap<String, Degrees>>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn eval(&self, expr: &Expr) -> HashSet<&'a str> {
+        match expr {
+            Expr::And(a, b) => {
+                let left = self.eval(a);
+                let right = self.eval(b);
+                left.intersection(&right).copied().collect()
+            },
+            Expr::Or(a, b) => {
+                let mut left = self.eval(a);
+                left.extend(self.eval(b));
+                left
+            },
+            Expr::Not(inner) => {
+                let excluded = self.eval(inner);
+                self.all().filter(|id| !excluded.contains(id)).collect()
+            },
+            Expr::Near { hops, target } => {
+                let near = analytics_service::nodes_within_hops(self.graph, &self.eval(target), *hops);
+                self.all().filter(|id| near.contains(*id)).collect()
+            },
+            Expr::Term(term) => self.graph.nodes.iter()
+                .filter(|node| self.matches(node, term))
+                .map(|node| node.id.as_str())
+                .collect(),
+        }
+    }
+
+    fn all(&self) -> impl Iterator<Item = &'a str> {
+        self.graph.nodes.iter().map(|node| node.id.as_str())
+    }
+
+    fn matches(&self, node: &Node, term: &Term) -> bool {
+        match term {
+            Term::Name(name) => text_matches(&node.id, name) || text_matches(&node.label, name),
+            Term::Match { field, value } => match self.field(node, field) {
+                Some(PropertyValue::Text(text)) => text_matches(&text, value),
+                Some(PropertyValue::List(items)) => items.iter().any(|item| text_matches(item, value)),
+                Some(other) => other.matches(value),
+                None => false,
+            },
+            Term::Compare { field, op, value } => match self.field(node, field) {
+                Some(actual) => compare(&actual, *op, value),
+                None => *op == Comparison::Ne,
+            },
+        }
+    }
+
+    fn field(&self, node: &Node, field: &str) -> Option<PropertyValue> {
+        match field {
+            "id" => return Some(PropertyValue::Text(node.id.clone())),
+            "label" => return Some(PropertyValue::Text(node.label.clone())),
+            "tag" => return node.metadata.get("tags").cloned(),
+            _ => {},
+        }
+        if let Some(metrics) = self.analytics.and_then(|a| a.nodes.get(&node.id)) {
+            let value = match field {
+                "degree" => Some(metrics.degree as f64),
+                "in_degree" => Some(metrics.in_degree as f64),
+                "out_degree" => Some(metrics.out_degree as f64),
+                "pagerank" => Some(metrics.pagerank),
+                "betweenness" => Some(metrics.betweenness),
+                "community" => Some(metrics.community as f64),
+                "component" => Some(metrics.component as f64),
+                _ => None,
+            };
+            if let Some(value) = value {
+                return Some(PropertyValue::Number(value));
+            }
+        }
+        if let Some(degrees) = self.degrees.as_ref().and_then(|d| d.get(&node.id)) {
+            let value = match field {
+                "degree" => Some(degrees.degree),
+                "in_degree" => Some(degrees.in_degree),
+                "out_degree" => Some(degrees.out_degree),
+                _ => None,
+            };
+            if let Some(value) = value {
+                return Some(PropertyValue::Number(value as f64));
+            }
+        }
+        node.metadata.get(field).cloned()
+    }
+}
+
+/// Case-insensitive equality, or a prefix match when `pattern` ends in `*`
+fn text_matches(text: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => text.to_lowercase().starts_with(&prefix.to_lowercase()),
+        None => text.eq_ignore_ascii_case(pattern),
+    }
+}
+
+fn compare(actual: &PropertyValue, op: Comparison, value: &str) -> bool {
+    let ordering = match actual {
+        PropertyValue::Number(n) => value.parse::<f64>().ok().and_then(|v| n.partial_cmp(&v)),
+        PropertyValue::Date(d) => parse_date(value).map(|v| d.cmp(&v)),
+        PropertyValue::Text(_) | PropertyValue::List(_) | PropertyValue::Bool(_) => {
+            let matched = match actual {
+                PropertyValue::Text(text) => text_matches(text, value),
+                _ => actual.matches(value),
+            };
+            return match op {
+                Comparison::Eq => matched,
+                Comparison::Ne => !matched,
+                _ => false,
+            };
+        },
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        Comparison::Eq => ordering.is_eq(),
+        Comparison::Ne => ordering.is_ne(),
+        Comparison::Gt => ordering.is_gt(),
+        Comparison::Ge => ordering.is_ge(),
+        Comparison::Lt => ordering.is_lt(),
+        Comparison::Le => ordering.is_le(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::edge::Edge;
+    use std::collections::HashMap;
+
+    fn node(id: &str, tags: &[&str], status: &str) -> Node {
+        let mut node = Node::new(id.to_string());
+        node.metadata.insert("tags".to_string(), PropertyValue::List(tags.iter().map(|t| t.to_string()).collect()));
+        node.metadata.insert("status".to_string(), PropertyValue::Text(status.to_string()));
+        node
+    }
+
+    /// Project X - Rust - Tokio - Actix, Rust - Borrowing, and an unrelated Bread page
+    fn graph() -> GraphData {
+        let edges = [("Project X", "Rust"), ("Rust", "Tokio"), ("Tokio", "Actix"), ("Rust", "Borrowing"), ("Actix", "Rust")];
+        GraphData {
+            nodes: vec![
+                node("Project X", &["project"], "active"),
+                node("Rust", &["rust", "lang"], "active"),
+                node("Tokio", &["rust"], "done"),
+                node("Actix", &["rust", "web"], "active"),
+                node("Borrowing", &["rust"], "done"),
+                node("Bread", &["cooking"], "done"),
+            ],
+            edges: edges.iter().map(|(s, t)| Edge::new(s.to_string(), t.to_string(), 1.0)).collect(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn run(query: &str) -> Vec<String> {
+        GraphQuery::parse(query).unwrap().evaluate(&graph(), None)
+    }
+
+    #[test]
+    fn test_example_query() {
+        // Rust-tagged nodes with more than one neighbour, at most two hops from Project X
+        assert_eq!(run(r#"tag:rust AND degree>1 NEAR[2] "Project X""#), vec!["Rust", "Tokio", "Actix"]);
+        assert_eq!(run(r#"tag:rust NEAR[1] "Project X""#), vec!["Rust"]);
+    }
+
+    #[test]
+    fn test_boolean_operators_and_precedence() {
+        assert_eq!(run("tag:rust status:done"), vec!["Tokio", "Borrowing"]);
+        assert_eq!(run("tag:cooking OR tag:web AND status:active"), vec!["Actix", "Bread"]);
+        assert_eq!(run("(tag:cooking OR tag:web) AND status:active"), vec!["Actix"]);
+        assert_eq!(run("NOT tag:rust AND NOT id:Proj*"), vec!["Bread"]);
+        assert_eq!(run("NEAR[1] Tokio"), vec!["Rust", "Tokio", "Actix"]);
+        assert_eq!(run("status!=done"), vec!["Project X", "Rust", "Actix"]);
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        assert_eq!(GraphQuery::parse("tag:rust AND (status:done").unwrap_err().position, 25);
+        assert_eq!(GraphQuery::parse("\"Project X").unwrap_err().message, "Unterminated string");
+        assert!(GraphQuery::parse("NEAR[x] Rust").is_err());
+        assert!(GraphQuery::parse("tag:rust OR").is_err());
+    }
+
+    #[test]
+    fn test_deep_and_long_queries_are_rejected() {
+        let parens = format!("{}x{}", "(".repeat(30000), ")".repeat(30000));
+        assert!(GraphQuery::parse(&parens).unwrap_err().message.contains("longer"));
+        let parens = format!("{}x{}", "(".repeat(100), ")".repeat(100));
+        assert!(GraphQuery::parse(&parens).unwrap_err().message.contains("nests"));
+        assert!(GraphQuery::parse(&"NOT ".repeat(100)).unwrap_err().message.contains("nests"));
+        assert!(GraphQuery::parse(&"NEAR[1] ".repeat(100)).unwrap_err().message.contains("nests"));
+        let nested = format!("{}x{}", "(".repeat(MAX_QUERY_DEPTH), ")".repeat(MAX_QUERY_DEPTH));
+        assert!(GraphQuery::parse(&nested).is_ok());
+
+        // The longest flat query still evaluates
+        let flat = "x ".repeat(MAX_QUERY_LENGTH / 2);
+        GraphQuery::parse(&flat).unwrap().evaluate(&graph(), None);
+    }
+
+    #[test]
+    fn test_view_filters_intersect() {
+        let graph = graph();
+        let view = |filters: &[ViewFilter]| select_view(&graph, filters, None).unwrap();
+
+        assert_eq!(view(&[]).len(), 6);
+        assert_eq!(view(&[ViewFilter::Prefix { prefix: "B".to_string() }]), vec!["Borrowing", "Bread"]);
+        assert_eq!(view(&[
+            ViewFilter::Neighbourhood { node: "Rust".to_string(), hops: 1 },
+            ViewFilter::Metadata { key: "status".to_string(), value: "done".to_string() },
+        ]), vec!["Tokio", "Borrowing"]);
+        assert_eq!(view(&[
+            ViewFilter::Nodes { ids: vec!["Bread".to_string(), "Rust".to_string(), "Missing".to_string()] },
+            ViewFilter::Query { query: "tag:rust".to_string() },
+        ]), vec!["Rust"]);
+        assert!(select_view(&graph, &[ViewFilter::Query { query: "(".to_string() }], None).is_err());
+    }
+
+    #[test]
+    fn test_only_metric_fields_need_analytics() {
+        assert!(!GraphQuery::parse("degree>1 AND tag:rust").unwrap().needs_analytics());
+        assert!(GraphQuery::parse("tag:rust AND NOT community=0").unwrap().needs_analytics());
+
+        let graph = graph();
+        let analytics = AnalyticsCache::new().get_or_compute(&graph);
+        let query = GraphQuery::parse("degree>1").unwrap();
+        assert_eq!(query.evaluate(&graph, None), query.evaluate(&graph, Some(&analytics)));
+    }
+
+    #[test]
+    fn test_induced_subgraph_keeps_internal_edges() {
+        let graph = graph();
+        let ids = vec!["Rust".to_string(), "Tokio".to_string(), "Bread".to_string()];
+        let subgraph = induced_subgraph(&graph, &ids);
+        assert_eq!(subgraph.nodes.len(), 3);
+        assert_eq!(subgraph.edges.len(), 1);
+        assert_eq!(subgraph.edges[0].target_node, "Tokio");
+    }
+}
@@ -0,0 +1,372 @@
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::utils::websocket_manager::SessionId;
+
+pub const RECORDING_EXTENSION: &str = "wsrec";
+const MAGIC: &[u8; 4] = b"WSRC";
+const FORMAT_VERSION: u8 = 1;
+const FLAG_SENT: u8 = 1;
+const FLAG_BINARY: u8 = 2;
+/// Longest header or frame record, so a corrupt length can't make a reader allocate gigabytes
+const MAX_RECORD_LENGTH: u32 = 64 * 1024 * 1024;
+
+/// Which session a recording is of, stored at its start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub session: SessionId,
+    pub user: Option<String>,
+    pub room: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl RecordingInfo {
+    /// Recordings are named after when the session started and its id
+    pub fn recording_id(&self) -> String {
+        format!("{}-{}", self.started_at.format("%Y%m%dT%H%M%SZ"), self.session)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// From the client to the server
+    Received,
+    /// From the server to the client
+    Sent,
+}
+
+/// A frame being recorded
+#[derive(Debug, Clone, Copy)]
+pub enum Frame<'a> {
+    Text(&'a str),
+    Binary(&'a [u8]),
+}
+
+/// A frame read back from a recording
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    /// Milliseconds since the recording started
+    pub at_ms: u32,
+    pub direction: Direction,
+    pub payload: Payload,
+}
+
+/// Reads a recording's frames one at a time, so a replay never holds the whole file
+pub struct RecordingReader<R> {
+    pub info: RecordingInfo,
+    reader: R,
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let info = read_header(&mut reader)?;
+        Ok(Self { info, reader })
+    }
+
+    /// The next frame the server sent, which is what a replay shows
+    pub fn next_sent(&mut self) -> io::Result<Option<RecordedFrame>> {
+        for frame in self.by_ref() {
+            let frame = frame?;
+            if frame.direction == Direction::Sent {
+                return Ok(Some(frame));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl RecordingReader<io::BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(io::BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = io::Result<RecordedFrame>;
+
+    // A session that ended with the server leaves a partial last record; it ends the recording
+    fn next(&mut self) -> Option<Self::Item> {
+        read_frame(&mut self.reader).transpose()
+    }
+}
+
+/// Writes one session's frames to `<dir>/<id>.wsrec` as they happen.
+///
+/// A recording is the magic `WSRC`, a format version byte and a length-prefixed JSON
+/// [`RecordingInfo`], then one record per frame: a flags byte (bit 0 set if sent,
+/// bit 1 if binary), milliseconds since the start as u32, the payload length as u32,
+/// and the payload. Integers are little-endian. Frames are recorded before
+/// compression, so a replay works for any client.
+pub struct SessionRecorder {
+    id: String,
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+impl SessionRecorder {
+    pub fn create(dir: impl AsRef<Path>, info: &RecordingInfo) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let id = info.recording_id();
+        let path = recording_path(&dir, &id);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        write_header(&mut writer, info)?;
+        info!("Recording session {} to {}", info.session, path.display());
+        Ok(Self { id, writer, started: Instant::now() })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn record(&mut self, direction: Direction, frame: Frame) -> io::Result<()> {
+        let at_ms = u32::try_from(self.started.elapsed().as_millis()).unwrap_or(u32::MAX);
+        write_frame(&mut self.writer, at_ms, direction, frame)
+    }
+
+    /// Push buffered frames to disk, so little is lost if the server stops abruptly
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn write_header(writer: &mut impl Write, info: &RecordingInfo) -> io::Result<()> {
+    let info = serde_json::to_vec(info).map_err(io::Error::other)?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[FORMAT_VERSION])?;
+    writer.write_all(&(info.len() as u32).to_le_bytes())?;
+    writer.write_all(&info)
+}
+
+fn write_frame(writer: &mut impl Write, at_ms: u32, direction: Direction, frame: Frame) -> io::Result<()> {
+    let (binary, payload) = match frame {
+        Frame::Text(text) => (false, text.as_bytes()),
+        Frame::Binary(data) => (true, data),
+    };
+    let mut flags = 0;
+    if direction == Direction::Sent {
+        flags |= FLAG_SENT;
+    }
+    if binary {
+        flags |= FLAG_BINARY;
+    }
+    let length = match u32::try_from(payload.len()) {
+        Ok(length) if length <= MAX_RECORD_LENGTH => length,
+        _ => return Err(io::Error::other("Frame too large to record")),
+    };
+    writer.write_all(&[flags])?;
+    writer.write_all(&at_ms.to_le_bytes())?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(payload)
+}
+
+fn read_header(reader: &mut impl Read) -> io::Result<RecordingInfo> {
+    let mut magic = [0u8; 5];
+    reader.read_exact(&mut magic)?;
+    if &magic[..4] != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a session recording"));
+    }
+    if magic[4] != FORMAT_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsupported recording version {}", magic[4])));
+    }
+    let length = check_length(read_u32(reader)?)?;
+    let mut info = vec![0u8; length];
+    reader.read_exact(&mut info)?;
+    serde_json::from_slice(&info).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The next frame, or `None` at the end of the recording or a partial last record
+fn read_frame(reader: &mut impl Read) -> io::Result<Option<RecordedFrame>> {
+    let mut head = [0u8; 9];
+    match reader.read_exact(&mut head) {
+        Ok(()) => {},
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let flags = head[0];
+    let at_ms = u32::from_le_bytes(head[1..5].try_into().unwrap());
+    let length = check_length(u32::from_le_bytes(head[5..9].try_into().unwrap()))?;
+    let mut data = vec![0u8; length];
+    match reader.read_exact(&mut data) {
+        Ok(()) => {},
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let payload = match flags & FLAG_BINARY {
+        0 => Payload::Text(String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?),
+        _ => Payload::Binary(data),
+    };
+    let direction = match flags & FLAG_SENT {
+        0 => Direction::Received,
+        _ => Direction::Sent,
+    };
+    Ok(Some(RecordedFrame { at_ms, direction, payload }))
+}
+
+fn check_length(length: u32) -> io::Result<usize> {
+    if length > MAX_RECORD_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Record of {} bytes exceeds the limit", length)));
+    }
+    Ok(length as usize)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Recording ids become file names, so they are limited to letters, digits and `-`.
+pub fn validate_recording_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid recording id: {}", id));
+    }
+    Ok(())
+}
+
+pub fn recording_path(dir: impl AsRef<Path>, id: &str) -> PathBuf {
+    dir.as_ref().join(format!("{}.{}", id, RECORDING_EXTENSION))
+}
+
+/// A stored recording as listed by `/api/recordings`
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub id: String,
+    #[serde(flatten)]
+    pub info: RecordingInfo,
+    pub bytes: u64,
+}
+
+/// Every recording in `dir`, newest first. Files that aren't readable recordings are skipped.
+pub fn list_recordings(dir: impl AsRef<Path>) -> io::Result<Vec<RecordingSummary>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut recordings = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(RECORDING_EXTENSION) {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+            continue;
+        };
+        let Ok(info) = File::open(&path).and_then(|mut file| read_header(&mut file)) else {
+            continue;
+        };
+        let bytes = fs::metadata(&path)?.len();
+        recordings.push(RecordingSummary { id, info, bytes });
+    }
+    recordings.sort_by_key(|recording| Reverse(recording.info.started_at));
+    Ok(recordings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::websocket_messages::{ClientEnvelope, ClientMessage, RequestId, ServerEnvelope, ServerMessage};
+
+    fn info() -> RecordingInfo {
+        RecordingInfo {
+            session: 7,
+            user: Some("ada".to_string()),
+            room: "default".to_string(),
+            started_at: DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap().with_timezone(&Utc),
+        }
+    }
+
+    #[test]
+    fn test_recording_roundtrip() {
+        let mut data = Vec::new();
+        write_header(&mut data, &info()).unwrap();
+        write_frame(&mut data, 0, Direction::Received, Frame::Text(r#"{"type":"getInitialData"}"#)).unwrap();
+        write_frame(&mut data, 16, Direction::Sent, Frame::Binary(&[1, 2, 3])).unwrap();
+
+        let recording = RecordingReader::new(data.as_slice()).unwrap();
+        assert_eq!(recording.info, info());
+        assert_eq!(recording.info.recording_id(), "20240501T120000Z-7");
+        let frames: Vec<_> = recording.collect::<io::Result<_>>().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1], RecordedFrame {
+            at_ms: 16,
+            direction: Direction::Sent,
+            payload: Payload::Binary(vec![1, 2, 3]),
+        });
+        let mut recording = RecordingReader::new(data.as_slice()).unwrap();
+        assert_eq!(recording.next_sent().unwrap(), Some(frames[1].clone()));
+        assert_eq!(recording.next_sent().unwrap(), None);
+
+        // A record cut off mid-write is dropped along with nothing before it
+        data.truncate(data.len() - 2);
+        assert_eq!(RecordingReader::new(data.as_slice()).unwrap().count(), 1);
+        assert!(RecordingReader::new(&b"GIF89a"[..]).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_lengths_are_rejected() {
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.push(FORMAT_VERSION);
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        let error = RecordingReader::new(header.as_slice()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut data = Vec::new();
+        write_header(&mut data, &info()).unwrap();
+        data.push(FLAG_BINARY);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        let error = RecordingReader::new(data.as_slice()).unwrap().next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_recorded_frames_parse_as_protocol_messages() {
+        // How a recording serves as a protocol fixture: every text frame must still parse
+        let mut data = Vec::new();
+        write_header(&mut data, &info()).unwrap();
+        let request = r#"{"id":1,"type":"search","query":"rust"}"#;
+        let response = serde_json::to_string(&ServerEnvelope {
+            id: Some(RequestId::Number(1)),
+            message: ServerMessage::Completed { request: "search".to_string() },
+        }).unwrap();
+        write_frame(&mut data, 0, Direction::Received, Frame::Text(request)).unwrap();
+        write_frame(&mut data, 5, Direction::Sent, Frame::Text(&response)).unwrap();
+
+        for frame in RecordingReader::new(data.as_slice()).unwrap() {
+            let frame = frame.unwrap();
+            let Payload::Text(text) = frame.payload else { continue };
+            match frame.direction {
+                Direction::Received => {
+                    let envelope: ClientEnvelope = serde_json::from_str(&text).unwrap();
+                    assert!(matches!(envelope.message, ClientMessage::Search { .. }));
+                },
+                Direction::Sent => {
+                    serde_json::from_str::<ServerEnvelope>(&text).unwrap();
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_recording_ids() {
+        assert!(validate_recording_id("20240501T120000Z-7").is_ok());
+        assert!(validate_recording_id("../etc/passwd").is_err());
+        assert!(validate_recording_id("").is_err());
+    }
+}
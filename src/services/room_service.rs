@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+use crate::config::Settings;
+use crate::models::graph::GraphData;
+use crate::services::analytics_service::AnalyticsCache;
+use crate::services::cluster_service::ClusterCache;
+use crate::services::import_service::GraphSource;
+use crate::services::node_lock_service::NodeLocks;
+use crate::services::presence_service::PresenceRegistry;
+use crate::services::tour_service::TourPlayer;
+use crate::utils::gpu_compute::GPUCompute;
+use crate::utils::websocket_manager::SessionId;
+
+pub type RoomId = String;
+
+/// Room sessions join when `/ws` names none. It serves the vault, or the graph
+/// uploaded to `/api/graph/import`, and can't be removed.
+pub const DEFAULT_ROOM: &str = "default";
+/// Longest room id accepted, in characters
+pub const MAX_ROOM_ID_LENGTH: usize = 64;
+
+/// A graph with its own simulation and participants. Sessions in a room see and
+/// edit only its graph, and presence, locks and tours stay within it.
+pub struct Room {
+    pub id: RoomId,
+    pub name: String,
+    /// Where the graph is loaded from; an empty room has none
+    pub graph_source: Arc<RwLock<Option<Arc<dyn GraphSource>>>>,
+    pub graph_data: Arc<RwLock<GraphData>>,
+    pub gpu_compute: Option<Arc<RwLock<GPUCompute>>>,
+    /// Settings overriding the server's in this room, shaped like `settings.toml`
+    pub settings: Value,
+    pub analytics: Arc<AnalyticsCache>,
+    pub clusters: Arc<ClusterCache>,
+    pub presence: PresenceRegistry,
+    pub locks: NodeLocks,
+    /// Tour being presented to the room's sessions
+    pub tour_player: TourPlayer,
+    /// Sessions in remote simulation mode; the room's simulation runs while there are any
+    pub remote_sessions: Mutex<HashSet<SessionId>>,
+    /// RAGFlow conversation the room's chat goes to, created on first use
+    pub conversation_id: Arc<Mutex<Option<String>>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Room {
+    /// An empty room without a graph source or simulation
+    pub fn new(id: RoomId, name: String, settings: Value) -> Self {
+        Self {
+            id,
+            name,
+            graph_source: Arc::new(RwLock::new(None)),
+            graph_data: Arc::new(RwLock::new(GraphData::default())),
+            gpu_compute: None,
+            settings,
+            analytics: Arc::new(AnalyticsCache::new()),
+            clusters: Arc::new(ClusterCache::new()),
+            presence: PresenceRegistry::new(),
+            locks: NodeLocks::new(),
+            tour_player: TourPlayer::new(),
+            remote_sessions: Mutex::new(HashSet::new()),
+            conversation_id: Arc::new(Mutex::new(None)),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_ROOM
+    }
+
+    /// Only the default room shows the vault. The vault's search index and history
+    /// stay out of other rooms, which serve their own graphs.
+    pub fn serves_vault(&self) -> bool {
+        self.is_default()
+    }
+
+    /// Record whether `session` wants the server to run the simulation
+    pub fn set_remote_simulation(&self, session: SessionId, remote: bool) {
+        let mut sessions = self.remote_sessions.lock().unwrap();
+        match remote {
+            true => sessions.insert(session),
+            false => sessions.remove(&session),
+        };
+    }
+
+    /// Whether the room's simulation should be stepped
+    pub fn is_simulating(&self) -> bool {
+        self.gpu_compute.is_some() && !self.remote_sessions.lock().unwrap().is_empty()
+    }
+
+    /// The server settings with this room's overrides applied. The overlay was
+    /// validated when the room was created, so this only falls back on `base` if
+    /// `base` itself changed shape.
+    pub fn effective_settings(&self, base: &Settings) -> Settings {
+        apply_overlay(base, &self.settings).unwrap_or_else(|_| base.clone())
+    }
+}
+
+/// Merge a partial settings object over `base`, key by key.
+pub fn apply_overlay(base: &Settings, overlay: &Value) -> Result<Settings, String> {
+    if overlay.is_null() {
+        return Ok(base.clone());
+    }
+    if !overlay.is_object() {
+        return Err("Room settings must be an object".to_string());
+    }
+    let mut merged = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    merge(&mut merged, overlay);
+    serde_json::from_value(merged).map_err(|e| format!("Invalid room settings: {}", e))
+}
+
+fn merge(target: &mut Value, overlay: &Value) {
+    match (target, overlay) {
+        (Value::Object(target), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match target.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        },
+        (target, overlay) => *target = overlay.clone(),
+    }
+}
+
+/// Room ids appear in URLs, so they are limited to lowercase letters, digits, `-` and `_`.
+pub fn validate_room_id(id: &str) -> Result<(), String> {
+    let valid_chars = id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if id.is_empty() || id.len() > MAX_ROOM_ID_LENGTH || !valid_chars {
+        return Err(format!(
+            "Room id must be 1 to {} lowercase letters, digits, '-' or '_'", MAX_ROOM_ID_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+/// A room id derived from a display name, e.g. `Team Vault` becomes `team-vault`
+pub fn room_id_from_name(name: &str) -> String {
+    let mut id = String::new();
+    for c in name.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            id.push(c);
+        } else if !id.is_empty() && !id.ends_with('-') {
+            id.push('-');
+        }
+    }
+    id.trim_end_matches('-').chars().take(MAX_ROOM_ID_LENGTH).collect()
+}
+
+/// Every open room, by id.
+pub struct RoomRegistry {
+    rooms: Mutex<BTreeMap<RoomId, Arc<Room>>>,
+}
+
+impl RoomRegistry {
+    /// A registry holding just the default room
+    pub fn new(default_room: Room) -> Self {
+        let mut rooms = BTreeMap::new();
+        rooms.insert(default_room.id.clone(), Arc::new(default_room));
+        Self { rooms: Mutex::new(rooms) }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Room>> {
+        self.rooms.lock().unwrap().get(id).cloned()
+    }
+
+    pub fn default_room(&self) -> Arc<Room> {
+        self.get(DEFAULT_ROOM).expect("the default room is never removed")
+    }
+
+    /// Adds a room. Fails if its id is taken.
+    pub fn insert(&self, room: Room) -> Result<Arc<Room>, String> {
+        let mut rooms = self.rooms.lock().unwrap();
+        if rooms.contains_key(&room.id) {
+            return Err(format!("Room {} already exists", room.id));
+        }
+        let room = Arc::new(room);
+        rooms.insert(room.id.clone(), room.clone());
+        Ok(room)
+    }
+
+    /// Removes a room. The default room can't be removed.
+    pub fn remove(&self, id: &str) -> Result<Arc<Room>, String> {
+        if id == DEFAULT_ROOM {
+            return Err("The default room can't be removed".to_string());
+        }
+        self.rooms.lock().unwrap().remove(id).ok_or_else(|| format!("Unknown room: {}", id))
+    }
+
+    pub fn all(&self) -> Vec<Arc<Room>> {
+        self.rooms.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_room_ids() {
+        assert!(validate_room_id("team-vault_2").is_ok());
+        assert!(validate_room_id("").is_err());
+        assert!(validate_room_id("Team").is_err());
+        assert!(validate_room_id("a/b").is_err());
+        assert!(validate_room_id(&"a".repeat(MAX_ROOM_ID_LENGTH + 1)).is_err());
+        assert_eq!(room_id_from_name("  Team Vault: Q3! "), "team-vault-q3");
+    }
+
+    #[test]
+    fn test_overlay_merges_nested_objects() {
+        let mut base = json!({ "a": { "x": 1, "y": 2 }, "b": [1, 2] });
+        merge(&mut base, &json!({ "a": { "y": 3 }, "b": [4], "c": true }));
+        assert_eq!(base, json!({ "a": { "x": 1, "y": 3 }, "b": [4], "c": true }));
+    }
+
+    #[test]
+    fn test_registry_keeps_the_default_room() {
+        let rooms = RoomRegistry::new(Room::new(DEFAULT_ROOM.to_string(), "Default".to_string(), Value::Null));
+        assert!(rooms.insert(Room::new("team".to_string(), "Team".to_string(), Value::Null)).is_ok());
+        assert!(rooms.insert(Room::new("team".to_string(), "Team".to_string(), Value::Null)).is_err());
+        assert_eq!(rooms.all().len(), 2);
+
+        assert!(rooms.remove(DEFAULT_ROOM).is_err());
+        assert!(rooms.remove("team").is_ok());
+        assert!(rooms.remove("team").is_err());
+        assert!(rooms.default_room().is_default());
+    }
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::{HashMap, HashSet};
+
+use crate::models::metadata::{Metadata, PropertyValue};
+use crate::services::content_store::StoredFile;
+
+// Standard BM25 parameters
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+const SNIPPET_RADIUS: usize = 60;
+pub const DEFAULT_RESULT_LIMIT: usize = 20;
+
+/// A node matching a search query.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchHit {
+    pub id: String,
+    pub score: f32,
+    pub snippet: String,
+}
+
+struct IndexedDocument {
+    version: String,
+    length: usize,
+    /// Distinct terms, so removal only touches this document's postings
+    terms: Vec<String>,
+    content: String,
+}
+
+/// In-memory BM25 inverted index over page content and metadata, keyed by node id.
+#[derive(Default)]
+pub struct SearchIndex {
+    postings: HashMap<String, HashMap<String, u32>>,
+    documents: HashMap<String, IndexedDocument>,
+    total_length: usize,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bring the index in line with the stored files, re-indexing only pages
+    /// whose version changed and dropping pages that are gone.
+    pub fn sync(&mut self, files: &[StoredFile]) {
+        let current: HashSet<String> = files.iter().map(|f| node_id(&f.file_name)).collect();
+        let stale: Vec<String> = self.documents.keys()
+            .filter(|id| !current.contains(*id))
+            .cloned()
+            .collect();
+        for id in stale {
+            self.remove(&id);
+        }
+
+        for file in files {
+            let id = node_id(&file.file_name);
+            if self.documents.get(&id).is_some_and(|doc| doc.version == file.version) {
+                continue;
+            }
+            self.upsert(&id, &file.version, &file.content, &file.metadata);
+        }
+    }
+
+    fn upsert(&mut self, id: &str, version: &str, content: &str, metadata: &Metadata) {
+        self.remove(id);
+
+        let mut text = metadata_text(metadata);
+        text.push('\n');
+        text.push_str(content);
+
+        let terms = tokenize(&text);
+        let mut frequencies: HashMap<String, u32> = HashMap::new();
+        for term in &terms {
+            *frequencies.entry(term.clone()).or_insert(0) += 1;
+        }
+        let distinct: Vec<String> = frequencies.keys().cloned().collect();
+        for (term, tf) in frequencies {
+            self.postings.entry(term).or_default().insert(id.to_string(), tf);
+        }
+
+        self.total_length += terms.len();
+        self.documents.insert(id.to_string(), IndexedDocument {
+            version: version.to_string(),
+            length: terms.len(),
+            terms: distinct,
+            content: content.to_string(),
+        });
+    }
+
+    fn remove(&mut self, id: &str) {
+        let Some(doc) = self.documents.remove(id) else {
+            return;
+        };
+        self.total_length -= doc.length;
+        for term in &doc.terms {
+            if let Some(docs) = self.postings.get_mut(term) {
+                docs.remove(id);
+                if docs.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+    }
+
+    /// Rank documents against `query` with BM25, best first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let n = self.documents.len() as f32;
+        let avg_length = self.total_length as f32 / n;
+        let mut scores: HashMap<&str, f32> = HashMap::new();
+
+        for term in &terms {
+            let Some(docs) = self.postings.get(term) else {
+                continue;
+            };
+            let df = docs.len() as f32;
+            let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for (id, &tf) in docs {
+                let length = self.documents[id].length as f32;
+                let tf = tf as f32;
+                let score = idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * length / avg_length));
+                *scores.entry(id.as_str()).or_insert(0.0) += score;
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = scores.into_iter()
+            .map(|(id, score)| SearchHit {
+                id: id.to_string(),
+                score,
+                snippet: snippet(&self.documents[id].content, &terms),
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+fn node_id(file_name: &str) -> String {
+    file_name.trim_end_matches(".md").to_string()
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Title, aliases, tags and textual properties, so pages are found by what they declare.
+fn metadata_text(metadata: &Metadata) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(metadata.title.clone());
+    parts.extend(metadata.aliases.iter().cloned());
+    parts.extend(metadata.tags.iter().cloned());
+    for value in metadata.properties.values() {
+        match value {
+            PropertyValue::Text(text) => parts.push(text.clone()),
+            PropertyValue::List(items) => parts.extend(items.iter().cloned()),
+            _ => {}
+        }
+    }
+    parts.join(" ")
+}
+
+/// Text around the first occurrence of a query term, or the start of the page.
+fn snippet(content: &str, terms: &[String]) -> String {
+    let lower = content.to_lowercase();
+    // Lowercasing can change byte lengths; only trust positions when it didn't
+    let position = if lower.len() == content.len() {
+        terms.iter().filter_map(|term| lower.find(term.as_str())).min()
+    } else {
+        None
+    };
+
+    let center = position.unwrap_or(0);
+    let mut start = center.saturating_sub(SNIPPET_RADIUS);
+    let mut end = (center + SNIPPET_RADIUS).min(content.len());
+    while !content.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !content.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut snippet = content[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < content.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(file_name: &str, version: &str, content: &str) -> StoredFile {
+        StoredFile {
+            file_name: file_name.to_string(),
+            version: version.to_string(),
+            content: content.to_string(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_ranks_by_bm25() {
+        let mut index = SearchIndex::new();
+        index.sync(&[
+            stored("Rust.md", "1", "Rust ownership and borrowing. Rust lifetimes."),
+            stored("WebXR.md", "1", "WebXR sessions render a graph, written in Rust."),
+            stored("Cooking.md", "1", "Bread needs flour and water."),
+        ]);
+
+        let hits = index.search("rust", 10);
+        assert_eq!(hits.iter().map(|h| h.id.as_str()).collect::<Vec<_>>(), vec!["Rust", "WebXR"]);
+        assert!(hits[0].score > hits[1].score);
+        assert!(hits[1].snippet.contains("Rust"));
+    }
+
+    #[test]
+    fn test_sync_reindexes_changed_and_drops_removed() {
+        let mut index = SearchIndex::new();
+        index.sync(&[stored("a.md", "1", "alpha"), stored("b.md", "1", "beta")]);
+        index.sync(&[stored("a.md", "2", "gamma")]);
+
+        assert!(index.search("alpha", 10).is_empty());
+        assert!(index.search("beta", 10).is_empty());
+        assert_eq!(index.search("gamma", 10)[0].id, "a");
+    }
+}
@@ -10,6 +10,7 @@ use log::{info, error, debug};
 use futures::{SinkExt, StreamExt};
 use std::error::Error;
 use crate::utils::websocket_manager::WebSocketManager;
+use crate::utils::websocket_messages::ServerMessage;
 use tokio::net::TcpStream;
 use url::Url;
 use actix_web::{web, Error as ActixError, HttpRequest, HttpResponse};
@@ -161,19 +162,11 @@ impl SpeechService {
                                                                 for item in content {
                                                                     if item["type"] == "audio" {
                                                                         if let Some(audio_data) = item["audio"].as_str() {
-                                                                            // Decode base64 audio data using the new API
-                                                                            if let Ok(audio_bytes) = BASE64.decode(audio_data) {
-                                                                                // Create a JSON wrapper for the binary data
-                                                                                let audio_message = json!({
-                                                                                    "type": "audio",
-                                                                                    "data": audio_bytes
-                                                                                });
-                                                                                
-                                                                                if let Err(e) = websocket_manager.broadcast_message(
-                                                                                    &serde_json::to_string(&audio_message).unwrap()
-                                                                                ).await {
-                                                                                    error!("Failed to broadcast audio: {}", e);
-                                                                                }
+                                                                            let audio_message = ServerMessage::AudioData {
+                                                                                audio_data: audio_data.to_string()
+                                                                            };
+                                                                            if let Err(e) = websocket_manager.broadcast_message(audio_message).await {
+                                                                                error!("Failed to broadcast audio: {}", e);
                                                                             }
                                                                         }
                                                                     }
@@ -222,15 +215,10 @@ impl SpeechService {
                                 match child.wait_with_output() {
                                     Ok(output) => {
                                         if output.status.success() {
-                                            // Create a JSON wrapper for the binary data
-                                            let audio_message = json!({
-                                                "type": "audio",
-                                                "data": output.stdout
-                                            });
-                                            
-                                            if let Err(e) = websocket_manager.broadcast_message(
-                                                &serde_json::to_string(&audio_message).unwrap()
-                                            ).await {
+                                            let audio_message = ServerMessage::AudioData {
+                                                audio_data: BASE64.encode(&output.stdout)
+                                            };
+                                            if let Err(e) = websocket_manager.broadcast_message(audio_message).await {
                                                 error!("Failed to broadcast audio: {}", e);
                                             }
                                         } else {
@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::services::presence_service::Pose;
+use crate::services::query_service::{GraphQuery, ViewFilter};
+
+pub const TOUR_STORE_PATH: &str = "data/tours.db";
+/// Shortest time a step is shown before advancing on its own
+pub const MIN_STEP_DURATION_MS: u64 = 500;
+
+/// One viewpoint of a tour.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TourStep {
+    /// Where the camera goes; clients keep their camera when omitted
+    pub camera: Option<Pose>,
+    /// Node the step is about, highlighted by clients
+    pub focus_node: Option<String>,
+    /// View shown during the step; no filters shows the whole graph
+    #[serde(default)]
+    pub filters: Vec<ViewFilter>,
+    pub narration: Option<String>,
+    /// Advance to the next step after this long; otherwise the presenter advances
+    pub duration_ms: Option<u64>,
+}
+
+/// A named sequence of viewpoints for presenting the graph.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Tour {
+    /// Taken from the URL when authored over REST
+    #[serde(default)]
+    pub name: String,
+    pub description: Option<String>,
+    /// Speak each step's narration through the speech service
+    #[serde(default)]
+    pub speak: bool,
+    pub steps: Vec<TourStep>,
+}
+
+impl Tour {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Tour name is empty".to_string());
+        }
+        if self.steps.is_empty() {
+            return Err("Tour has no steps".to_string());
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            if step.camera.is_some_and(|camera| !camera.is_finite()) {
+                return Err(format!("Step {}: camera pose contains a non-finite value", index));
+            }
+            for filter in &step.filters {
+                if let ViewFilter::Query { query } = filter {
+                    GraphQuery::parse(query).map_err(|e| format!("Step {}: {}", index, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tours authored over REST, kept in SQLite next to the content store.
+pub struct TourStore {
+    conn: Mutex<Connection>,
+}
+
+impl TourStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        if let Some(parent) = path.as_ref().parent() {
+            // Let SQLite report the error if the directory really can't be created
+            let _ = std::fs::create_dir_all(parent);
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, rusqlite::Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tours (
+                 name       TEXT PRIMARY KEY,
+                 tour       TEXT NOT NULL,
+                 updated_at TEXT NOT NULL
+             );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Create or replace a tour
+    pub fn put(&self, tour: &Tour) -> Result<(), rusqlite::Error> {
+        let json = serde_json::to_string(tour).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO tours (name, tour, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (name) DO UPDATE SET tour = ?2, updated_at = ?3",
+            params![tour.name, json, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<Tour>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT tour FROM tours WHERE name = ?1", params![name], row_to_tour)
+            .optional()
+    }
+
+    /// Every tour by name, with when it was last changed
+    pub fn all(&self) -> Result<Vec<(Tour, DateTime<Utc>)>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT tour, updated_at FROM tours ORDER BY name")?;
+        let tours = stmt.query_map([], |row| {
+            let updated_at: String = row.get(1)?;
+            let updated_at = DateTime::parse_from_rfc3339(&updated_at)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?;
+            Ok((row_to_tour(row)?, updated_at))
+        })?;
+        tours.collect()
+    }
+
+    /// Returns false if there was no such tour
+    pub fn delete(&self, name: &str) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.execute("DELETE FROM tours WHERE name = ?1", params![name])? > 0)
+    }
+}
+
+fn row_to_tour(row: &rusqlite::Row) -> Result<Tour, rusqlite::Error> {
+    let tour: String = row.get(0)?;
+    serde_json::from_str(&tour)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// The step every session is shown.
+#[derive(Debug, Clone)]
+pub struct TourPosition {
+    pub tour: String,
+    pub index: usize,
+    pub step_count: usize,
+    pub step: TourStep,
+    pub speak: bool,
+    /// Changes with every step, so a timer set for an earlier step can tell it's stale
+    pub generation: u64,
+}
+
+struct Playback {
+    tour: Tour,
+    index: usize,
+}
+
+#[derive(Default)]
+struct PlayerState {
+    playback: Option<Playback>,
+    generation: u64,
+}
+
+/// The tour being presented. There is at most one, shown to every session at once.
+#[derive(Default)]
+pub struct TourPlayer {
+    state: Mutex<PlayerState>,
+}
+
+impl TourPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts `tour` at its first step, replacing any tour being presented
+    pub fn start(&self, tour: Tour) -> TourPosition {
+        let mut state = self.state.lock().unwrap();
+        state.playback = Some(Playback { tour, index: 0 });
+        state.generation += 1;
+        position(&state.playback, state.generation).unwrap()
+    }
+
+    /// Moves `by` steps. Returns `None`, ending the tour, when that leaves the tour
+    /// forward; moving back from the first step stays on it.
+    pub fn step(&self, by: isize) -> Result<Option<TourPosition>, String> {
+        let mut state = self.state.lock().unwrap();
+        let playback = state.playback.as_mut().ok_or_else(|| "No tour is being presented".to_string())?;
+        let index = playback.index.saturating_add_signed(by);
+        if index >= playback.tour.steps.len() {
+            state.playback = None;
+            state.generation += 1;
+            return Ok(None);
+        }
+        playback.index = index;
+        state.generation += 1;
+        Ok(position(&state.playback, state.generation))
+    }
+
+    /// Ends the tour, returning its name
+    pub fn stop(&self) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.playback.take().map(|playback| playback.tour.name)
+    }
+
+    pub fn current(&self) -> Option<TourPosition> {
+        let state = self.state.lock().unwrap();
+        position(&state.playback, state.generation)
+    }
+
+    /// Name of the tour being presented
+    pub fn tour_name(&self) -> Option<String> {
+        self.state.lock().unwrap().playback.as_ref().map(|playback| playback.tour.name.clone())
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.state.lock().unwrap().generation == generation
+    }
+}
+
+fn position(playback: &Option<Playback>, generation: u64) -> Option<TourPosition> {
+    playback.as_ref().map(|playback| TourPosition {
+        tour: playback.tour.name.clone(),
+        index: playback.index,
+        step_count: playback.tour.steps.len(),
+        step: playback.tour.steps[playback.index].clone(),
+        speak: playback.tour.speak,
+        generation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tour(name: &str, steps: usize) -> Tour {
+        Tour {
+            name: name.to_string(),
+            description: None,
+            speak: false,
+            steps: (0..steps).map(|i| TourStep {
+                camera: None,
+                focus_node: Some(format!("node{}", i)),
+                filters: vec![],
+                narration: Some(format!("Step {}", i)),
+                duration_ms: None,
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let store = TourStore::init(Connection::open_in_memory().unwrap()).unwrap();
+        store.put(&tour("intro", 2)).unwrap();
+        store.put(&tour("intro", 3)).unwrap();
+        store.put(&tour("deep-dive", 1)).unwrap();
+
+        assert_eq!(store.get("intro").unwrap().unwrap().steps.len(), 3);
+        let names: Vec<String> = store.all().unwrap().into_iter().map(|(tour, _)| tour.name).collect();
+        assert_eq!(names, vec!["deep-dive", "intro"]);
+        assert!(store.delete("intro").unwrap());
+        assert!(!store.delete("intro").unwrap());
+        assert!(store.get("intro").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(tour("intro", 1).validate().is_ok());
+        assert!(tour("intro", 0).validate().is_err());
+        assert!(tour(" ", 1).validate().is_err());
+
+        let mut bad_query = tour("intro", 1);
+        bad_query.steps[0].filters.push(ViewFilter::Query { query: "tag:".to_string() });
+        assert!(bad_query.validate().is_err());
+    }
+
+    #[test]
+    fn test_player_steps_through_and_ends() {
+        let player = TourPlayer::new();
+        assert!(player.step(1).is_err());
+
+        let first = player.start(tour("intro", 2));
+        assert_eq!((first.index, first.step_count), (0, 2));
+        assert_eq!(player.step(-1).unwrap().unwrap().index, 0);
+
+        let second = player.step(1).unwrap().unwrap();
+        assert_eq!(second.step.focus_node.as_deref(), Some("node1"));
+        assert!(!player.is_current(first.generation));
+        assert!(player.is_current(second.generation));
+
+        assert!(player.step(1).unwrap().is_none());
+        assert!(player.current().is_none());
+        assert_eq!(player.stop(), None);
+    }
+}
@@ -1,25 +1,61 @@
 use miniz_oxide::deflate::compress_to_vec;
-use miniz_oxide::inflate::decompress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
 use serde_json;
 use std::io;
 use log::{debug, error};
 
 const COMPRESSION_MAGIC: &[u8] = b"COMP";
+/// Header of compressed binary frames, so they can't be mistaken for compressed JSON
+const BINARY_COMPRESSION_MAGIC: &[u8] = b"COMB";
 const COMPRESSION_LEVEL: u8 = 6; // Balance between compression ratio and speed
+/// Refuse to inflate frames beyond this, so a small frame can't exhaust memory
+const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
 
 pub fn compress_message(message: &str) -> Result<Vec<u8>, serde_json::Error> {
     debug!("Compressing message of length: {}", message.len());
-    
-    let mut compressed = Vec::with_capacity(COMPRESSION_MAGIC.len() + message.len());
-    compressed.extend_from_slice(COMPRESSION_MAGIC);
-    compressed.extend_from_slice(&compress_to_vec(message.as_bytes(), COMPRESSION_LEVEL));
-    
-    debug!("Compressed size: {} bytes", compressed.len());
-    Ok(compressed)
+    Ok(compress_with(COMPRESSION_MAGIC, message.as_bytes()))
 }
 
 pub fn decompress_message(compressed: &[u8]) -> Result<String, io::Error> {
-    if compressed.len() < COMPRESSION_MAGIC.len() {
+    let decompressed = decompress_with(COMPRESSION_MAGIC, compressed)?;
+    String::from_utf8(decompressed)
+        .map_err(|e| {
+            error!("Invalid UTF-8 in decompressed data: {}", e);
+            io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8")
+        })
+}
+
+/// Compress a binary frame, e.g. node positions
+pub fn compress_binary(data: &[u8]) -> Vec<u8> {
+    debug!("Compressing binary frame of length: {}", data.len());
+    compress_with(BINARY_COMPRESSION_MAGIC, data)
+}
+
+pub fn decompress_binary(compressed: &[u8]) -> Result<Vec<u8>, io::Error> {
+    decompress_with(BINARY_COMPRESSION_MAGIC, compressed)
+}
+
+/// Whether a binary frame holds a compressed JSON message
+pub fn is_compressed_message(data: &[u8]) -> bool {
+    data.starts_with(COMPRESSION_MAGIC)
+}
+
+/// Whether a binary frame holds compressed binary data
+pub fn is_compressed_binary(data: &[u8]) -> bool {
+    data.starts_with(BINARY_COMPRESSION_MAGIC)
+}
+
+fn compress_with(magic: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::with_capacity(magic.len() + data.len());
+    compressed.extend_from_slice(magic);
+    compressed.extend_from_slice(&compress_to_vec(data, COMPRESSION_LEVEL));
+
+    debug!("Compressed size: {} bytes", compressed.len());
+    compressed
+}
+
+fn decompress_with(magic: &[u8], compressed: &[u8]) -> Result<Vec<u8>, io::Error> {
+    if compressed.len() < magic.len() {
         error!("Compressed data too short: {} bytes", compressed.len());
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -27,7 +63,7 @@ pub fn decompress_message(compressed: &[u8]) -> Result<String, io::Error> {
         ));
     }
 
-    if &compressed[..COMPRESSION_MAGIC.len()] != COMPRESSION_MAGIC {
+    if &compressed[..magic.len()] != magic {
         error!("Invalid compression header");
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -35,16 +71,10 @@ pub fn decompress_message(compressed: &[u8]) -> Result<String, io::Error> {
         ));
     }
 
-    let decompressed = decompress_to_vec(&compressed[COMPRESSION_MAGIC.len()..])
+    decompress_to_vec_with_limit(&compressed[magic.len()..], MAX_DECOMPRESSED_SIZE)
         .map_err(|e| {
             error!("Decompression failed: {:?}", e);
             io::Error::new(io::ErrorKind::InvalidData, "Failed to decompress data")
-        })?;
-    
-    String::from_utf8(decompressed)
-        .map_err(|e| {
-            error!("Invalid UTF-8 in decompressed data: {}", e);
-            io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8")
         })
 }
 
@@ -82,6 +112,18 @@ mod tests {
         assert_eq!(empty, decompressed);
     }
 
+    #[test]
+    fn test_binary_roundtrip() {
+        let positions: Vec<u8> = (0..240u8).collect();
+        let compressed = compress_binary(&positions);
+        assert!(is_compressed_binary(&compressed));
+        assert!(!is_compressed_message(&compressed));
+        assert_eq!(decompress_binary(&compressed).unwrap(), positions);
+        // Compressed text is not accepted as binary data, nor the other way round
+        assert!(decompress_binary(&compress_message("{}").unwrap()).is_err());
+        assert!(decompress_message(&compressed).is_err());
+    }
+
     #[test]
     fn test_large_message() {
         let large_message = "A".repeat(1000000);
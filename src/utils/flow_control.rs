@@ -0,0 +1,231 @@
+use std::time::{Duration, Instant};
+
+use crate::config::WebSocketSettings;
+
+/// How often throughput is measured, the rate adapted and reported to the client
+pub const FLOW_WINDOW: Duration = Duration::from_secs(1);
+/// Frames arrive on the simulation's tick, so one due slightly after a tick is sent on it
+const SEND_TOLERANCE: Duration = Duration::from_millis(4);
+/// Frames per second the rate recovers by in each window the client kept up
+const FPS_RECOVERY_STEP: f32 = 5.0;
+/// When the client falls behind, the rate drops to this share of what it acknowledged
+const FPS_BACKOFF: f32 = 0.9;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlowLimits {
+    /// Frames sent but not yet acknowledged before newer frames wait instead
+    pub max_outstanding: u64,
+    pub min_fps: f32,
+    pub max_fps: f32,
+}
+
+impl From<&WebSocketSettings> for FlowLimits {
+    fn from(settings: &WebSocketSettings) -> Self {
+        let min_fps = settings.min_fps.max(1.0);
+        Self {
+            max_outstanding: settings.max_outstanding_frames.max(1),
+            min_fps,
+            max_fps: settings.max_fps.max(min_fps),
+        }
+    }
+}
+
+/// Throughput over the last window, as reported to the client
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRate {
+    /// Frames per second the client acknowledged
+    pub fps: f32,
+    /// Frames per second the session is allowed to send
+    pub target_fps: f32,
+    /// Frames replaced by newer ones before they could be sent
+    pub dropped: u64,
+}
+
+/// Paces position frames to a client that acknowledges them. At most
+/// `max_outstanding` frames are in flight; while the client is behind, only the
+/// latest frame waits, replacing older ones. The frame rate backs off to what the
+/// client acknowledges when it falls behind and recovers while it keeps up.
+pub struct FlowControl<T> {
+    limits: FlowLimits,
+    target_fps: f32,
+    /// Frames sent and acknowledged since flow control started
+    sent: u64,
+    acked: u64,
+    /// Latest frame not sent yet
+    pending: Option<T>,
+    last_sent: Option<Instant>,
+    window_start: Instant,
+    window_acked: u64,
+    window_dropped: u64,
+    /// Whether a frame had to wait for acknowledgements this window
+    window_stalled: bool,
+    window_offered: bool,
+}
+
+impl<T> FlowControl<T> {
+    pub fn new(limits: FlowLimits, now: Instant) -> Self {
+        Self {
+            limits,
+            target_fps: limits.max_fps,
+            sent: 0,
+            acked: 0,
+            pending: None,
+            last_sent: None,
+            window_start: now,
+            window_acked: 0,
+            window_dropped: 0,
+            window_stalled: false,
+            window_offered: false,
+        }
+    }
+
+    pub fn outstanding(&self) -> u64 {
+        self.sent - self.acked
+    }
+
+    /// Queues the latest frame, replacing any frame still waiting. Returns the frame
+    /// to send now, if the client can take one.
+    pub fn offer(&mut self, frame: T, now: Instant) -> Option<T> {
+        self.window_offered = true;
+        if self.pending.replace(frame).is_some() {
+            self.window_dropped += 1;
+        }
+        self.poll(now)
+    }
+
+    /// The client has received `count` frames in all. Returns the waiting frame if it
+    /// can be sent now.
+    pub fn acknowledge(&mut self, count: u64, now: Instant) -> Option<T> {
+        // Acks can't cover frames never sent, nor go backwards
+        self.acked = count.clamp(self.acked, self.sent);
+        self.poll(now)
+    }
+
+    /// Returns the waiting frame if it can be sent now.
+    pub fn poll(&mut self, now: Instant) -> Option<T> {
+        self.pending.as_ref()?;
+        if self.outstanding() >= self.limits.max_outstanding {
+            self.window_stalled = true;
+            return None;
+        }
+        if self.pacing_delay(now).is_some() {
+            return None;
+        }
+        self.sent += 1;
+        self.last_sent = Some(now);
+        self.pending.take()
+    }
+
+    /// How long until the waiting frame can be sent, when only the frame rate holds it
+    /// back. A frame waiting for acknowledgements goes out on the next one instead.
+    pub fn retry_in(&self, now: Instant) -> Option<Duration> {
+        self.pending.as_ref()?;
+        if self.outstanding() >= self.limits.max_outstanding {
+            return None;
+        }
+        self.pacing_delay(now)
+    }
+
+    fn pacing_delay(&self, now: Instant) -> Option<Duration> {
+        let interval = Duration::from_secs_f32(1.0 / self.target_fps);
+        let elapsed = now.duration_since(self.last_sent?) + SEND_TOLERANCE;
+        interval.checked_sub(elapsed).filter(|delay| !delay.is_zero())
+    }
+
+    /// Ends the current window: measures what the client acknowledged and adapts the
+    /// rate. Returns nothing if no frames were offered during the window.
+    pub fn measure(&mut self, now: Instant) -> Option<FrameRate> {
+        let elapsed = now.duration_since(self.window_start).as_secs_f32();
+        let fps = match elapsed > 0.0 {
+            true => (self.acked - self.window_acked) as f32 / elapsed,
+            false => 0.0,
+        };
+        let FlowLimits { min_fps, max_fps, .. } = self.limits;
+        self.target_fps = match self.window_stalled {
+            true => (fps * FPS_BACKOFF).clamp(min_fps, max_fps),
+            false => (self.target_fps + FPS_RECOVERY_STEP).min(max_fps),
+        };
+        let rate = self.window_offered.then_some(FrameRate { fps, target_fps: self.target_fps, dropped: self.window_dropped });
+
+        self.window_start = now;
+        self.window_acked = self.acked;
+        self.window_dropped = 0;
+        self.window_stalled = false;
+        self.window_offered = false;
+        rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> FlowLimits {
+        FlowLimits { max_outstanding: 2, min_fps: 5.0, max_fps: 60.0 }
+    }
+
+    #[test]
+    fn test_frames_are_coalesced_while_the_client_is_behind() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(16);
+        let mut flow = FlowControl::new(limits(), start);
+
+        assert_eq!(flow.offer(1, start), Some(1));
+        assert_eq!(flow.offer(2, start + tick), Some(2));
+        // Two frames in flight: later ones wait, and only the latest is kept
+        assert_eq!(flow.offer(3, start + tick * 2), None);
+        assert_eq!(flow.offer(4, start + tick * 3), None);
+        assert_eq!(flow.outstanding(), 2);
+
+        assert_eq!(flow.acknowledge(1, start + tick * 4), Some(4));
+        assert_eq!(flow.acknowledge(1, start + tick * 5), None);
+        // An ack beyond what was sent is capped
+        assert_eq!(flow.acknowledge(10, start + tick * 6), None);
+        assert_eq!(flow.outstanding(), 0);
+
+        let rate = flow.measure(start + Duration::from_secs(1)).unwrap();
+        assert_eq!(rate.dropped, 1);
+        assert!((rate.fps - 3.0).abs() < 0.01);
+        // It stalled, so the rate backs off to the floor
+        assert_eq!(rate.target_fps, 5.0);
+    }
+
+    #[test]
+    fn test_rate_recovers_while_the_client_keeps_up() {
+        let start = Instant::now();
+        let mut flow = FlowControl::new(limits(), start);
+        flow.target_fps = 20.0;
+
+        // At 20 fps a frame offered 16 ms after the last one waits
+        assert_eq!(flow.offer(1, start), Some(1));
+        assert_eq!(flow.offer(2, start + Duration::from_millis(16)), None);
+        assert_eq!(flow.offer(3, start + Duration::from_millis(50)), Some(3));
+        flow.acknowledge(2, start + Duration::from_millis(60));
+
+        let rate = flow.measure(start + Duration::from_secs(1)).unwrap();
+        assert_eq!(rate.target_fps, 25.0);
+        assert_eq!(rate.dropped, 1);
+        assert!(flow.measure(start + Duration::from_secs(2)).is_none());
+    }
+
+    #[test]
+    fn test_frame_held_by_the_rate_is_due_after_the_interval() {
+        let start = Instant::now();
+        let mut flow = FlowControl::new(limits(), start);
+        flow.target_fps = 20.0;
+
+        assert_eq!(flow.offer(1, start), Some(1));
+        assert_eq!(flow.retry_in(start), None);
+        assert_eq!(flow.offer(2, start + Duration::from_millis(16)), None);
+        // The last frame must not wait for another offer or ack
+        let delay = flow.retry_in(start + Duration::from_millis(16)).unwrap();
+        assert!((delay.as_secs_f32() - 0.03).abs() < 0.0001);
+        assert_eq!(flow.poll(start + Duration::from_millis(16) + delay), Some(2));
+        assert_eq!(flow.retry_in(start + Duration::from_millis(50)), None);
+
+        // One waiting for acknowledgements is sent on the next ack instead
+        assert_eq!(flow.offer(3, start + Duration::from_millis(100)), None);
+        assert_eq!(flow.retry_in(start + Duration::from_millis(100)), None);
+        assert_eq!(flow.acknowledge(1, start + Duration::from_millis(110)), Some(3));
+    }
+}
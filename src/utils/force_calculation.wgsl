@@ -9,7 +9,11 @@ struct Edge {
     source: u32,      // 4 bytes
     target_idx: u32,  // 4 bytes
     weight: f32,      // 4 bytes
-    padding1: u32,    // 4 bytes
+    kind: u32,        // 4 bytes (EdgeKind discriminant)
+    flags: u32,       // 4 bytes (bit 0: directed)
+    padding3: u32,    // 4 bytes
+    padding4: u32,    // 4 bytes
+    padding5: u32,    // 4 bytes - Total: 32 bytes, matches GPUEdge
 }
 
 struct NodesBuffer {
@@ -43,11 +47,36 @@ const MAX_VELOCITY: f32 = 10.0;  // Matches CPU implementation
 const NATURAL_LENGTH: f32 = 30.0;  // Reduced to match initial distribution
 const REPULSION_SCALE: f32 = 10000.0;  // Matches CPU implementation
 
+// Edge kinds, must match EdgeKind in models/edge.rs
+const EDGE_KIND_WIKILINK: u32 = 0u;
+const EDGE_KIND_TAG: u32 = 1u;
+const EDGE_KIND_EMBED: u32 = 2u;
+const EDGE_KIND_BLOCK_REF: u32 = 3u;
+const EDGE_KIND_MENTION: u32 = 4u;
+const EDGE_KIND_SEMANTIC: u32 = 5u;
+const EDGE_KIND_AI_SUGGESTED: u32 = 6u;
+const EDGE_KIND_CONTAINS: u32 = 7u;
+
 @group(0) @binding(0) var<storage, read_write> nodes_buffer: NodesBuffer;
 @group(0) @binding(1) var<storage, read> edges_buffer: EdgesBuffer;
 @group(0) @binding(2) var<uniform> params: SimulationParams;
 
 // Utility functions
+// Per-kind multiplier on the spring constant: blocks stay close to their page,
+// explicit links pull hard, inferred relations (mentions, similarity,
+// suggestions) only loosely
+fn edge_kind_spring(kind: u32) -> f32 {
+    switch kind {
+        case EDGE_KIND_CONTAINS: { return 2.0; }
+        case EDGE_KIND_EMBED: { return 1.5; }
+        case EDGE_KIND_WIKILINK, EDGE_KIND_BLOCK_REF: { return 1.0; }
+        case EDGE_KIND_TAG: { return 0.6; }
+        case EDGE_KIND_SEMANTIC: { return 0.4; }
+        case EDGE_KIND_MENTION, EDGE_KIND_AI_SUGGESTED: { return 0.3; }
+        default: { return 1.0; }
+    }
+}
+
 fn is_valid_float(x: f32) -> bool {
     return x == x && abs(x) < 1e10;
 }
@@ -174,11 +203,19 @@ fn compute_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let n_edges = arrayLength(&edges_buffer.edges);
     for (var i = 0u; i < n_edges; i = i + 1u) {
         let edge = edges_buffer.edges[i];
+        // Springs act on both endpoints; direction only matters for rendering
+        var other_idx: u32;
         if (edge.source == node_id) {
-            let target_node = nodes_buffer.nodes[edge.target_idx];
-            if (is_valid_vec3(target_node.position)) {
-                force += calculate_spring_force(node.position, target_node.position, node.mass, target_node.mass, true, edge.weight);
-            }
+            other_idx = edge.target_idx;
+        } else if (edge.target_idx == node_id) {
+            other_idx = edge.source;
+        } else {
+            continue;
+        }
+        let other_node = nodes_buffer.nodes[other_idx];
+        if (is_valid_vec3(other_node.position)) {
+            let weight = edge.weight * edge_kind_spring(edge.kind);
+            force += calculate_spring_force(node.position, other_node.position, node.mass, other_node.mass, true, weight);
         }
     }
 
@@ -4,7 +4,7 @@ use std::io::Error;
 use log::{debug, info};
 use crate::models::graph::GraphData;
 use crate::models::edge::GPUEdge;
-use crate::models::node::GPUNode;
+use crate::models::node::{GPUNode, GPUNodePositionUpdate};
 use crate::models::simulation_params::SimulationParams;
 use futures::channel::oneshot;
 
@@ -66,10 +66,7 @@ pub struct GPUCompute {
     simulation_params: SimulationParams,
     fisheye_params: FisheyeParams,
     is_initialized: bool,
-    position_update_buffer: Buffer,
     position_staging_buffer: Buffer,
-    position_pipeline: ComputePipeline,
-    position_bind_group: BindGroup,
 }
 
 impl GPUCompute {
@@ -260,16 +257,7 @@ impl GPUCompute {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        // Create dedicated position buffers
-        let position_update_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Position Update Buffer"),
-            size: (MAX_NODES as u64) * 12, // 3 floats per node
-            usage: wgpu::BufferUsages::STORAGE 
-                | wgpu::BufferUsages::COPY_DST 
-                | wgpu::BufferUsages::COPY_SRC,
-            mapped_at_creation: false,
-        });
-
+        // Create position readback buffer
         let position_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Position Staging Buffer"),
             size: (MAX_NODES as u64) * 12,
@@ -277,55 +265,6 @@ impl GPUCompute {
             mapped_at_creation: false,
         });
 
-        // Create position update shader module
-        let position_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Position Update Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("update_positions.wgsl").into()),
-        });
-
-        // Create position bind group layout
-        let position_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Position Update Layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
-
-        // Create position pipeline
-        let position_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Position Update Pipeline"),
-            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Position Pipeline Layout"),
-                bind_group_layouts: &[&position_bind_group_layout],
-                push_constant_ranges: &[],
-            })),
-            module: &position_module,
-            entry_point: Some("update_positions"),
-            cache: None,
-            compilation_options: Default::default(),
-        });
-
-        // Create position bind group
-        let position_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Position Update Bind Group"),
-            layout: &position_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: position_update_buffer.as_entire_binding(),
-                },
-            ],
-        });
-
         // Create bind groups
         let force_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Force Compute Bind Group"),
@@ -380,10 +319,7 @@ impl GPUCompute {
             simulation_params,
             fisheye_params,
             is_initialized: false,
-            position_update_buffer,
             position_staging_buffer,
-            position_pipeline,
-            position_bind_group,
         })
     }
 
@@ -414,60 +350,21 @@ impl GPUCompute {
         Ok(())
     }
 
-    /// Fast path for position updates from client
-    pub async fn update_positions(&mut self, binary_data: &[u8]) -> Result<(), Error> {
-        // Verify data length (12 bytes per node)
-        let expected_size = self.num_nodes as usize * 12;
-        if binary_data.len() != expected_size {
-            return Err(Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Invalid position data length: expected {}, got {}", 
-                    expected_size, binary_data.len())
-            ));
-        }
-
-        // Write directly to position buffer
-        self.queue.write_buffer(
-            &self.position_update_buffer,
-            0,
-            binary_data
-        );
-
-        // Run position validation shader
-        let mut encoder = self.device.create_command_encoder(
-            &wgpu::CommandEncoderDescriptor {
-                label: Some("Position Update Encoder"),
+    /// Overwrites the position and velocity of individual nodes, leaving the rest of
+    /// the layout alone. Updates are `(graph index, state)`; unknown indices are skipped.
+    pub fn merge_positions(&mut self, updates: &[(usize, GPUNodePositionUpdate)]) -> Result<(), Error> {
+        let stride = std::mem::size_of::<GPUNode>() as u64;
+        for (index, update) in updates {
+            if *index >= self.num_nodes as usize {
+                continue;
             }
-        );
-
-        {
-            let mut compute_pass = encoder.begin_compute_pass(
-                &wgpu::ComputePassDescriptor {
-                    label: Some("Position Validation Pass"),
-                    timestamp_writes: None,
-                }
-            );
-
-            compute_pass.set_pipeline(&self.position_pipeline);
-            compute_pass.set_bind_group(0, &self.position_bind_group, &[]);
-            compute_pass.dispatch_workgroups(
-                (self.num_nodes + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE, 
-                1, 
-                1
+            // Position and velocity lead the node, so mass and flags are untouched
+            self.queue.write_buffer(
+                &self.nodes_buffer,
+                *index as u64 * stride,
+                bytemuck::bytes_of(update),
             );
         }
-
-        // Copy validated positions to node buffer
-        encoder.copy_buffer_to_buffer(
-            &self.position_update_buffer,
-            0,
-            &self.nodes_buffer,
-            0,
-            expected_size as u64,
-        );
-
-        self.queue.submit(Some(encoder.finish()));
-
         Ok(())
     }
 
@@ -1,5 +1,10 @@
 pub mod audio_processor;
+pub mod compression;
+pub mod flow_control;
 pub mod gpu_compute;
+pub mod outline;
+pub mod page_properties;
+pub mod reference_parser;
 pub mod websocket_manager;
 pub mod websocket_messages;
 pub mod websocket_openai;
@@ -0,0 +1,167 @@
+use lazy_static::lazy_static;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+
+use crate::utils::page_properties::PageProperties;
+
+lazy_static! {
+    static ref BULLET_LINE: Regex = Regex::new(r"^([ \t]*)[-*+] (.*)$").unwrap();
+    static ref BLOCK_PROPERTY: Regex = Regex::new(r"^\s*([A-Za-z0-9_\-]+)::\s*(.*?)\s*$").unwrap();
+}
+
+/// One block of a page outline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineBlock {
+    /// Logseq block uuid from an `id::` property, if the block has one.
+    pub id: Option<String>,
+    /// Index of the enclosing block, `None` for top-level blocks.
+    pub parent: Option<usize>,
+    pub depth: usize,
+    /// Block text without its property lines.
+    pub content: String,
+}
+
+/// Split a page into its outline blocks.
+///
+/// Logseq style pages are split on bullets and nested by indentation. Pages without
+/// bullets fall back to one top-level block per paragraph, heading or list item.
+pub fn parse_outline(content: &str) -> Vec<OutlineBlock> {
+    let body = &content[PageProperties::parse(content).body_offset..];
+    let blocks = parse_bullets(body);
+    if !blocks.is_empty() {
+        return blocks;
+    }
+
+    split_markdown_blocks(body)
+        .into_iter()
+        .map(|content| OutlineBlock {
+            id: None,
+            parent: None,
+            depth: 0,
+            content,
+        })
+        .collect()
+}
+
+/// Split markdown into the text of its paragraphs, headings and list items.
+pub fn split_markdown_blocks(content: &str) -> Vec<String> {
+    let parser = Parser::new(content);
+    let mut blocks = Vec::new();
+    let mut current_block = String::new();
+
+    for event in parser {
+        match event {
+            Event::Start(Tag::Heading { .. } | Tag::Item)
+            | Event::End(TagEnd::Paragraph | TagEnd::Item | TagEnd::Heading(_))
+                if !current_block.is_empty() => {
+                blocks.push(std::mem::take(&mut current_block));
+            },
+            Event::Text(text) => current_block.push_str(&text),
+            _ => {},
+        }
+    }
+
+    if !current_block.is_empty() {
+        blocks.push(current_block);
+    }
+
+    blocks
+}
+
+fn parse_bullets(body: &str) -> Vec<OutlineBlock> {
+    let mut blocks: Vec<OutlineBlock> = Vec::new();
+    // (depth, block index) of the blocks enclosing the current line
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut in_code = false;
+
+    for line in body.lines() {
+        if !in_code {
+            if let Some(caps) = BULLET_LINE.captures(line) {
+                let depth = indent_depth(&caps[1]);
+                while stack.last().is_some_and(|&(d, _)| d >= depth) {
+                    stack.pop();
+                }
+                blocks.push(OutlineBlock {
+                    id: None,
+                    parent: stack.last().map(|&(_, index)| index),
+                    depth,
+                    content: String::new(),
+                });
+                stack.push((depth, blocks.len() - 1));
+                push_line(blocks.last_mut().unwrap(), &caps[2]);
+                in_code = caps[2].trim_start().starts_with("```");
+                continue;
+            }
+        }
+
+        // Text before the first bullet belongs to the page, not to a block
+        let Some(block) = blocks.last_mut() else {
+            continue;
+        };
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code {
+            if let Some(caps) = BLOCK_PROPERTY.captures(line) {
+                if caps[1].eq_ignore_ascii_case("id") {
+                    block.id = Some(caps[2].to_string());
+                }
+                continue;
+            }
+        }
+        push_line(block, line.trim());
+    }
+
+    blocks
+}
+
+fn push_line(block: &mut OutlineBlock, line: &str) {
+    if line.is_empty() {
+        return;
+    }
+    if !block.content.is_empty() {
+        block.content.push('\n');
+    }
+    block.content.push_str(line);
+}
+
+/// Tabs count as one level, spaces as half a level (Logseq indents with tabs or two spaces).
+fn indent_depth(indent: &str) -> usize {
+    let tabs = indent.chars().filter(|&c| c == '\t').count();
+    let spaces = indent.chars().filter(|&c| c == ' ').count();
+    tabs + spaces / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_outline_with_block_ids() {
+        let content = "title:: Rust\n\n- Ownership\n\t- Borrowing\n\t  id:: 6501a2b4-0000-4000-8000-00000000abcd\n\t  see ((6501a2b4-0000-4000-8000-00000000ffff))\n\t\t- Lifetimes\n- Traits";
+        let blocks = parse_outline(content);
+
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0].parent, None);
+        assert_eq!(blocks[1].parent, Some(0));
+        assert_eq!(blocks[1].id.as_deref(), Some("6501a2b4-0000-4000-8000-00000000abcd"));
+        assert_eq!(blocks[1].content, "Borrowing\nsee ((6501a2b4-0000-4000-8000-00000000ffff))");
+        assert_eq!(blocks[2].parent, Some(1));
+        assert_eq!(blocks[2].depth, 2);
+        assert_eq!(blocks[3].parent, None);
+    }
+
+    #[test]
+    fn test_bullets_inside_code_are_content() {
+        let blocks = parse_outline("- example\n  ```\n  - not a block\n  ```\n- next");
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0].content.contains("- not a block"));
+    }
+
+    #[test]
+    fn test_plain_markdown_falls_back_to_paragraphs() {
+        let blocks = parse_outline("# Heading\n\nFirst paragraph.\n\nSecond paragraph.");
+        let contents: Vec<&str> = blocks.iter().map(|b| b.content.as_str()).collect();
+        assert_eq!(contents, vec!["Heading", "First paragraph.", "Second paragraph."]);
+        assert!(blocks.iter().all(|b| b.parent.is_none()));
+    }
+}
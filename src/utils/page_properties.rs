@@ -0,0 +1,306 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::models::metadata::PropertyValue;
+
+lazy_static! {
+    static ref PROPERTY_LINE: Regex = Regex::new(r"^\s*(?:-\s+)?([A-Za-z0-9_\-]+)::\s*(.*?)\s*$").unwrap();
+    static ref INLINE_TAG: Regex = Regex::new(r"(?:^|\s)#(?:\[\[([^\]]+)\]\]|([\w/\-]+))").unwrap();
+    static ref ORDINAL_SUFFIX: Regex = Regex::new(r"(\d)(?:st|nd|rd|th)\b").unwrap();
+}
+
+/// Properties declared at the top of a markdown page.
+#[derive(Debug, Clone, Default)]
+pub struct PageProperties {
+    /// Logseq `key:: value` properties from the first block, keys lowercased.
+    pub properties: HashMap<String, String>,
+    /// YAML front matter (Obsidian style), converted to JSON values.
+    pub front_matter: HashMap<String, Value>,
+    /// Byte offset where the page body starts, after any front matter.
+    pub body_offset: usize,
+}
+
+impl PageProperties {
+    /// Parse front matter and first-block properties from page content.
+    pub fn parse(content: &str) -> Self {
+        let (front_matter, body_offset) = parse_front_matter(content);
+        let properties = parse_front_block(&content[body_offset..]);
+
+        Self {
+            properties,
+            front_matter,
+            body_offset,
+        }
+    }
+
+    /// Get a Logseq page property by (case-insensitive) key.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(&key.to_lowercase()).map(String::as_str)
+    }
+
+    /// Get a front matter value by key, rendered as a list of strings.
+    pub fn front_matter_values(&self, key: &str) -> Vec<String> {
+        match self.front_matter.get(key) {
+            Some(value) => value_to_strings(value),
+            None => Vec::new(),
+        }
+    }
+
+    /// All tags declared for the page: `tags::` property, front matter `tags`,
+    /// and inline `#tag` / `#[[multi word]]` occurrences in the body.
+    pub fn tags(&self, content: &str) -> Vec<String> {
+        let mut tags = Vec::new();
+
+        if let Some(value) = self.property("tags") {
+            tags.extend(split_property_list(value));
+        }
+        tags.extend(self.front_matter_values("tags"));
+
+        let body = content.get(self.body_offset..).unwrap_or("");
+        for caps in INLINE_TAG.captures_iter(body) {
+            if let Some(tag) = caps.get(1).or_else(|| caps.get(2)) {
+                tags.push(tag.as_str().to_string());
+            }
+        }
+
+        let mut normalized: Vec<String> = tags.iter()
+            .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        normalized.sort();
+        normalized.dedup();
+        normalized
+    }
+
+    /// Page properties and front matter as typed values, keys lowercased.
+    /// Page properties win over front matter keys of the same name.
+    pub fn typed_properties(&self) -> HashMap<String, PropertyValue> {
+        let mut typed: HashMap<String, PropertyValue> = self.front_matter.iter()
+            .filter_map(|(key, value)| json_to_property(value).map(|v| (key.to_lowercase(), v)))
+            .collect();
+        for (key, value) in &self.properties {
+            typed.insert(key.clone(), typed_value(value));
+        }
+        typed
+    }
+
+    /// The first of `keys` that holds a date, in page properties or front matter.
+    pub fn date(&self, keys: &[&str]) -> Option<DateTime<Utc>> {
+        keys.iter().find_map(|key| {
+            self.property(key)
+                .map(str::to_string)
+                .or_else(|| self.front_matter_values(key).into_iter().next())
+                .and_then(|value| parse_date(&value))
+        })
+    }
+
+    /// Page title: `title` property or front matter, else the first heading.
+    pub fn title(&self, content: &str) -> Option<String> {
+        self.property("title")
+            .map(str::to_string)
+            .or_else(|| self.front_matter_values("title").into_iter().next())
+            .or_else(|| first_heading(content.get(self.body_offset..).unwrap_or("")))
+    }
+}
+
+/// Infer the type of a `key:: value` property: booleans, numbers, dates,
+/// `[[page]]`/`#tag` lists, and plain text otherwise.
+pub fn typed_value(value: &str) -> PropertyValue {
+    let value = value.trim();
+    if let Ok(b) = value.parse::<bool>() {
+        return PropertyValue::Bool(b);
+    }
+    if let Ok(n) = value.parse::<f64>() {
+        if n.is_finite() {
+            return PropertyValue::Number(n);
+        }
+    }
+    if let Some(date) = parse_date(value) {
+        return PropertyValue::Date(date);
+    }
+    if value.contains("[[") || value.starts_with('#') {
+        return PropertyValue::List(split_property_list(value));
+    }
+    PropertyValue::Text(value.to_string())
+}
+
+/// Parse the date formats found in notes: RFC 3339, `2024-01-31`, `2024/01/31`,
+/// `2024-01-31 14:00` and Logseq journal links like `[[Jan 31st, 2024]]`.
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim().trim_start_matches("[[").trim_end_matches("]]").trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(date.and_utc());
+        }
+    }
+    let without_ordinal = ORDINAL_SUFFIX.replace(value, "$1");
+    for format in ["%Y-%m-%d", "%Y/%m/%d", "%Y_%m_%d", "%b %d, %Y", "%B %d, %Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(&without_ordinal, format) {
+            return date.and_hms_opt(0, 0, 0).map(|d| d.and_utc());
+        }
+    }
+    None
+}
+
+fn json_to_property(value: &Value) -> Option<PropertyValue> {
+    match value {
+        Value::Null | Value::Object(_) => None,
+        Value::Bool(b) => Some(PropertyValue::Bool(*b)),
+        Value::Number(n) => n.as_f64().map(PropertyValue::Number),
+        Value::String(s) => Some(match parse_date(s) {
+            Some(date) => PropertyValue::Date(date),
+            None => PropertyValue::Text(s.clone()),
+        }),
+        Value::Array(_) => Some(PropertyValue::List(value_to_strings(value))),
+    }
+}
+
+fn first_heading(body: &str) -> Option<String> {
+    let mut heading: Option<String> = None;
+    for event in Parser::new(body) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(h) = heading.as_mut() {
+                    h.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(h) = heading.take().filter(|h| !h.trim().is_empty()) {
+                    return Some(h.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a Logseq property value like `[[a]], b, #c` into plain names.
+pub fn split_property_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|item| item.trim()
+            .trim_start_matches('#')
+            .trim_start_matches("[[")
+            .trim_end_matches("]]")
+            .trim()
+            .to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn value_to_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::Null => Vec::new(),
+        Value::String(s) => split_property_list(s),
+        Value::Array(items) => items.iter().flat_map(value_to_strings).collect(),
+        other => vec![other.to_string()],
+    }
+}
+
+/// Returns the parsed YAML front matter and the offset of the content following it.
+fn parse_front_matter(content: &str) -> (HashMap<String, Value>, usize) {
+    let trimmed = content.trim_start_matches('\u{feff}');
+    let bom_len = content.len() - trimmed.len();
+
+    let first_line_end = match trimmed.find('\n') {
+        Some(idx) => idx,
+        None => return (HashMap::new(), 0),
+    };
+    if trimmed[..first_line_end].trim_end() != "---" {
+        return (HashMap::new(), 0);
+    }
+
+    let yaml_start = first_line_end + 1;
+    let mut offset = yaml_start;
+    for line in trimmed[yaml_start..].split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if line.trim_end() == "---" || line.trim_end() == "..." {
+            let yaml = &trimmed[yaml_start..line_start];
+            let front_matter = serde_yaml::from_str::<HashMap<String, Value>>(yaml).unwrap_or_default();
+            return (front_matter, bom_len + offset);
+        }
+    }
+
+    // Unterminated front matter is treated as regular content
+    (HashMap::new(), 0)
+}
+
+/// Collects `key:: value` lines from the first block of the page.
+fn parse_front_block(body: &str) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+
+    let mut first = true;
+    for line in body.lines().skip_while(|line| line.trim().is_empty()) {
+        if line.trim().is_empty() {
+            break;
+        }
+        // A new top-level bullet ends the first block
+        if !first && line.starts_with("- ") {
+            break;
+        }
+        first = false;
+
+        if let Some(caps) = PROPERTY_LINE.captures(line) {
+            properties.insert(caps[1].to_lowercase(), caps[2].to_string());
+        }
+    }
+
+    properties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logseq_properties_anywhere_in_front_block() {
+        let content = "title:: Rust\nvisibility:: team\npublic:: true\n\n- body:: not a page property";
+        let props = PageProperties::parse(content);
+        assert_eq!(props.property("visibility"), Some("team"));
+        assert_eq!(props.property("Public"), Some("true"));
+        assert_eq!(props.property("body"), None);
+    }
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let content = "---\npublish: true\ntags: [rust, \"graph theory\"]\n---\nalias:: rs\n\nBody #inline and #[[Multi Word]]";
+        let props = PageProperties::parse(content);
+        assert_eq!(props.front_matter_values("publish"), vec!["true".to_string()]);
+        assert_eq!(props.property("alias"), Some("rs"));
+        assert_eq!(
+            props.tags(content),
+            vec!["graph theory", "inline", "multi word", "rust"]
+        );
+    }
+
+    #[test]
+    fn test_typed_properties_dates_and_title() {
+        let content = "---\nrating: 4\ncreated: 2024-01-31\n---\ndraft:: true\nrelated:: [[Rust]], [[WebXR]]\nupdated:: [[Feb 2nd, 2024]]\n\n# Graph *Notes*\n- body";
+        let props = PageProperties::parse(content);
+        let typed = props.typed_properties();
+
+        assert_eq!(typed["rating"], PropertyValue::Number(4.0));
+        assert_eq!(typed["draft"], PropertyValue::Bool(true));
+        assert_eq!(typed["related"], PropertyValue::List(vec!["Rust".to_string(), "WebXR".to_string()]));
+        assert!(typed["created"].matches("2024-01-31"));
+        assert_eq!(props.date(&["updated"]).unwrap().to_rfc3339(), "2024-02-02T00:00:00+00:00");
+        assert_eq!(props.title(content).as_deref(), Some("Graph Notes"));
+    }
+
+    #[test]
+    fn test_unterminated_front_matter_is_content() {
+        let content = "---\npublic:: true\n";
+        let props = PageProperties::parse(content);
+        assert_eq!(props.body_offset, 0);
+        assert!(props.front_matter.is_empty());
+    }
+}
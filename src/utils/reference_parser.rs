@@ -0,0 +1,342 @@
+use lazy_static::lazy_static;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::models::reference::{Reference, ReferenceKind};
+use crate::utils::page_properties::{split_property_list, PageProperties};
+
+lazy_static! {
+    /// Logseq/Obsidian inline reference syntax, tried left to right.
+    static ref INLINE_REFERENCE: Regex = Regex::new(r#"(?x)
+        \{\{\s*embed\s+\[\[(?P<embed_page>[^\]]+)\]\]\s*\}\}
+        | \{\{\s*embed\s+\(\((?P<embed_block>[^)\s]+)\)\)\s*\}\}
+        | !\[\[(?P<obsidian_embed>[^\]]+)\]\]
+        | \#\[\[(?P<tag_page>[^\]]+)\]\]
+        | \[\[(?P<link>[^\]]+)\]\]
+        | \(\((?P<block_ref>[^)\s]+)\)\)
+        | (?:^|[\s(,])\#(?P<tag>[^\s\#,.;:!?()\[\]{}"']+)
+    "#).unwrap();
+    static ref PROPERTY_LINE: Regex = Regex::new(r"^\s*([A-Za-z0-9_\-]+)::\s*(.*?)\s*$").unwrap();
+    static ref BLOCK_ID: Regex = Regex::new(r"(?m)^\s*(?:-\s+)?id::\s*([0-9A-Fa-f\-]{36})\s*$").unwrap();
+}
+
+/// Extracts typed references from Logseq/Obsidian flavoured markdown.
+///
+/// Markdown structure (code, links) comes from `pulldown-cmark`; the Logseq
+/// syntax is matched on the remaining text line by line. Page names are
+/// resolved case-insensitively against the known pages and their aliases.
+pub struct ReferenceParser {
+    /// Lowercased page name or alias -> canonical page name
+    lookup: HashMap<String, String>,
+    /// Page name normalised to lowercase words -> canonical page name, for mentions
+    mention_names: HashMap<String, String>,
+    /// Block uuid -> page containing it
+    block_index: HashMap<String, String>,
+    max_mention_words: usize,
+}
+
+impl ReferenceParser {
+    pub fn new(pages: &[String]) -> Self {
+        let mut parser = Self {
+            lookup: HashMap::new(),
+            mention_names: HashMap::new(),
+            block_index: HashMap::new(),
+            max_mention_words: 0,
+        };
+        for page in pages {
+            parser.add_name(page, page);
+        }
+        parser
+    }
+
+    /// Register aliases (`alias::` properties) as alternative names for pages.
+    pub fn with_aliases(mut self, aliases: &HashMap<String, Vec<String>>) -> Self {
+        for (page, page_aliases) in aliases {
+            for alias in page_aliases {
+                self.add_name(alias, page);
+            }
+        }
+        self
+    }
+
+    /// Register which page each block uuid lives in, for resolving `((uuid))`.
+    pub fn with_block_index(mut self, block_index: HashMap<String, String>) -> Self {
+        self.block_index = block_index;
+        self
+    }
+
+    fn add_name(&mut self, name: &str, page: &str) {
+        self.lookup.insert(name.to_lowercase(), page.to_string());
+        let words = normalize_words(name);
+        if !words.is_empty() {
+            self.max_mention_words = self.max_mention_words.max(words.len());
+            self.mention_names.insert(words.join(" "), page.to_string());
+        }
+    }
+
+    /// Resolve a written page name to its canonical page, or return it as written.
+    pub fn resolve(&self, name: &str) -> String {
+        let name = name.trim();
+        if let Some(page) = self.lookup.get(&name.to_lowercase()) {
+            return page.clone();
+        }
+        if let Some((_, last)) = name.rsplit_once('/') {
+            if let Some(page) = self.lookup.get(&last.to_lowercase()) {
+                return page.clone();
+            }
+        }
+        name.to_string()
+    }
+
+    /// Parse all references in a page.
+    pub fn parse(&self, content: &str) -> Vec<Reference> {
+        let props = PageProperties::parse(content);
+        let mut refs: Vec<Reference> = props.front_matter_values("tags").iter()
+            .map(|tag| Reference::page(ReferenceKind::Tag, self.resolve(tag)))
+            .collect();
+
+        let body = &content[props.body_offset..];
+        let mut line = String::new();
+        let mut in_code_block = false;
+        let mut link: Option<(String, String)> = None;
+
+        for event in Parser::new_ext(body, Options::empty()) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) => {
+                    self.scan_line(&line, &mut refs);
+                    line.clear();
+                    in_code_block = true;
+                }
+                Event::End(TagEnd::CodeBlock) => in_code_block = false,
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    self.scan_line(&line, &mut refs);
+                    line.clear();
+                    link = Some((dest_url.to_string(), String::new()));
+                }
+                Event::End(TagEnd::Link) => {
+                    if let Some((dest, text)) = link.take() {
+                        self.push_markdown_link(&dest, &text, &mut refs);
+                    }
+                }
+                Event::Text(text) if !in_code_block => match link.as_mut() {
+                    Some((_, link_text)) => link_text.push_str(&text),
+                    None => line.push_str(&text),
+                },
+                Event::Text(_) => {}
+                _ => {
+                    // Inline code, breaks and block boundaries all end the current line
+                    self.scan_line(&line, &mut refs);
+                    line.clear();
+                }
+            }
+        }
+        self.scan_line(&line, &mut refs);
+
+        refs
+    }
+
+    fn scan_line(&self, line: &str, refs: &mut Vec<Reference>) {
+        if line.trim().is_empty() {
+            return;
+        }
+
+        if let Some(caps) = PROPERTY_LINE.captures(line) {
+            match caps[1].to_lowercase().as_str() {
+                // Aliases name this page, ids name a block; neither points elsewhere
+                "alias" | "id" => return,
+                "tags" => {
+                    for tag in split_property_list(&caps[2]) {
+                        refs.push(Reference::page(ReferenceKind::Tag, self.resolve(&tag)));
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        let mut last_end = 0;
+        for caps in INLINE_REFERENCE.captures_iter(line) {
+            let whole = caps.get(0).unwrap();
+            self.scan_mentions(&line[last_end..whole.start()], refs);
+            last_end = whole.end();
+
+            if let Some(m) = caps.name("embed_page").or_else(|| caps.name("obsidian_embed")) {
+                let (target, alias) = split_link(m.as_str());
+                refs.push(Reference {
+                    alias,
+                    ..Reference::page(ReferenceKind::Embed, self.resolve(&target))
+                });
+            } else if let Some(m) = caps.name("embed_block") {
+                refs.push(self.block_reference(ReferenceKind::Embed, m.as_str()));
+            } else if let Some(m) = caps.name("tag_page").or_else(|| caps.name("tag")) {
+                refs.push(Reference::page(ReferenceKind::Tag, self.resolve(m.as_str())));
+            } else if let Some(m) = caps.name("link") {
+                let (target, alias) = split_link(m.as_str());
+                refs.push(Reference {
+                    alias,
+                    ..Reference::page(ReferenceKind::Link, self.resolve(&target))
+                });
+            } else if let Some(m) = caps.name("block_ref") {
+                refs.push(self.block_reference(ReferenceKind::BlockRef, m.as_str()));
+            }
+        }
+        self.scan_mentions(&line[last_end..], refs);
+    }
+
+    fn push_markdown_link(&self, dest: &str, text: &str, refs: &mut Vec<Reference>) {
+        let dest = dest.trim();
+        if dest.is_empty() || dest.contains("://") || dest.starts_with('#') || dest.starts_with("mailto:") {
+            // External link: its text may still mention pages
+            self.scan_mentions(text, refs);
+            return;
+        }
+
+        let alias = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+        if let Some(uuid) = dest.strip_prefix("((").and_then(|d| d.strip_suffix("))")) {
+            refs.push(Reference {
+                alias,
+                ..self.block_reference(ReferenceKind::BlockRef, uuid)
+            });
+            return;
+        }
+
+        let target = match dest.strip_prefix("[[").and_then(|d| d.strip_suffix("]]")) {
+            Some(page) => page.to_string(),
+            None => {
+                let decoded = percent_decode(dest.split('#').next().unwrap_or(dest));
+                let file = decoded.rsplit('/').next().unwrap_or(&decoded).to_string();
+                file.strip_suffix(".md").map(str::to_string).unwrap_or(file)
+            }
+        };
+        refs.push(Reference {
+            alias,
+            ..Reference::page(ReferenceKind::Link, self.resolve(&target))
+        });
+    }
+
+    fn block_reference(&self, kind: ReferenceKind, uuid: &str) -> Reference {
+        Reference {
+            kind,
+            target: self.block_index.get(uuid).cloned().unwrap_or_default(),
+            block: Some(uuid.to_string()),
+            alias: None,
+        }
+    }
+
+    /// Longest-match scan of known page names over the words of plain text.
+    fn scan_mentions(&self, text: &str, refs: &mut Vec<Reference>) {
+        if self.max_mention_words == 0 {
+            return;
+        }
+        let words = normalize_words(text);
+        let mut i = 0;
+        while i < words.len() {
+            let mut matched = 1;
+            for n in (1..=self.max_mention_words.min(words.len() - i)).rev() {
+                if let Some(page) = self.mention_names.get(&words[i..i + n].join(" ")) {
+                    refs.push(Reference::page(ReferenceKind::Mention, page.clone()));
+                    matched = n;
+                    break;
+                }
+            }
+            i += matched;
+        }
+    }
+}
+
+/// Block uuids declared with `id::` properties in a page.
+pub fn block_ids(content: &str) -> Vec<String> {
+    BLOCK_ID.captures_iter(content)
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+/// Split `page|alias` and drop any `#heading` fragment from the page part.
+fn split_link(inner: &str) -> (String, Option<String>) {
+    let (page, alias) = match inner.split_once('|') {
+        Some((page, alias)) => (page, Some(alias.trim().to_string())),
+        None => (inner, None),
+    };
+    let page = page.split('#').next().unwrap_or(page);
+    (page.trim().to_string(), alias)
+}
+
+fn normalize_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = (char::from(bytes[i + 1]).to_digit(16), char::from(bytes[i + 2]).to_digit(16));
+            if let (Some(hi), Some(lo)) = hex {
+                out.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser() -> ReferenceParser {
+        let pages = vec!["Rust".to_string(), "Graph Theory".to_string(), "WebXR".to_string()];
+        let aliases = HashMap::from([("Rust".to_string(), vec!["rustlang".to_string()])]);
+        let blocks = HashMap::from([("6571e2f0-1111-2222-3333-444455556666".to_string(), "WebXR".to_string())]);
+        ReferenceParser::new(&pages).with_aliases(&aliases).with_block_index(blocks)
+    }
+
+    fn kinds(refs: &[Reference], kind: ReferenceKind) -> Vec<String> {
+        refs.iter().filter(|r| r.kind == kind).map(|r| r.target.clone()).collect()
+    }
+
+    #[test]
+    fn test_links_tags_and_embeds() {
+        let content = "alias:: [[Oxide]]\n\n- See [[rust]] and [[Graph Theory|graphs]]\n\
+                       - #WebXR #[[Graph Theory]] {{embed [[Rust]]}} ![[webxr]]\n\
+                       - [the book](Graph%20Theory.md) and [site](https://example.com)";
+        let refs = parser().parse(content);
+
+        assert_eq!(kinds(&refs, ReferenceKind::Link), vec!["Rust", "Graph Theory", "Graph Theory"]);
+        assert_eq!(kinds(&refs, ReferenceKind::Tag), vec!["WebXR", "Graph Theory"]);
+        assert_eq!(kinds(&refs, ReferenceKind::Embed), vec!["Rust", "WebXR"]);
+        assert!(kinds(&refs, ReferenceKind::Mention).is_empty());
+        assert!(!refs.iter().any(|r| r.target == "Oxide"));
+
+        let aliased = refs.iter().find(|r| r.alias.as_deref() == Some("graphs")).unwrap();
+        assert_eq!(aliased.target, "Graph Theory");
+    }
+
+    #[test]
+    fn test_block_refs_and_mentions() {
+        let content = "- ((6571e2f0-1111-2222-3333-444455556666)) about graph theory in RustLang\n\
+                       - `Rust` in code is ignored\n\
+                       ```\nRust\n```\n\
+                       tags:: rust, [[webxr]]";
+        let refs = parser().parse(content);
+
+        let block = refs.iter().find(|r| r.kind == ReferenceKind::BlockRef).unwrap();
+        assert_eq!(block.target, "WebXR");
+        assert_eq!(kinds(&refs, ReferenceKind::Mention), vec!["Graph Theory", "Rust"]);
+        assert_eq!(kinds(&refs, ReferenceKind::Tag), vec!["Rust", "WebXR"]);
+    }
+
+    #[test]
+    fn test_block_ids() {
+        let content = "- a block\n  id:: 6571e2f0-1111-2222-3333-444455556666\n- other";
+        assert_eq!(block_ids(content), vec!["6571e2f0-1111-2222-3333-444455556666"]);
+    }
+}
@@ -2,18 +2,47 @@ use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
 use actix::prelude::*;
 use log::{info, error};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::cell::RefCell;
 use std::sync::{Mutex, Arc};
-use serde_json::json;
+use std::time::Instant;
 use actix_web_actors::ws::WebsocketContext;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
 
 use crate::AppState;
 use crate::models::simulation_params::SimulationMode;
 use crate::handlers::{WebSocketSession, WebSocketSessionHandler};
-use crate::utils::websocket_messages::{MessageHandler, SendText, ClientMessage};
+use crate::handlers::tour_handler::TourCommand;
+use crate::handlers::websocket_handlers::{GraphChanged, PositionFrame};
+use crate::services::node_lock_service;
+use crate::services::recording_service::{Direction, Frame, RecordingInfo, SessionRecorder};
+use crate::services::room_service::{Room, DEFAULT_ROOM};
+use crate::utils::compression;
+use crate::utils::flow_control::FlowLimits;
+use crate::utils::websocket_messages::{
+    self, ClientEnvelope, ClientMessage, MessageHandler, ProtocolOptions, SendMessage, ServerMessage,
+};
+
+pub type SessionId = u64;
+
+/// Query parameters of the `/ws` upgrade request
+#[derive(Deserialize)]
+pub struct ConnectParams {
+    /// Name shown for the session in `/api/sessions`
+    pub user: Option<String>,
+    /// Room to join; the default room when omitted
+    pub room: Option<String>,
+}
 
 /// Manages WebSocket sessions and communication.
 pub struct WebSocketManager {
-    pub sessions: Mutex<Vec<Addr<WebSocketSession>>>,
+    /// Connected sessions with the room they joined, registered when their actor
+    /// starts and removed when it stops
+    sessions: Mutex<BTreeMap<SessionId, (String, Addr<WebSocketSession>)>>,
+    next_session_id: AtomicU64,
     pub conversation_id: Arc<Mutex<Option<String>>>,
 }
 
@@ -21,11 +50,114 @@ impl WebSocketManager {
     /// Creates a new WebSocketManager instance.
     pub fn new() -> Self {
         WebSocketManager {
-            sessions: Mutex::new(Vec::new()),
+            sessions: Mutex::new(BTreeMap::new()),
+            next_session_id: AtomicU64::new(1),
             conversation_id: Arc::new(Mutex::new(None)),
         }
     }
 
+    pub fn register(&self, id: SessionId, room: &str, session: Addr<WebSocketSession>) {
+        self.sessions.lock().unwrap().insert(id, (room.to_string(), session));
+        info!("WebSocket session {} connected to room {}", id, room);
+    }
+
+    pub fn deregister(&self, id: SessionId) {
+        if self.sessions.lock().unwrap().remove(&id).is_some() {
+            info!("WebSocket session {} disconnected", id);
+        }
+    }
+
+    /// Addresses of every connected session, by id
+    pub fn sessions(&self) -> Vec<(SessionId, Addr<WebSocketSession>)> {
+        self.sessions.lock().unwrap()
+            .iter()
+            .map(|(id, (_, session))| (*id, session.clone()))
+            .collect()
+    }
+
+    /// Addresses of the sessions in `room`, by id
+    pub fn room_sessions(&self, room: &str) -> Vec<(SessionId, Addr<WebSocketSession>)> {
+        self.sessions.lock().unwrap()
+            .iter()
+            .filter(|(_, (session_room, _))| session_room == room)
+            .map(|(id, (_, session))| (*id, session.clone()))
+            .collect()
+    }
+
+    pub fn session(&self, id: SessionId) -> Option<Addr<WebSocketSession>> {
+        self.sessions.lock().unwrap().get(&id).map(|(_, session)| session.clone())
+    }
+
+    /// Sends a message to one session. Returns false if no such session is connected.
+    pub fn send_to(&self, id: SessionId, message: ServerMessage) -> bool {
+        match self.session(id) {
+            Some(session) => {
+                session.do_send(SendMessage::new(message));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Sends a message to every session in `room` except `except`
+    pub fn broadcast_except(&self, room: &str, except: SessionId, message: ServerMessage) {
+        for (id, session) in self.room_sessions(room) {
+            if id != except {
+                session.do_send(SendMessage::new(message.clone()));
+            }
+        }
+    }
+
+    /// Relays presence changes and dragged nodes in `room` since the last call;
+    /// a session is not sent its own.
+    pub fn relay_changes(&self, room: &Room) {
+        let changed = room.presence.take_changed();
+        let moves = room.locks.take_moves();
+        if changed.is_empty() && moves.is_empty() {
+            return;
+        }
+        for (id, session) in self.room_sessions(&room.id) {
+            let peers: Vec<_> = changed.iter().filter(|peer| peer.session != id).cloned().collect();
+            if !peers.is_empty() {
+                session.do_send(SendMessage::new(ServerMessage::PresenceUpdate { peers }));
+            }
+            let moves: Vec<_> = moves.iter().filter(|node_move| node_move.session != id).cloned().collect();
+            if !moves.is_empty() {
+                session.do_send(SendMessage::new(ServerMessage::NodesMoved { moves }));
+            }
+        }
+    }
+
+    /// Advances the room's simulation by one step and sends the positions to its sessions
+    /// in remote simulation mode; the others lay the graph out themselves
+    pub async fn step_simulation(&self, room: &Room) {
+        let Some(gpu_compute) = &room.gpu_compute else {
+            return;
+        };
+        let mut gpu = gpu_compute.write().await;
+        if let Err(e) = gpu.step() {
+            error!("GPU compute step failed in room {}: {}", room.id, e);
+            return;
+        }
+        match gpu.get_node_positions().await {
+            Ok(nodes) => {
+                let frame = Arc::new(nodes);
+                let remote = room.remote_sessions.lock().unwrap().clone();
+                for (_, session) in self.room_sessions(&room.id).into_iter().filter(|(id, _)| remote.contains(id)) {
+                    session.do_send(PositionFrame(frame.clone()));
+                }
+            },
+            Err(e) => error!("Failed to read positions in room {}: {}", room.id, e),
+        }
+    }
+
+    /// Releases every node `session` held in `room` and tells the others
+    pub fn release_locks(&self, room: &Room, session: SessionId) {
+        for node in room.locks.release_all(session) {
+            self.broadcast_except(&room.id, session, ServerMessage::NodeUnlocked { node });
+        }
+    }
+
     /// Initializes the WebSocketManager with a conversation ID.
     pub async fn initialize(&self, ragflow_service: &crate::services::ragflow_service::RAGFlowService) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let conversation_id = ragflow_service.create_conversation("default_user".to_string()).await?;
@@ -38,34 +170,232 @@ impl WebSocketManager {
     /// Handles incoming WebSocket connection requests.
     pub async fn handle_websocket(req: HttpRequest, stream: web::Payload, state: web::Data<AppState>) -> Result<HttpResponse, Error> {
         info!("New WebSocket connection request");
+        let (compression_threshold, flow_limits, recording_dir) = {
+            let settings = state.settings.read().await;
+            let websocket = &settings.websocket;
+            (
+                websocket.compression_threshold,
+                FlowLimits::from(websocket),
+                websocket.record_sessions.then(|| websocket.recording_dir.clone()),
+            )
+        };
+        let (user, room) = web::Query::<ConnectParams>::from_query(req.query_string())
+            .map(|params| (params.0.user, params.0.room))
+            .unwrap_or_default();
+        let room_id = room.unwrap_or_else(|| DEFAULT_ROOM.to_string());
+        let Some(room) = state.rooms.get(&room_id) else {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "status": "error",
+                "message": format!("Unknown room: {}", room_id)
+            })));
+        };
+        let id = state.websocket_manager.next_session_id.fetch_add(1, Ordering::Relaxed);
+        let connected_at = Utc::now();
+        let recorder = recording_dir.and_then(|dir| {
+            let info = RecordingInfo { session: id, user: user.clone(), room: room.id.clone(), started_at: connected_at };
+            SessionRecorder::create(dir, &info)
+                .map_err(|e| error!("Failed to start recording session {}: {}", id, e))
+                .ok()
+        });
         let session = WebSocketSession {
+            id,
+            user,
+            address: req.connection_info().realip_remote_addr().map(str::to_string),
+            connected_at,
+            hb: Instant::now(),
             state: state.clone(),
             tts_method: "piper".to_string(),
             openai_ws: None,
             simulation_mode: SimulationMode::Remote,
-            conversation_id: Some(state.websocket_manager.conversation_id.clone()),
+            conversation_id: Some(room.conversation_id.clone()),
+            room,
+            view: None,
+            clusters: None,
+            playback: None,
+            protocol: ProtocolOptions::default(),
+            request_id: None,
+            compression_threshold,
+            tour_view: None,
+            recorder: RefCell::new(recorder),
+            flow_limits,
+            flow: None,
+            flow_report: None,
+            flow_retry: None,
         };
         ws::start(session, &req, stream)
     }
 
+    /// Serves the JSON Schema of the WebSocket protocol.
+    pub async fn handle_schema() -> HttpResponse {
+        HttpResponse::Ok().json(websocket_messages::protocol_schema())
+    }
+
     /// Broadcasts a message to all connected WebSocket sessions.
-    pub async fn broadcast_message(&self, message: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let sessions = self.sessions.lock().unwrap().clone();
-        for session in sessions {
-            let msg: SendText = SendText(message.to_string());
-            session.do_send(msg);
+    pub async fn broadcast_message(&self, message: ServerMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for (_, session) in self.sessions() {
+            session.do_send(SendMessage::new(message.clone()));
         }
         Ok(())
     }
 
-    /// Broadcasts graph update to all connected WebSocket sessions.
-    pub async fn broadcast_graph_update(&self, graph_data: &crate::models::graph::GraphData) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let json_data = json!({
-            "type": "graph_update",
-            "graph_data": graph_data
-        });
-        let message = json_data.to_string();
-        self.broadcast_message(&message).await
+    /// Tells every session in `room` the graph changed; each sends its client the graph as its view shows it.
+    pub async fn broadcast_graph_update(&self, room: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for (_, session) in self.room_sessions(room) {
+            session.do_send(GraphChanged);
+        }
+        Ok(())
+    }
+}
+
+impl WebSocketSession {
+    /// Parses a JSON client message and handles it with its request id
+    fn handle_text(&mut self, text: &str, ctx: &mut WebsocketContext<Self>) {
+        self.record(Direction::Received, Frame::Text(text));
+        match serde_json::from_str::<ClientEnvelope>(text) {
+            Ok(ClientEnvelope { id, message }) => {
+                self.request_id = id;
+                self.dispatch(message, ctx);
+                self.request_id = None;
+            },
+            Err(e) => {
+                error!("Failed to parse client message: {}", e);
+                self.send_server_message(ServerMessage::Error {
+                    message: format!("Invalid message format: {}", e),
+                    code: Some("INVALID_MESSAGE".to_string()),
+                }, ctx);
+            }
+        }
+    }
+
+    /// Merges an uploaded position frame into the layout, node by node. Records follow
+    /// this session's visible nodes; nodes held by other sessions keep their position.
+    fn handle_positions(&mut self, data: Vec<u8>, ctx: &mut WebsocketContext<Self>) {
+        let Some(gpu_compute) = self.room.gpu_compute.clone() else {
+            return;
+        };
+        if self.clusters.is_some() || self.playback.is_some() {
+            self.send_server_message(ServerMessage::Error {
+                message: "Positions can't be uploaded in cluster view or during history playback".to_string(),
+                code: Some("POSITION_UPDATE_ERROR".to_string()),
+            }, ctx);
+            return;
+        }
+        let room = self.room.clone();
+        let session = self.id;
+        let visible = self.view.as_ref().map(|view| view.indices.clone());
+        let ctx_addr = ctx.address();
+
+        let fut = async move {
+            let held = room.locks.held_by_others(session);
+            let graph = room.graph_data.read().await;
+            let indices = visible.unwrap_or_else(|| (0..graph.nodes.len()).collect());
+            let updates = node_lock_service::decode_position_frame(&data, &indices).map(|updates| {
+                // A cached view can outlive a rebuild that shrank the graph; skip what no longer exists
+                updates.into_iter()
+                    .filter(|(index, _)| graph.nodes.get(*index).is_some_and(|node| !held.contains_key(&node.id)))
+                    .collect::<Vec<_>>()
+            });
+            drop(graph);
+
+            let result = match updates {
+                Ok(updates) => gpu_compute.write().await.merge_positions(&updates).map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            let response = match result {
+                Ok(()) => ServerMessage::Completed { request: "positions".to_string() },
+                Err(e) => {
+                    error!("Failed to update node positions: {}", e);
+                    ServerMessage::Error {
+                        message: format!("Failed to update node positions: {}", e),
+                        code: Some("POSITION_UPDATE_ERROR".to_string()),
+                    }
+                }
+            };
+            ctx_addr.do_send(SendMessage::new(response));
+        };
+        ctx.spawn(fut.into_actor(self));
+    }
+
+    /// Routes a client message to its handler
+    fn dispatch(&mut self, message: ClientMessage, ctx: &mut WebsocketContext<Self>) {
+        match message {
+            ClientMessage::Hello { version, compression, binary_formats, flow_control } => {
+                WebSocketSessionHandler::handle_hello(self, ctx, version, compression, binary_formats, flow_control);
+            },
+            ClientMessage::ChatMessage { message, use_openai } => {
+                WebSocketSessionHandler::handle_chat_message(self, ctx, message, use_openai);
+            },
+            ClientMessage::SetSimulationMode { mode } => {
+                WebSocketSessionHandler::handle_simulation_mode(self, ctx, &mode);
+            },
+            ClientMessage::RecalculateLayout { params } => {
+                WebSocketSessionHandler::handle_layout(self, ctx, params);
+            },
+            ClientMessage::GetInitialData => {
+                WebSocketSessionHandler::handle_initial_data(self, ctx);
+            },
+            ClientMessage::UpdateFisheyeSettings { enabled, strength, focus_point, radius } => {
+                WebSocketSessionHandler::handle_fisheye_settings(self, ctx, enabled, strength, focus_point, radius);
+            },
+            ClientMessage::Search { query, limit } => {
+                WebSocketSessionHandler::handle_search(self, ctx, query, limit);
+            },
+            ClientMessage::Query { query, subgraph, filter } => {
+                WebSocketSessionHandler::handle_query(self, ctx, query, subgraph, filter);
+            },
+            ClientMessage::SetView { filters } => {
+                WebSocketSessionHandler::handle_set_view(self, ctx, filters);
+            },
+            ClientMessage::SetClusterView { enabled } => {
+                WebSocketSessionHandler::handle_set_cluster_view(self, ctx, enabled);
+            },
+            ClientMessage::ExpandCluster { cluster } => {
+                WebSocketSessionHandler::handle_expand_cluster(self, ctx, cluster, true);
+            },
+            ClientMessage::CollapseCluster { cluster } => {
+                WebSocketSessionHandler::handle_expand_cluster(self, ctx, cluster, false);
+            },
+            ClientMessage::PlayHistory { from, to, interval_ms } => {
+                WebSocketSessionHandler::handle_play_history(self, ctx, from, to, interval_ms);
+            },
+            ClientMessage::StopHistory => {
+                WebSocketSessionHandler::handle_stop_history(self, ctx);
+            },
+            ClientMessage::Presence(update) => {
+                WebSocketSessionHandler::handle_presence(self, ctx, update);
+            },
+            ClientMessage::GrabNode { node } => {
+                WebSocketSessionHandler::handle_grab_node(self, ctx, node);
+            },
+            ClientMessage::MoveNode { node, position } => {
+                WebSocketSessionHandler::handle_move_node(self, ctx, node, position);
+            },
+            ClientMessage::ReleaseNode { node } => {
+                WebSocketSessionHandler::handle_release_node(self, ctx, node);
+            },
+            ClientMessage::StartTour { name } => {
+                WebSocketSessionHandler::handle_tour(self, ctx, TourCommand::Start(name));
+            },
+            ClientMessage::NextTourStep => {
+                WebSocketSessionHandler::handle_tour(self, ctx, TourCommand::Step(1));
+            },
+            ClientMessage::PreviousTourStep => {
+                WebSocketSessionHandler::handle_tour(self, ctx, TourCommand::Step(-1));
+            },
+            ClientMessage::StopTour => {
+                WebSocketSessionHandler::handle_tour(self, ctx, TourCommand::Stop);
+            },
+            ClientMessage::FramesReceived { count } => {
+                WebSocketSessionHandler::handle_frames_received(self, ctx, count);
+            },
+            _ => {
+                error!("Unhandled client message type");
+                self.send_reply(self.request_id.clone(), ServerMessage::Error {
+                    message: "Unhandled message type".to_string(),
+                    code: Some("UNHANDLED_MESSAGE".to_string()),
+                }, ctx);
+            }
+        }
     }
 }
 
@@ -73,73 +403,42 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut WebsocketContext<Self>) {
         match msg {
             Ok(ws::Message::Ping(msg)) => {
-                let ctx: &mut WebsocketContext<WebSocketSession> = ctx;
+                self.hb = Instant::now();
                 ctx.pong(&msg);
             },
-            Ok(ws::Message::Pong(_)) => (),
+            Ok(ws::Message::Pong(_)) => {
+                self.hb = Instant::now();
+            },
             Ok(ws::Message::Text(text)) => {
-                match serde_json::from_str::<ClientMessage>(&text) {
-                    Ok(client_msg) => match client_msg {
-                        ClientMessage::ChatMessage { message, use_openai } => {
-                            WebSocketSessionHandler::handle_chat_message(self, ctx, message, use_openai);
-                        },
-                        ClientMessage::SetSimulationMode { mode } => {
-                            WebSocketSessionHandler::handle_simulation_mode(self, ctx, &mode);
-                        },
-                        ClientMessage::RecalculateLayout { params } => {
-                            WebSocketSessionHandler::handle_layout(self, ctx, params);
-                        },
-                        ClientMessage::GetInitialData => {
-                            WebSocketSessionHandler::handle_initial_data(self, ctx);
-                        },
-                        ClientMessage::UpdateFisheyeSettings { enabled, strength, focus_point, radius } => {
-                            WebSocketSessionHandler::handle_fisheye_settings(self, ctx, enabled, strength, focus_point, radius);
-                        },
-                        _ => {
-                            error!("Unhandled client message type");
-                            let error_message = json!({
-                                "type": "error",
-                                "message": "Unhandled message type"
-                            });
-                            MessageHandler::send_json_response(self, error_message, ctx);
-                        }
-                    },
-                    Err(e) => {
-                        error!("Failed to parse client message: {}", e);
-                        let error_message = json!({
-                            "type": "error",
-                            "message": format!("Invalid message format: {}", e)
-                        });
-                        MessageHandler::send_json_response(self, error_message, ctx);
-                    }
+                self.handle_text(&text, ctx);
+            },
+            // Only clients that negotiated compression send compressed frames; otherwise a
+            // position upload could start with the magic bytes by chance
+            Ok(ws::Message::Binary(bin)) if self.protocol.compression.is_some() && compression::is_compressed_message(&bin) => {
+                match compression::decompress_message(&bin) {
+                    Ok(text) => self.handle_text(&text, ctx),
+                    Err(e) => self.send_server_message(ServerMessage::Error {
+                        message: format!("Failed to decompress message: {}", e),
+                        code: Some("INVALID_MESSAGE".to_string()),
+                    }, ctx),
                 }
             },
             Ok(ws::Message::Binary(bin)) => {
-                if let Some(gpu_compute) = &self.state.gpu_compute {
-                    let gpu = gpu_compute.clone();
-                    let bin_data = bin.to_vec();
-                    let ctx_addr = ctx.address();
-
-                    ctx.spawn(
-                        async move {
-                            let mut gpu = gpu.write().await;
-                            if let Err(e) = gpu.update_positions(&bin_data).await {
-                                error!("Failed to update node positions: {}", e);
-                                let error_message = json!({
-                                    "type": "error",
-                                    "message": format!("Failed to update node positions: {}", e)
-                                });
-                                if let Ok(error_str) = serde_json::to_string(&error_message) {
-                                    let msg: SendText = SendText(error_str);
-                                    ctx_addr.do_send(msg);
-                                }
-                            }
-                            let msg: SendText = SendText("Position update complete".to_string());
-                            ctx_addr.do_send(msg);
+                let bin_data = match self.protocol.compression.is_some() && compression::is_compressed_binary(&bin) {
+                    true => match compression::decompress_binary(&bin) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            self.send_server_message(ServerMessage::Error {
+                                message: format!("Failed to decompress positions: {}", e),
+                                code: Some("POSITION_UPDATE_ERROR".to_string()),
+                            }, ctx);
+                            return;
                         }
-                        .into_actor(self)
-                    );
-                }
+                    },
+                    false => bin.to_vec(),
+                };
+                self.record(Direction::Received, Frame::Binary(&bin_data));
+                self.handle_positions(bin_data, ctx);
             },
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
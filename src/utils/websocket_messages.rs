@@ -1,11 +1,29 @@
 use actix::prelude::*;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use crate::config::{FisheyeSettings, Settings};
+use crate::models::graph::GraphData;
+use crate::models::node::{GPUNode, GPUNodePositionUpdate};
 use crate::models::simulation_params::SimulationParams;
+use crate::services::history_service::CommitFrame;
+use crate::services::node_lock_service::{NodeLock, NodeMove};
+use crate::services::presence_service::{Presence, PresenceUpdate};
+use crate::services::query_service::ViewFilter;
+use crate::services::search_service::SearchHit;
+use crate::services::tour_service::TourStep;
+use crate::utils::websocket_manager::SessionId;
 use actix_web_actors::ws;
 use log::{error, debug};
 use bytestring::ByteString;
 
+/// Version of the protocol described by `ClientMessage` and `ServerMessage`. Clients
+/// announce theirs in `hello` and the server answers with the version both speak.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest client version the server still understands
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
 /// Helper function to convert hex color to proper format
 fn format_color(color: &str) -> String {
     let color = color.trim_matches('"')
@@ -15,15 +33,175 @@ fn format_color(color: &str) -> String {
 }
 
 /// GPU-computed node positions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GPUPositionUpdate {
     pub positions: Vec<[f32; 3]>
 }
 
-/// Message for sending text data
+/// Layout of binary position frames: one record per visible node, in graph order,
+/// as little-endian f32. Position uploads from the client always use `position-velocity-f32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum BinaryFormat {
+    /// x, y, z, vx, vy, vz: 24 bytes per node
+    #[default]
+    PositionVelocityF32,
+    /// x, y, z: 12 bytes per node
+    PositionF32,
+}
+
+impl BinaryFormat {
+    pub const ALL: [BinaryFormat; 2] = [BinaryFormat::PositionVelocityF32, BinaryFormat::PositionF32];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryFormat::PositionVelocityF32 => "position-velocity-f32",
+            BinaryFormat::PositionF32 => "position-f32",
+        }
+    }
+
+    pub fn encode(self, nodes: &[GPUNode]) -> Vec<u8> {
+        match self {
+            BinaryFormat::PositionVelocityF32 => {
+                let mut binary_data = Vec::with_capacity(nodes.len() * std::mem::size_of::<GPUNodePositionUpdate>());
+                for node in nodes {
+                    let update = GPUNodePositionUpdate {
+                        x: node.x,
+                        y: node.y,
+                        z: node.z,
+                        vx: node.vx,
+                        vy: node.vy,
+                        vz: node.vz,
+                    };
+                    binary_data.extend_from_slice(bytemuck::bytes_of(&update));
+                }
+                binary_data
+            },
+            BinaryFormat::PositionF32 => nodes.iter()
+                .flat_map(|node| [node.x, node.y, node.z])
+                .flat_map(f32::to_le_bytes)
+                .collect(),
+        }
+    }
+}
+
+/// How large frames are compressed once negotiated. Frames below the configured
+/// threshold are sent as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compression {
+    /// Raw deflate in a binary frame, headed `COMP` for JSON messages and `COMB` for
+    /// binary frames. Clients may send frames the same way.
+    Deflate,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 1] = [Compression::Deflate];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Deflate => "deflate",
+        }
+    }
+}
+
+/// What a session agreed to in its `hello` handshake. Sessions that skip the
+/// handshake get the current version, no compression and the default binary format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolOptions {
+    pub version: u32,
+    pub compression: Option<Compression>,
+    pub binary_format: BinaryFormat,
+    /// The client acknowledges position frames, so they can be paced to it
+    pub flow_control: bool,
+}
+
+impl Default for ProtocolOptions {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            compression: None,
+            binary_format: BinaryFormat::default(),
+            flow_control: false,
+        }
+    }
+}
+
+impl ProtocolOptions {
+    /// Pick the highest version both sides speak and the first of the client's
+    /// compression schemes and binary formats that the server supports.
+    pub fn negotiate(version: u32, compression: &[String], binary_formats: &[String], flow_control: bool) -> Result<Self, String> {
+        if version < MIN_PROTOCOL_VERSION {
+            return Err(format!(
+                "Protocol version {} is not supported, the server speaks {} to {}",
+                version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ));
+        }
+        let binary_format = binary_formats.iter()
+            .find_map(|name| BinaryFormat::ALL.into_iter().find(|format| format.name() == name))
+            .unwrap_or_default();
+        Ok(Self {
+            version: version.min(PROTOCOL_VERSION),
+            compression: compression.iter()
+                .find_map(|name| Compression::ALL.into_iter().find(|scheme| scheme.name() == name)),
+            binary_format,
+            flow_control,
+        })
+    }
+}
+
+/// Identifies a client request. Every message sent in response carries it back as `id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(u64),
+    Text(String),
+}
+
+/// A client message as sent over the socket, with its optional request id
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ClientEnvelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<RequestId>,
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+/// A server message as sent over the socket, with the id of the request it answers
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ServerEnvelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<RequestId>,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+/// JSON Schema of both directions of the protocol, for generating client types
+pub fn protocol_schema() -> Value {
+    json!({
+        "version": PROTOCOL_VERSION,
+        "client": schemars::schema_for!(ClientEnvelope),
+        "server": schemars::schema_for!(ServerEnvelope),
+    })
+}
+
+/// Message for sending a server message, answering request `id` when set
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct SendText(pub String);
+pub struct SendMessage {
+    pub id: Option<RequestId>,
+    pub message: ServerMessage,
+}
+
+impl SendMessage {
+    pub fn new(message: ServerMessage) -> Self {
+        Self { id: None, message }
+    }
+
+    pub fn reply(id: Option<RequestId>, message: ServerMessage) -> Self {
+        Self { id, message }
+    }
+}
 
 /// Message for sending binary data
 #[derive(Message)]
@@ -46,9 +224,24 @@ pub struct OpenAIConnected;
 pub struct OpenAIConnectionFailed;
 
 /// Represents messages sent from the client.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
+    /// Protocol handshake, answered with the options the server agreed to
+    #[serde(rename = "hello")]
+    Hello {
+        version: u32,
+        /// Compression schemes the client can decode, preferred first
+        #[serde(default)]
+        compression: Vec<String>,
+        /// Binary position formats the client can decode, preferred first
+        #[serde(default)]
+        binary_formats: Vec<String>,
+        /// The client will acknowledge position frames with `framesReceived`
+        #[serde(default)]
+        flow_control: bool,
+    },
+
     #[serde(rename = "setTtsMethod")]
     SetTTSMethod { method: String },
     
@@ -83,13 +276,190 @@ pub enum ClientMessage {
         strength: f32,
         focus_point: [f32; 3],
         radius: f32,
+    },
+
+    #[serde(rename = "search")]
+    Search {
+        query: String,
+        limit: Option<usize>,
+    },
+
+    /// Graph query; `filter` narrows this session's view and position stream to the result
+    #[serde(rename = "query")]
+    Query {
+        query: String,
+        #[serde(default)]
+        subgraph: bool,
+        #[serde(default)]
+        filter: bool,
+    },
+
+    /// Narrow this session to the nodes matching every filter; no filters shows the whole graph
+    #[serde(rename = "setView")]
+    SetView {
+        #[serde(default)]
+        filters: Vec<ViewFilter>,
+    },
+
+    /// Switch between the full graph and cluster meta-nodes with aggregate edges
+    #[serde(rename = "setClusterView")]
+    SetClusterView { enabled: bool },
+
+    #[serde(rename = "expandCluster")]
+    ExpandCluster { cluster: String },
+
+    #[serde(rename = "collapseCluster")]
+    CollapseCluster { cluster: String },
+
+    /// Animate the graph's history; dates default to the start and end of the history
+    #[serde(rename = "playHistory")]
+    PlayHistory {
+        from: Option<String>,
+        to: Option<String>,
+        /// Delay between commits
+        interval_ms: Option<u64>,
+    },
+
+    #[serde(rename = "stopHistory")]
+    StopHistory,
+
+    /// Avatar pose, selection and display name for the other participants. Sent as
+    /// often as the client likes; only failures are answered.
+    #[serde(rename = "presence")]
+    Presence(PresenceUpdate),
+
+    /// Take a node to drag it; others can't move it until it is released
+    #[serde(rename = "grabNode")]
+    GrabNode { node: String },
+
+    /// Drag a grabbed node. Sent as often as the client likes; only failures are answered.
+    #[serde(rename = "moveNode")]
+    MoveNode {
+        node: String,
+        position: [f32; 3],
+    },
+
+    #[serde(rename = "releaseNode")]
+    ReleaseNode { node: String },
+
+    /// Present a stored tour to every session in the room
+    #[serde(rename = "startTour")]
+    StartTour { name: String },
+
+    #[serde(rename = "nextTourStep")]
+    NextTourStep,
+
+    #[serde(rename = "previousTourStep")]
+    PreviousTourStep,
+
+    #[serde(rename = "stopTour")]
+    StopTour,
+
+    /// Position frames received since `hello` enabled flow control, in all
+    #[serde(rename = "framesReceived")]
+    FramesReceived { count: u64 },
+}
+
+/// Rendering settings sent with the initial graph
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct ClientSettings {
+    pub visualization: ClientVisualizationSettings,
+    pub bloom: ClientBloomSettings,
+    pub fisheye: FisheyeSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientVisualizationSettings {
+    pub node_color: String,
+    pub edge_color: String,
+    pub hologram_color: String,
+    pub node_size_scaling_factor: f32,
+    pub hologram_scale: f32,
+    pub hologram_opacity: f32,
+    pub edge_opacity: f32,
+    pub label_font_size: u32,
+    pub fog_density: f32,
+    pub force_directed_iterations: u32,
+    pub force_directed_repulsion: f32,
+    pub force_directed_attraction: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientBloomSettings {
+    pub node_bloom_strength: f32,
+    pub node_bloom_radius: f32,
+    pub node_bloom_threshold: f32,
+    pub edge_bloom_strength: f32,
+    pub edge_bloom_radius: f32,
+    pub edge_bloom_threshold: f32,
+    pub environment_bloom_strength: f32,
+    pub environment_bloom_radius: f32,
+    pub environment_bloom_threshold: f32,
+}
+
+impl From<&Settings> for ClientSettings {
+    fn from(settings: &Settings) -> Self {
+        let visualization = &settings.visualization;
+        let bloom = &settings.bloom;
+        Self {
+            visualization: ClientVisualizationSettings {
+                node_color: format_color(&visualization.node_color),
+                edge_color: format_color(&visualization.edge_color),
+                hologram_color: format_color(&visualization.hologram_color),
+                node_size_scaling_factor: visualization.node_size_scaling_factor,
+                hologram_scale: visualization.hologram_scale,
+                hologram_opacity: visualization.hologram_opacity,
+                edge_opacity: visualization.edge_opacity,
+                label_font_size: visualization.label_font_size,
+                fog_density: visualization.fog_density,
+                force_directed_iterations: visualization.force_directed_iterations,
+                force_directed_repulsion: visualization.force_directed_repulsion,
+                force_directed_attraction: visualization.force_directed_attraction,
+            },
+            bloom: ClientBloomSettings {
+                node_bloom_strength: bloom.node_bloom_strength,
+                node_bloom_radius: bloom.node_bloom_radius,
+                node_bloom_threshold: bloom.node_bloom_threshold,
+                edge_bloom_strength: bloom.edge_bloom_strength,
+                edge_bloom_radius: bloom.edge_bloom_radius,
+                edge_bloom_threshold: bloom.edge_bloom_threshold,
+                environment_bloom_strength: bloom.environment_bloom_strength,
+                environment_bloom_radius: bloom.environment_bloom_radius,
+                environment_bloom_threshold: bloom.environment_bloom_threshold,
+            },
+            fisheye: settings.fisheye.clone(),
+        }
     }
 }
 
 /// Represents messages sent from the server to the client.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "type")]
 pub enum ServerMessage {
+    /// Answer to the client's `hello`
+    #[serde(rename = "hello")]
+    Hello {
+        version: u32,
+        compression: Option<Compression>,
+        binary_format: BinaryFormat,
+        /// Position frames are paced to the client's acknowledgements
+        flow_control: bool,
+    },
+
+    /// A request that has no response of its own was handled; `request` is its type
+    #[serde(rename = "completed")]
+    Completed {
+        request: String
+    },
+
+    #[serde(rename = "getInitialData")]
+    InitialData {
+        graph_data: GraphData,
+        settings: ClientSettings,
+    },
+
     #[serde(rename = "audioData")]
     AudioData {
         audio_data: String // base64 encoded audio
@@ -114,7 +484,7 @@ pub enum ServerMessage {
     
     #[serde(rename = "graphUpdate")]
     GraphUpdate {
-        graph_data: Value
+        graph_data: GraphData
     },
     
     #[serde(rename = "simulationModeSet")]
@@ -132,23 +502,136 @@ pub enum ServerMessage {
     },
 
     #[serde(rename = "gpuPositions")]
-    GPUPositions(GPUPositionUpdate)
+    GPUPositions(GPUPositionUpdate),
+
+    #[serde(rename = "searchResults")]
+    SearchResults {
+        query: String,
+        results: Vec<SearchHit>,
+    },
+
+    #[serde(rename = "queryResult")]
+    QueryResult {
+        query: String,
+        node_ids: Vec<String>,
+        /// Induced subgraph, when requested or when the result became the view filter
+        graph: Option<GraphData>,
+        filter: bool,
+    },
+
+    #[serde(rename = "viewSet")]
+    ViewSet {
+        filters: Vec<ViewFilter>,
+        node_count: usize,
+    },
+
+    #[serde(rename = "clusterView")]
+    ClusterView {
+        enabled: bool,
+        /// Clusters currently shown as their contents rather than as a meta-node
+        expanded: Vec<String>,
+        node_count: usize,
+    },
+
+    /// Start of a history playback. `graph` holds everything that appears during it;
+    /// `nodes` and `edges` are what exists at `from`, before the first frame.
+    #[serde(rename = "historyStart")]
+    HistoryStart {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        commit_count: usize,
+        graph: GraphData,
+        nodes: Vec<String>,
+        edges: Vec<(String, String)>,
+    },
+
+    #[serde(rename = "historyFrame")]
+    HistoryFrame(CommitFrame),
+
+    /// Playback finished or was stopped; the live graph follows as a graphUpdate
+    #[serde(rename = "historyEnd")]
+    HistoryEnd { completed: bool },
+
+    /// Sent on connect: this session's id, everyone already present and the nodes they hold
+    #[serde(rename = "presenceRoster")]
+    PresenceRoster {
+        session: SessionId,
+        peers: Vec<Presence>,
+        locks: Vec<NodeLock>,
+    },
+
+    #[serde(rename = "peerJoined")]
+    PeerJoined(Presence),
+
+    #[serde(rename = "peerLeft")]
+    PeerLeft { session: SessionId },
+
+    /// Peers whose presence changed since the last update
+    #[serde(rename = "presenceUpdate")]
+    PresenceUpdate { peers: Vec<Presence> },
+
+    #[serde(rename = "nodeLocked")]
+    NodeLocked(NodeLock),
+
+    /// Released by its holder, or because the holder disconnected
+    #[serde(rename = "nodeUnlocked")]
+    NodeUnlocked { node: String },
+
+    /// Latest positions of nodes other sessions dragged since the last update
+    #[serde(rename = "nodesMoved")]
+    NodesMoved { moves: Vec<NodeMove> },
+
+    /// The presented tour moved to a step; the step's view follows as a viewSet and graphUpdate
+    #[serde(rename = "tourStep")]
+    TourStep {
+        tour: String,
+        index: usize,
+        step_count: usize,
+        step: TourStep,
+    },
+
+    /// The tour ended, `completed` when it ran past its last step. The view from
+    /// before the tour is restored.
+    #[serde(rename = "tourEnd")]
+    TourEnd {
+        tour: String,
+        completed: bool,
+    },
+
+    /// Position frame throughput over the last second, sent with flow control
+    #[serde(rename = "frameRate")]
+    FrameRate {
+        /// Frames per second the client acknowledged
+        fps: f32,
+        /// Frames per second the server currently sends at most
+        target_fps: f32,
+        /// Frames replaced by newer ones before they were sent
+        dropped: u64,
+    },
 }
 
 pub trait MessageHandler: Actor<Context = ws::WebsocketContext<Self>> {
-    fn send_json_response(&self, response: Value, ctx: &mut ws::WebsocketContext<Self>) {
-        match serde_json::to_string(&response) {
+    /// Write a serialized message to the socket
+    fn write_text(&self, text: String, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.text(ByteString::from(text));
+    }
+
+    /// Send a message answering request `id`, or unsolicited when `id` is `None`
+    fn send_reply(&self, id: Option<RequestId>, message: ServerMessage, ctx: &mut ws::WebsocketContext<Self>) {
+        match serde_json::to_string(&ServerEnvelope { id, message }) {
             Ok(json_string) => {
                 debug!("Sending JSON response: {}", json_string);
-                ctx.text(ByteString::from(json_string));
+                self.write_text(json_string, ctx);
             },
             Err(e) => {
-                error!("Failed to serialize JSON response: {}", e);
-                let error_message = json!({
-                    "type": "error",
-                    "message": format!("Failed to serialize response: {}", e),
-                    "code": "SERIALIZATION_ERROR"
-                });
+                error!("Failed to serialize ServerMessage: {}", e);
+                let error_message = ServerEnvelope {
+                    id: None,
+                    message: ServerMessage::Error {
+                        message: format!("Failed to serialize response: {}", e),
+                        code: Some("SERIALIZATION_ERROR".to_string()),
+                    },
+                };
                 if let Ok(error_string) = serde_json::to_string(&error_message) {
                     ctx.text(ByteString::from(error_string));
                 }
@@ -157,18 +640,7 @@ pub trait MessageHandler: Actor<Context = ws::WebsocketContext<Self>> {
     }
 
     fn send_server_message(&self, message: ServerMessage, ctx: &mut ws::WebsocketContext<Self>) {
-        match serde_json::to_value(message) {
-            Ok(value) => self.send_json_response(value, ctx),
-            Err(e) => {
-                error!("Failed to serialize ServerMessage: {}", e);
-                let error_message = json!({
-                    "type": "error",
-                    "message": "Internal server error",
-                    "code": "SERIALIZATION_ERROR"
-                });
-                self.send_json_response(error_message, ctx);
-            }
-        }
+        self.send_reply(None, message, ctx);
     }
 
     fn handle_fisheye_update(&self, settings: ClientMessage, gpu_compute: &mut crate::utils::gpu_compute::GPUCompute, ctx: &mut ws::WebsocketContext<Self>) {
@@ -250,6 +722,67 @@ mod tests {
         assert!(json.contains("\"radius\":100.0"));
     }
 
+    #[test]
+    fn test_request_ids_are_echoed() {
+        let request: ClientEnvelope = serde_json::from_str(r#"{"id": 7, "type": "getInitialData"}"#).unwrap();
+        assert_eq!(request.id, Some(RequestId::Number(7)));
+        assert!(matches!(request.message, ClientMessage::GetInitialData));
+
+        let request: ClientEnvelope = serde_json::from_str(r#"{"type": "search", "query": "rust", "limit": null}"#).unwrap();
+        assert_eq!(request.id, None);
+        assert!(matches!(request.message, ClientMessage::Search { .. }));
+
+        let reply = ServerEnvelope {
+            id: Some(RequestId::Text("a1".to_string())),
+            message: ServerMessage::Completed { request: "recalculateLayout".to_string() },
+        };
+        let json = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json, json!({"id": "a1", "type": "completed", "request": "recalculateLayout"}));
+
+        let unsolicited = serde_json::to_value(ServerEnvelope { id: None, message: ServerMessage::HistoryEnd { completed: true } }).unwrap();
+        assert_eq!(unsolicited, json!({"type": "historyEnd", "completed": true}));
+    }
+
+    #[test]
+    fn test_hello_negotiation() {
+        let options = ProtocolOptions::negotiate(
+            PROTOCOL_VERSION + 1,
+            &["brotli".to_string(), "deflate".to_string()],
+            &["float16".to_string(), "position-f32".to_string()],
+            true,
+        ).unwrap();
+        assert_eq!(options.version, PROTOCOL_VERSION);
+        assert_eq!(options.compression, Some(Compression::Deflate));
+        assert_eq!(options.binary_format, BinaryFormat::PositionF32);
+
+        assert!(options.flow_control);
+
+        let options = ProtocolOptions::negotiate(PROTOCOL_VERSION, &[], &[], false).unwrap();
+        assert_eq!(options, ProtocolOptions::default());
+
+        assert!(ProtocolOptions::negotiate(MIN_PROTOCOL_VERSION - 1, &[], &[], false).is_err());
+    }
+
+    #[test]
+    fn test_binary_formats() {
+        let node = GPUNode { x: 1.0, y: 2.0, z: 3.0, vx: 4.0, vy: 5.0, vz: 6.0, mass: 1, flags: 0, padding: [0; 2] };
+        assert_eq!(BinaryFormat::PositionVelocityF32.encode(&[node, node]).len(), 48);
+        let compact = BinaryFormat::PositionF32.encode(&[node]);
+        assert_eq!(compact.len(), 12);
+        assert_eq!(f32::from_le_bytes(compact[8..12].try_into().unwrap()), 3.0);
+    }
+
+    #[test]
+    fn test_protocol_schema_covers_every_message() {
+        let schema = protocol_schema().to_string();
+        for message_type in ["hello", "getInitialData", "recalculateLayout", "playHistory", "presence", "grabNode", "startTour", "framesReceived"] {
+            assert!(schema.contains(&format!("\"{}\"", message_type)), "client {}", message_type);
+        }
+        for message_type in ["hello", "completed", "graphUpdate", "historyFrame", "peerJoined", "nodesMoved", "tourStep", "frameRate", "position-f32"] {
+            assert!(schema.contains(&format!("\"{}\"", message_type)), "server {}", message_type);
+        }
+    }
+
     #[test]
     fn test_color_formatting() {
         assert_eq!(format_color("0xFF0000"), "#FF0000");
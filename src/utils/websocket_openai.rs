@@ -15,7 +15,7 @@ use tokio::net::TcpStream;
 use std::time::Instant;
 
 use crate::config::Settings;
-use crate::utils::websocket_messages::{OpenAIMessage, OpenAIConnected, OpenAIConnectionFailed, SendText};
+use crate::utils::websocket_messages::{OpenAIMessage, OpenAIConnected, OpenAIConnectionFailed, SendMessage, ServerMessage};
 use crate::handlers::WebSocketSession;  // Updated import path
 
 const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
@@ -196,15 +196,10 @@ impl OpenAIWebSocket {
         let start_time = Instant::now();
         debug!("Preparing to send audio data to client");
 
-        // Send audio data as JSON
-        let audio_message = json!({
-            "type": "audio",
-            "audio": audio_data
-        });
-
-        // Convert to string and send
-        let message_str = audio_message.to_string();
-        if let Err(e) = self.client_addr.try_send(SendText(message_str)) {
+        let audio_message = ServerMessage::AudioData {
+            audio_data: audio_data.to_string()
+        };
+        if let Err(e) = self.client_addr.try_send(SendMessage::new(audio_message)) {
             error!("Failed to send audio data to client: {}", e);
             return Err(Box::new(WebSocketError::SendFailed(format!(
                 "Failed to send audio data to client: {}", e
@@ -219,14 +214,11 @@ impl OpenAIWebSocket {
     async fn send_error_to_client(&self, error_msg: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
         debug!("Preparing to send error message to client: {}", error_msg);
         
-        let error_message = json!({
-            "type": "error",
-            "message": error_msg
-        });
-
-        // Convert to string and send
-        let message_str = error_message.to_string();
-        if let Err(e) = self.client_addr.try_send(SendText(message_str)) {
+        let error_message = ServerMessage::Error {
+            message: error_msg.to_string(),
+            code: Some("OPENAI_ERROR".to_string()),
+        };
+        if let Err(e) = self.client_addr.try_send(SendMessage::new(error_message)) {
             error!("Failed to send error message to client: {}", e);
             return Err(Box::new(WebSocketError::SendFailed(format!(
                 "Failed to send error message to client: {}", e